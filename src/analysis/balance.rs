@@ -0,0 +1,263 @@
+//! Comprobación del balance energético interno de un archivo de resultados (.res)
+//!
+//! Se comprueba que:
+//! - los flujos netos de cada concepto y elemento son la suma de sus partes positiva y negativa
+//! - los flujos netos por conceptos de cada zona suman el total de la zona
+//! - los flujos de los elementos de cada zona suman los de los conceptos de la envolvente que desglosan
+//! - las demandas mensuales suman las anuales, en las zonas y en el edificio
+//! - la superficie de las zonas, con sus multiplicadores, suma la del edificio
+//!
+//! Las partes positiva y negativa del total de la zona se obtienen a partir del balance horario
+//! conjunto y no son la suma de las de cada concepto, por lo que solo se comparan los flujos netos.
+//! Del mismo modo, los elementos no incluyen los puentes térmicos, y sus partes positiva y
+//! negativa solo coinciden aproximadamente con las de los conceptos.
+
+use super::informe::{Gravedad, Informe};
+use crate::parsers::types::{EdificioLIDER, Flujos, ZonaLIDER};
+
+/// Diferencia absoluta admisible entre un flujo neto y la suma de sus partes [kWh/m²·año]
+const TOLERANCIA_NETO: f32 = 0.01;
+
+/// Diferencia absoluta admisible entre la suma de conceptos y el total de la zona [kWh/m²·año]
+const TOLERANCIA_CONCEPTOS: f32 = 0.25;
+
+/// Diferencia relativa admisible entre la suma de conceptos y el total de la zona [-]
+const TOLERANCIA_CONCEPTOS_REL: f32 = 0.02;
+
+/// Diferencia absoluta admisible entre los flujos de elementos y de conceptos [kWh/m²·año]
+const TOLERANCIA_ELEMENTOS: f32 = 0.05;
+
+/// Diferencia relativa admisible entre los flujos de elementos y de conceptos [-]
+const TOLERANCIA_ELEMENTOS_REL: f32 = 0.01;
+
+/// Diferencia absoluta admisible entre la suma de demandas mensuales y la anual [kWh/m²·año]
+const TOLERANCIA_DEMANDA: f32 = 0.01;
+
+/// Diferencia relativa admisible entre superficies [-]
+const TOLERANCIA_SUPERFICIE: f32 = 0.01;
+
+/// ¿Difieren los valores más de la tolerancia absoluta y de la relativa (respecto a la referencia)?
+fn difieren(valor: f32, referencia: f32, tol: f32, tol_rel: f32) -> bool {
+    let diff = (valor - referencia).abs();
+    diff > tol && diff > tol_rel * referencia.abs()
+}
+
+/// Comprueba el balance energético de los datos de un archivo .res
+pub fn comprobar_balance_res(edificio: &EdificioLIDER) -> Informe {
+    let mut informe = Informe::new("Balance energético del archivo .res");
+
+    // Superficie del edificio
+    let superficie: f32 = edificio
+        .zonas
+        .values()
+        .map(|z| z.superficie * z.multiplicador as f32)
+        .sum();
+    if difieren(
+        superficie,
+        edificio.superficie,
+        f32::EPSILON,
+        TOLERANCIA_SUPERFICIE,
+    ) {
+        informe.push(
+            Gravedad::Error,
+            "Superficie",
+            &edificio.nombre,
+            format!(
+                "La superficie de las zonas ({:.2} m²) no suma la del edificio ({:.2} m²)",
+                superficie, edificio.superficie
+            ),
+        );
+    }
+
+    // Demandas del edificio
+    comprobar_demandas(
+        &mut informe,
+        &edificio.nombre,
+        &edificio.calefaccion_meses,
+        edificio.calefaccion,
+        &edificio.refrigeracion_meses,
+        edificio.refrigeracion,
+    );
+
+    // Zonas, por orden de planta
+    for nombre in edificio.plantas.iter().flat_map(|p| p.zonas.iter()) {
+        if let Some(zona) = edificio.zonas.get(nombre) {
+            comprobar_zona(&mut informe, zona);
+        }
+    }
+    informe
+}
+
+/// Comprueba el balance de los flujos y las demandas de una zona
+fn comprobar_zona(informe: &mut Informe, zona: &ZonaLIDER) {
+    let nombre = zona.nombre.as_str();
+    let c = &zona.conceptos;
+
+    // Flujos netos de conceptos y elementos
+    for (concepto, flujos) in c.to_named_list().iter() {
+        comprobar_netos(informe, &format!("{} ({})", nombre, concepto), flujos);
+    }
+    for elemento in &zona.elementos {
+        comprobar_netos(informe, &elemento.nombre, &elemento.flujos);
+    }
+
+    // Suma de conceptos
+    let suma =
+        c.pext + c.cub + c.suelos + c.pts + c.huecos_solar + c.huecos_trans + c.fint + c.vent;
+    for (periodo, valor, total) in &[
+        ("calefacción", suma.calnet, c.total.calnet),
+        ("refrigeración", suma.refnet, c.total.refnet),
+    ] {
+        if difieren(
+            *valor,
+            *total,
+            TOLERANCIA_CONCEPTOS,
+            TOLERANCIA_CONCEPTOS_REL,
+        ) {
+            informe.push(
+                Gravedad::Error,
+                "Suma de conceptos",
+                nombre,
+                format!(
+                    "El flujo neto de {} por conceptos ({:.2} kWh/m²·año) no suma el total ({:.2} kWh/m²·año)",
+                    periodo, valor, total
+                ),
+            );
+        }
+    }
+
+    // Flujos de elementos frente a los de los conceptos de la envolvente (sin puentes térmicos)
+    if !zona.elementos.is_empty() {
+        let elementos = zona
+            .elementos
+            .iter()
+            .fold(Flujos::default(), |acc, e| acc + e.flujos);
+        let envolvente = c.pext + c.cub + c.suelos + c.huecos_solar + c.huecos_trans;
+        for (periodo, valor, referencia) in &[
+            ("calefacción", elementos.calnet, envolvente.calnet),
+            ("refrigeración", elementos.refnet, envolvente.refnet),
+        ] {
+            if difieren(
+                *valor,
+                *referencia,
+                TOLERANCIA_ELEMENTOS,
+                TOLERANCIA_ELEMENTOS_REL,
+            ) {
+                informe.push(
+                    Gravedad::Aviso,
+                    "Flujos de elementos",
+                    nombre,
+                    format!(
+                        "El flujo neto de {} de los elementos ({:.2} kWh/m²·año) difiere del de los conceptos de la envolvente sin puentes térmicos ({:.2} kWh/m²·año)",
+                        periodo, valor, referencia
+                    ),
+                );
+            }
+        }
+    }
+
+    // Demandas
+    comprobar_demandas(
+        informe,
+        nombre,
+        &zona.calefaccion_meses,
+        zona.calefaccion,
+        &zona.refrigeracion_meses,
+        zona.refrigeracion,
+    );
+}
+
+/// Comprueba que los flujos netos son la suma de sus partes positiva y negativa
+fn comprobar_netos(informe: &mut Informe, objeto: &str, flujos: &Flujos) {
+    for (periodo, pos, neg, net) in &[
+        ("calefacción", flujos.calpos, flujos.calneg, flujos.calnet),
+        ("refrigeración", flujos.refpos, flujos.refneg, flujos.refnet),
+    ] {
+        if difieren(pos + neg, *net, TOLERANCIA_NETO, 0.0) {
+            informe.push(
+                Gravedad::Error,
+                "Flujo neto",
+                objeto,
+                format!(
+                    "El flujo neto de {} ({:.3} kWh/m²·año) no es la suma del positivo ({:.3}) y el negativo ({:.3})",
+                    periodo, net, pos, neg
+                ),
+            );
+        }
+    }
+}
+
+/// Comprueba que las demandas mensuales suman las anuales
+fn comprobar_demandas(
+    informe: &mut Informe,
+    objeto: &str,
+    cal_meses: &[f32],
+    cal: f32,
+    ref_meses: &[f32],
+    refr: f32,
+) {
+    for (tipo, meses, anual) in &[
+        ("calefacción", cal_meses, cal),
+        ("refrigeración", ref_meses, refr),
+    ] {
+        let suma: f32 = meses.iter().sum();
+        if meses.len() != 12 {
+            informe.push(
+                Gravedad::Error,
+                "Demandas mensuales",
+                objeto,
+                format!(
+                    "Número incorrecto de demandas mensuales de {}: {}",
+                    tipo,
+                    meses.len()
+                ),
+            );
+        } else if difieren(suma, *anual, TOLERANCIA_DEMANDA, 0.0) {
+            informe.push(
+                Gravedad::Error,
+                "Demandas mensuales",
+                objeto,
+                format!(
+                    "Las demandas mensuales de {} ({:.2} kWh/m²) no suman la anual ({:.2} kWh/m²·año)",
+                    tipo, suma, anual
+                ),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::testfile;
+
+    #[test]
+    fn balance_res() {
+        for name in &["test.res", "test2019.res"] {
+            let edificio = EdificioLIDER::from_file(testfile(name)).unwrap();
+            let informe = comprobar_balance_res(&edificio);
+            assert!(informe.incidencias.is_empty(), "{}: {}", name, informe);
+        }
+
+        // Datos alterados
+        let mut edificio = EdificioLIDER::from_file(testfile("test.res")).unwrap();
+        edificio.superficie *= 1.1;
+        edificio.calefaccion_meses[0] -= 1.0;
+        let zona = edificio.zonas.get_mut("P01_E01").unwrap();
+        zona.conceptos.fint.refpos += 2.0;
+        zona.conceptos.fint.refnet += 2.0;
+        zona.elementos[0].flujos.calnet += 1.0;
+        let informe = comprobar_balance_res(&edificio);
+        let comprobaciones: Vec<_> = informe.incidencias.iter().map(|i| i.comprobacion).collect();
+        assert_eq!(
+            comprobaciones,
+            &[
+                "Superficie",
+                "Demandas mensuales",
+                "Flujo neto",
+                "Suma de conceptos",
+                "Flujos de elementos"
+            ]
+        );
+    }
+}
@@ -0,0 +1,75 @@
+//! Horas fuera de consigna de las zonas
+//!
+//! Se obtienen a partir de las temperaturas horarias de las zonas en el archivo .bin, contando las
+//! horas en las que la temperatura del local queda por debajo de la consigna baja (calefacción) o
+//! por encima de la consigna alta (refrigeración) en más de una tolerancia.
+
+use super::DIAS_MESES;
+use crate::parsers::bin::ZonaLider;
+
+/// Diferencia de temperatura admisible respecto a la consigna [ºC]
+pub const TOLERANCIA_CONSIGNA: f32 = 0.5;
+
+/// Horas fuera de consigna de una zona, por meses [h]
+#[derive(Debug, Clone, Default)]
+pub struct HorasFueraConsigna {
+    /// Horas por debajo de la consigna baja en cada mes [h]
+    pub calefaccion: [usize; 12],
+    /// Horas por encima de la consigna alta en cada mes [h]
+    pub refrigeracion: [usize; 12],
+}
+
+impl HorasFueraConsigna {
+    /// Horas fuera de consigna de la zona, con la tolerancia indicada [ºC]
+    pub fn from_zona(zona: &ZonaLider, tolerancia: f32) -> Self {
+        let mut horas = Self::default();
+        let mut inicio = 0;
+        for (mes, dias) in DIAS_MESES.iter().enumerate() {
+            let fin = (inicio + dias * 24).min(zona.t_real.len());
+            for hora in inicio.min(fin)..fin {
+                let t = zona.t_real[hora];
+                if zona.t_min.get(hora).map(|t_min| t < t_min - tolerancia) == Some(true) {
+                    horas.calefaccion[mes] += 1;
+                }
+                if zona.t_max.get(hora).map(|t_max| t > t_max + tolerancia) == Some(true) {
+                    horas.refrigeracion[mes] += 1;
+                }
+            }
+            inicio = fin;
+        }
+        horas
+    }
+
+    /// Horas anuales por debajo y por encima de consigna [h]
+    pub fn totales(&self) -> (usize, usize) {
+        (
+            self.calefaccion.iter().sum(),
+            self.refrigeracion.iter().sum(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn horas_fuera_consigna() {
+        let mut zona = ZonaLider {
+            t_real: vec![20.0; 8760],
+            t_min: vec![20.0; 8760],
+            t_max: vec![25.0; 8760],
+            ..Default::default()
+        };
+        // Enero: 2 horas frías (una dentro de la tolerancia); Febrero: 1 hora cálida
+        zona.t_real[0] = 19.0;
+        zona.t_real[1] = 19.6;
+        zona.t_real[2] = 18.0;
+        zona.t_real[31 * 24] = 26.0;
+        let horas = HorasFueraConsigna::from_zona(&zona, TOLERANCIA_CONSIGNA);
+        assert_eq!(horas.calefaccion[0], 2);
+        assert_eq!(horas.refrigeracion[0], 0);
+        assert_eq!(horas.refrigeracion[1], 1);
+        assert_eq!(horas.totales(), (2, 1));
+    }
+}
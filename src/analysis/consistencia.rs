@@ -0,0 +1,216 @@
+//! Comprobación de coherencia entre los archivos de resultados (.res) y de zonas (.bin) de un cálculo
+//!
+//! Se comprueba que ambos archivos contienen las mismas zonas, con la misma superficie y multiplicador,
+//! y que las demandas obtenidas integrando las cargas sensibles horarias del .bin se corresponden
+//! con las demandas mensuales y anuales del .res.
+
+use std::collections::BTreeSet;
+
+use super::informe::{Gravedad, Informe};
+use super::DIAS_MESES;
+use crate::parsers::bin::{BinData, ZonaLider};
+use crate::parsers::types::{EdificioLIDER, ZonaLIDER};
+
+/// Número de horas en un año
+const NHORAS: usize = 8760;
+
+/// Diferencia relativa admisible entre superficies [-]
+const TOLERANCIA_SUPERFICIE: f32 = 0.01;
+
+/// Diferencia absoluta admisible entre demandas mensuales [kWh/m²·mes]
+const TOLERANCIA_DEMANDA_MES: f32 = 0.1;
+
+/// Diferencia relativa admisible entre demandas mensuales [-]
+const TOLERANCIA_DEMANDA_MES_REL: f32 = 0.05;
+
+/// Cociente máximo entre demandas anuales para considerarlas del mismo orden de magnitud [-]
+const MAX_RATIO_DEMANDA_ANUAL: f32 = 2.0;
+
+/// Demandas mensuales de calefacción y refrigeración integrando las cargas sensibles horarias [kWh/m²·mes]
+///
+/// Se usa el mismo convenio de signos que en el archivo .res (calefacción negativa)
+pub fn demandas_mensuales(zona: &ZonaLider) -> (Vec<f32>, Vec<f32>) {
+    let mut cal = Vec::with_capacity(12);
+    let mut refr = Vec::with_capacity(12);
+    let area = if zona.area.abs() < f32::EPSILON {
+        1.0
+    } else {
+        zona.area
+    };
+    let mut inicio = 0;
+    for dias in DIAS_MESES.iter() {
+        let fin = (inicio + dias * 24).min(zona.q_sen.len());
+        let cargas = &zona.q_sen[inicio.min(fin)..fin];
+        cal.push(cargas.iter().map(|q| q.min(0.0)).sum::<f32>() / 1000.0 / area);
+        refr.push(cargas.iter().map(|q| q.max(0.0)).sum::<f32>() / 1000.0 / area);
+        inicio = fin;
+    }
+    (cal, refr)
+}
+
+/// Comprueba la coherencia de los datos de zonas del .res y el .bin de un mismo cálculo
+pub fn comprobar_res_bin(edificio: &EdificioLIDER, bindata: &BinData) -> Informe {
+    let mut informe = Informe::new("Coherencia entre archivos .res y .bin");
+
+    // Conjuntos de zonas
+    let zonas_res: BTreeSet<&String> = edificio.zonas.keys().collect();
+    let zonas_bin: BTreeSet<&String> = bindata.zonas.keys().collect();
+    for zona in zonas_res.difference(&zonas_bin) {
+        informe.push(
+            Gravedad::Error,
+            "Zonas",
+            zona,
+            "La zona del archivo .res no existe en el archivo .bin".to_string(),
+        );
+    }
+    for zona in zonas_bin.difference(&zonas_res) {
+        informe.push(
+            Gravedad::Error,
+            "Zonas",
+            zona,
+            "La zona del archivo .bin no existe en el archivo .res".to_string(),
+        );
+    }
+
+    for nombre in zonas_res.intersection(&zonas_bin) {
+        comprobar_zona(
+            &mut informe,
+            &edificio.zonas[*nombre],
+            &bindata.zonas[*nombre],
+        );
+    }
+    informe
+}
+
+/// Comprueba la coherencia de los datos de una zona del .res y el .bin
+fn comprobar_zona(informe: &mut Informe, zres: &ZonaLIDER, zbin: &ZonaLider) {
+    let nombre = zres.nombre.as_str();
+
+    if (zres.superficie - zbin.area).abs() > TOLERANCIA_SUPERFICIE * zres.superficie.abs() {
+        informe.push(
+            Gravedad::Error,
+            "Superficie",
+            nombre,
+            format!(
+                "Superficie distinta en .res ({:.2} m²) y .bin ({:.2} m²)",
+                zres.superficie, zbin.area
+            ),
+        );
+    }
+
+    if zres.multiplicador != zbin.multiplicador {
+        informe.push(
+            Gravedad::Error,
+            "Multiplicador",
+            nombre,
+            format!(
+                "Multiplicador distinto en .res ({}) y .bin ({})",
+                zres.multiplicador, zbin.multiplicador
+            ),
+        );
+    }
+
+    if zbin.q_sen.len() != NHORAS {
+        informe.push(
+            Gravedad::Error,
+            "Datos horarios",
+            nombre,
+            format!(
+                "Número de horas incorrecto en el .bin: {} (se esperaban {})",
+                zbin.q_sen.len(),
+                NHORAS
+            ),
+        );
+        return;
+    }
+
+    // Demandas mensuales y anuales
+    let (cal_bin, ref_bin) = demandas_mensuales(zbin);
+    for (tipo, res_meses, bin_meses, res_anual) in &[
+        (
+            "calefacción",
+            &zres.calefaccion_meses,
+            &cal_bin,
+            zres.calefaccion,
+        ),
+        (
+            "refrigeración",
+            &zres.refrigeracion_meses,
+            &ref_bin,
+            zres.refrigeracion,
+        ),
+    ] {
+        let bin_anual: f32 = bin_meses.iter().sum();
+        let (a, b) = (res_anual.abs(), bin_anual.abs());
+        if a.max(b) > TOLERANCIA_DEMANDA_MES
+            && (a.min(b) < f32::EPSILON || a.max(b) / a.min(b) > MAX_RATIO_DEMANDA_ANUAL)
+        {
+            informe.push(
+                Gravedad::Error,
+                "Demanda anual",
+                nombre,
+                format!(
+                    "Demanda anual de {} de distinto orden en .res ({:.2} kWh/m²·año) y .bin ({:.2} kWh/m²·año)",
+                    tipo, res_anual, bin_anual
+                ),
+            );
+            continue;
+        }
+        for (mes, (res_mes, bin_mes)) in res_meses.iter().zip(bin_meses.iter()).enumerate() {
+            let diff = (res_mes - bin_mes).abs();
+            if diff > TOLERANCIA_DEMANDA_MES && diff > TOLERANCIA_DEMANDA_MES_REL * res_mes.abs() {
+                informe.push(
+                    Gravedad::Aviso,
+                    "Demanda mensual",
+                    nombre,
+                    format!(
+                        "Demanda de {} del mes {} distinta en .res ({:.2} kWh/m²) y .bin ({:.2} kWh/m²)",
+                        tipo,
+                        mes + 1,
+                        res_mes,
+                        bin_mes
+                    ),
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::testfile;
+
+    #[test]
+    fn coherencia_res_bin() {
+        let bindata = BinData::from_file(testfile("test.bin")).unwrap();
+
+        // Archivos del mismo cálculo
+        let edificio = EdificioLIDER::from_file(testfile("test.res")).unwrap();
+        let informe = comprobar_res_bin(&edificio, &bindata);
+        assert!(informe.incidencias.is_empty(), "{}", informe);
+
+        // Archivos de cálculos distintos
+        let edificio = EdificioLIDER::from_file(testfile("test2019.res")).unwrap();
+        let informe = comprobar_res_bin(&edificio, &bindata);
+        assert!(!informe.is_ok());
+        assert!(informe
+            .incidencias
+            .iter()
+            .any(|i| i.comprobacion == "Zonas"));
+
+        // Zona con superficie, multiplicador y demandas alteradas
+        let mut edificio = EdificioLIDER::from_file(testfile("test.res")).unwrap();
+        let zona = edificio.zonas.get_mut("P01_E01").unwrap();
+        zona.superficie *= 1.5;
+        zona.multiplicador = 2;
+        zona.refrigeracion_meses[6] *= 1.2;
+        let informe = comprobar_res_bin(&edificio, &bindata);
+        let comprobaciones: Vec<_> = informe.incidencias.iter().map(|i| i.comprobacion).collect();
+        assert_eq!(
+            comprobaciones,
+            &["Superficie", "Multiplicador", "Demanda mensual"]
+        );
+        assert_eq!(informe.count(Gravedad::Error), 2);
+    }
+}
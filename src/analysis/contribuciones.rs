@@ -0,0 +1,199 @@
+//! Contribución de los elementos constructivos a los conceptos de demanda
+//!
+//! Los flujos por conceptos de HULC no indican qué elementos los producen. Los elementos se
+//! asignan a los conceptos según su tipo. Los forjados exteriores y los elementos sin clasificar
+//! pueden formar parte de cubiertas, suelos o muros, por lo que se incluyen en todos ellos.
+//!
+//! Los valores se refieren a la superficie total de las zonas consideradas, aplicando los
+//! multiplicadores, para que sean comparables con los flujos por conceptos del objeto.
+
+use crate::graphs::nan_safe_cmp;
+use crate::parsers::types::{EdificioLIDER, Flujos, TipoElemento};
+
+/// Componente de los flujos de calor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Componente {
+    /// Ganancias en temporada de calefacción
+    CalPos,
+    /// Pérdidas en temporada de calefacción
+    CalNeg,
+    /// Demanda neta en temporada de calefacción
+    CalNet,
+    /// Ganancias en temporada de refrigeración
+    RefPos,
+    /// Pérdidas en temporada de refrigeración
+    RefNeg,
+    /// Demanda neta en temporada de refrigeración
+    RefNet,
+}
+
+impl std::str::FromStr for Componente {
+    type Err = crate::utils::Error;
+
+    /// Obtiene el componente a partir del nombre de la serie de las gráficas (p.e. "cal+")
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "cal+" => Ok(Self::CalPos),
+            "cal-" => Ok(Self::CalNeg),
+            "cal" => Ok(Self::CalNet),
+            "ref+" => Ok(Self::RefPos),
+            "ref-" => Ok(Self::RefNeg),
+            "ref" => Ok(Self::RefNet),
+            _ => Err(format!("Componente de flujos desconocido: {}", s).into()),
+        }
+    }
+}
+
+impl Componente {
+    /// Descripción del componente
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::CalPos => "Ganancias en calefacción",
+            Self::CalNeg => "Pérdidas en calefacción",
+            Self::CalNet => "Calefacción neta",
+            Self::RefPos => "Ganancias en refrigeración",
+            Self::RefNeg => "Pérdidas en refrigeración",
+            Self::RefNet => "Refrigeración neta",
+        }
+    }
+
+    /// Valor del componente para unos flujos
+    pub fn valor(self, flujos: &Flujos) -> f32 {
+        match self {
+            Self::CalPos => flujos.calpos,
+            Self::CalNeg => flujos.calneg,
+            Self::CalNet => flujos.calnet,
+            Self::RefPos => flujos.refpos,
+            Self::RefNeg => flujos.refneg,
+            Self::RefNet => flujos.refnet,
+        }
+    }
+}
+
+/// Tipos de elementos que contribuyen a cada concepto de demanda (índice de 0 a 8)
+///
+/// Los puentes térmicos, las fuentes internas y la ventilación no tienen elementos asociados.
+/// Los huecos contribuyen a los conceptos solar (4) y de transmisión (5) con la parte
+/// correspondiente de sus flujos. El total (8) incluye todos los tipos de elementos.
+pub fn tipos_concepto(concepto: usize) -> &'static [TipoElemento] {
+    use TipoElemento::*;
+    match concepto {
+        0 => &[ParedExterior, Medianera, ParedTerreno, Otro],
+        1 => &[Cubierta, ForjadoExterior, Otro],
+        2 => &[SueloTerreno, ForjadoExterior, ForjadoInterior, Otro],
+        4 | 5 => &[Hueco],
+        8 => &TipoElemento::TODOS,
+        _ => &[],
+    }
+}
+
+/// Contribución de un elemento a un concepto de demanda
+#[derive(Debug, Clone)]
+pub struct Contribucion {
+    /// Zona del elemento
+    pub zona: String,
+    /// Nombre del elemento
+    pub elemento: String,
+    /// Tipo de elemento
+    pub tipo: TipoElemento,
+    /// Valor del componente referido a la superficie de las zonas [kWh/m²·año]
+    pub valor: f32,
+}
+
+/// Elementos de las zonas indicadas que contribuyen a un concepto de demanda
+///
+/// Se ordenan de mayor a menor valor absoluto del componente y se omiten los elementos sin flujo.
+pub fn contribuciones(
+    edificio: &EdificioLIDER,
+    zonas: &[String],
+    concepto: usize,
+    componente: Componente,
+) -> Vec<Contribucion> {
+    let tipos = tipos_concepto(concepto);
+    let zonas: Vec<_> = zonas
+        .iter()
+        .filter_map(|nombre| edificio.zonas.get(nombre))
+        .collect();
+    let superficie: f32 = zonas
+        .iter()
+        .map(|z| z.superficie * z.multiplicador as f32)
+        .sum();
+    if superficie.abs() < f32::EPSILON {
+        return Vec::new();
+    }
+    let mut contribuciones: Vec<Contribucion> = zonas
+        .iter()
+        .flat_map(|zona| {
+            let factor = zona.superficie * zona.multiplicador as f32 / superficie;
+            zona.elementos
+                .iter()
+                .filter(move |el| tipos.contains(&el.tipo))
+                .map(move |el| {
+                    let flujos = match (concepto, el.tipo) {
+                        (4, TipoElemento::Hueco) => zona.flujos_hueco(el).0,
+                        (5, TipoElemento::Hueco) => zona.flujos_hueco(el).1,
+                        _ => el.flujos,
+                    };
+                    Contribucion {
+                        zona: zona.nombre.clone(),
+                        elemento: el.nombre.clone(),
+                        tipo: el.tipo,
+                        valor: componente.valor(&flujos) * factor,
+                    }
+                })
+        })
+        .filter(|c| c.valor.abs() >= 0.005)
+        .collect();
+    contribuciones.sort_by(|a, b| nan_safe_cmp(&b.valor.abs(), &a.valor.abs()));
+    contribuciones
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::testfile;
+
+    #[test]
+    fn contribuciones_conceptos() {
+        let edificio = EdificioLIDER::from_file(testfile("test.res")).unwrap();
+        let zonas: Vec<String> = edificio
+            .plantas
+            .iter()
+            .flat_map(|p| p.zonas.clone())
+            .collect();
+
+        // Los elementos suman los flujos de la envolvente, sin puentes térmicos
+        let c = edificio.conceptos();
+        let envolvente = c.pext + c.cub + c.suelos + c.huecos_solar + c.huecos_trans;
+        let total: f32 = contribuciones(&edificio, &zonas, 8, Componente::CalNet)
+            .iter()
+            .map(|c| c.valor)
+            .sum();
+        assert!((total - envolvente.calnet).abs() < 0.1);
+
+        let huecos = contribuciones(&edificio, &zonas, 5, Componente::RefPos);
+        assert!(!huecos.is_empty());
+        assert!(huecos.iter().all(|c| c.tipo == TipoElemento::Hueco));
+        assert!(huecos
+            .windows(2)
+            .all(|w| w[0].valor.abs() >= w[1].valor.abs()));
+
+        // Los huecos se reparten entre los conceptos solar y de transmisión
+        let suma = |concepto, componente| -> f32 {
+            contribuciones(&edificio, &zonas, concepto, componente)
+                .iter()
+                .map(|c| c.valor)
+                .sum()
+        };
+        let solar = suma(4, Componente::CalPos);
+        let transmision = suma(5, Componente::CalPos);
+        assert!((solar - c.huecos_solar.calpos).abs() < 0.1);
+        assert!((transmision - c.huecos_trans.calpos).abs() < 0.1);
+        assert!((solar - transmision).abs() > 1.0);
+        assert!(contribuciones(&edificio, &zonas, 4, Componente::CalNeg).is_empty());
+        assert!((suma(5, Componente::CalNeg) - c.huecos_trans.calneg).abs() < 0.1);
+
+        assert!(contribuciones(&edificio, &zonas, 7, Componente::CalNeg).is_empty());
+        assert_eq!("cal-".parse::<Componente>().unwrap(), Componente::CalNeg);
+    }
+}
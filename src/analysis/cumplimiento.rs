@@ -0,0 +1,422 @@
+//! Cumplimiento de los límites de demanda energética del DB-HE
+//!
+//! Los límites de demanda de calefacción y refrigeración de cada versión del DB-HE dependen del uso
+//! del edificio y de su zona climática, y se definen en una tabla de datos (res/limites_dbhe.csv).
+//! La zona climática y el uso se pueden indicar en la interfaz o detectar a partir del archivo de
+//! proyecto de HULC (.ctehexml) situado junto al archivo de resultados.
+
+use std::path::Path;
+use std::sync::OnceLock;
+
+use crate::parsers::res::EdificioLIDER;
+use crate::utils::Error;
+
+/// Tabla de límites de demanda del DB-HE
+const LIMITES_DBHE: &str = include_str!("../res/limites_dbhe.csv");
+
+/// Zonas climáticas del CTE
+pub const ZONAS_CLIMATICAS: [&str; 19] = [
+    "α1", "α2", "α3", "A1", "A2", "A3", "A4", "B1", "B2", "B3", "B4", "C1", "C2", "C3", "C4", "D1",
+    "D2", "D3", "E1",
+];
+
+/// Zona climática del CTE (p.e. D3)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ZonaClimatica(String);
+
+impl std::str::FromStr for ZonaClimatica {
+    type Err = Error;
+
+    /// Obtiene la zona climática a partir de su nombre (p.e. "D3", "alfa3" o "α3")
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let zona = s
+            .trim()
+            .to_lowercase()
+            .replace("alfa", "α")
+            .replace("alpha", "α")
+            .to_ascii_uppercase();
+        if ZONAS_CLIMATICAS.contains(&zona.as_str()) {
+            Ok(Self(zona))
+        } else {
+            Err(format!("Zona climática desconocida: {}", s).into())
+        }
+    }
+}
+
+impl std::fmt::Display for ZonaClimatica {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl ZonaClimatica {
+    /// Zona climática de invierno (α, A, B, C, D, E)
+    pub fn invierno(&self) -> &str {
+        let fin = self.0.char_indices().last().map(|(i, _)| i).unwrap_or(0);
+        &self.0[..fin]
+    }
+
+    /// Zona climática de verano (1, 2, 3, 4)
+    pub fn verano(&self) -> &str {
+        &self.0[self.invierno().len()..]
+    }
+}
+
+/// Uso del edificio a efectos del DB-HE
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UsoEdificio {
+    /// Uso residencial privado
+    Residencial,
+    /// Otros usos
+    Terciario,
+}
+
+impl std::str::FromStr for UsoEdificio {
+    type Err = Error;
+
+    /// Obtiene el uso a partir del identificador usado en la interfaz y la tabla de límites
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "residencial" => Ok(Self::Residencial),
+            "terciario" => Ok(Self::Terciario),
+            _ => Err(format!("Uso del edificio desconocido: {}", s).into()),
+        }
+    }
+}
+
+impl std::fmt::Display for UsoEdificio {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let nombre = match self {
+            Self::Residencial => "residencial",
+            Self::Terciario => "terciario",
+        };
+        write!(f, "{}", nombre)
+    }
+}
+
+/// Servicio con límite de demanda
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Servicio {
+    /// Calefacción, con límite según la zona climática de invierno
+    Calefaccion,
+    /// Refrigeración, con límite según la zona climática de verano
+    Refrigeracion,
+}
+
+impl std::str::FromStr for Servicio {
+    type Err = Error;
+
+    /// Obtiene el servicio a partir del identificador usado en la tabla de límites
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "calefaccion" => Ok(Self::Calefaccion),
+            "refrigeracion" => Ok(Self::Refrigeracion),
+            _ => Err(format!("Servicio desconocido: {}", s).into()),
+        }
+    }
+}
+
+impl std::fmt::Display for Servicio {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let nombre = match self {
+            Self::Calefaccion => "calefacción",
+            Self::Refrigeracion => "refrigeración",
+        };
+        write!(f, "{}", nombre)
+    }
+}
+
+/// Límite de demanda de un servicio para una versión del DB-HE, uso y zona climática
+#[derive(Debug, Clone)]
+pub struct LimiteDemanda {
+    /// Versión del DB-HE (p.e. "DB-HE 2013")
+    pub version: String,
+    /// Uso del edificio
+    pub uso: UsoEdificio,
+    /// Servicio
+    pub servicio: Servicio,
+    /// Zona climática de invierno (calefacción) o de verano (refrigeración)
+    pub zona: String,
+    /// Valor base del límite [kWh/m²·año]
+    pub base: f32,
+    /// Factor de corrección por superficie [kWh/año]
+    pub factor_superficie: f32,
+}
+
+impl LimiteDemanda {
+    /// Valor del límite para la superficie útil del edificio indicada [kWh/m²·año]
+    pub fn valor(&self, superficie: f32) -> f32 {
+        if superficie > 0.0 {
+            self.base + self.factor_superficie / superficie
+        } else {
+            self.base
+        }
+    }
+}
+
+/// Límites de demanda de la tabla del DB-HE
+///
+/// La tabla se interpreta una sola vez y se conserva el resultado, incluido el error si la tabla
+/// es incorrecta.
+pub fn limites_dbhe() -> Result<&'static [LimiteDemanda], Error> {
+    static LIMITES: OnceLock<Result<Vec<LimiteDemanda>, String>> = OnceLock::new();
+    LIMITES
+        .get_or_init(|| leer_limites(LIMITES_DBHE).map_err(|e| e.to_string()))
+        .as_deref()
+        .map_err(|e| format!("Error en la tabla de límites del DB-HE: {}", e).into())
+}
+
+/// Interpreta el contenido de una tabla de límites de demanda
+fn leer_limites(texto: &str) -> Result<Vec<LimiteDemanda>, Error> {
+    let mut limites = Vec::new();
+    for (num, linea) in texto.lines().enumerate() {
+        let linea = linea.trim();
+        if linea.is_empty() || linea.starts_with('#') {
+            continue;
+        }
+        let campos: Vec<&str> = linea.split(',').map(str::trim).collect();
+        if campos.len() != 6 {
+            return Err(format!(
+                "Línea {} de la tabla de límites del DB-HE incorrecta",
+                num + 1
+            )
+            .into());
+        }
+        limites.push(LimiteDemanda {
+            version: campos[0].to_string(),
+            uso: campos[1].parse()?,
+            servicio: campos[2].parse()?,
+            zona: campos[3].to_string(),
+            base: campos[4].parse()?,
+            factor_superficie: campos[5].parse()?,
+        });
+    }
+    Ok(limites)
+}
+
+/// Versiones del DB-HE de la tabla de límites, en el orden de la tabla
+pub fn versiones_dbhe() -> Result<Vec<String>, Error> {
+    let mut versiones: Vec<String> = Vec::new();
+    for limite in limites_dbhe()? {
+        if !versiones.contains(&limite.version) {
+            versiones.push(limite.version.clone());
+        }
+    }
+    Ok(versiones)
+}
+
+/// Criterios de comprobación del DB-HE
+#[derive(Debug, Clone)]
+pub struct CriteriosDBHE {
+    /// Versión del DB-HE (p.e. "DB-HE 2013")
+    pub version: String,
+    /// Zona climática del edificio
+    pub zona: Option<ZonaClimatica>,
+    /// Uso del edificio
+    pub uso: Option<UsoEdificio>,
+}
+
+impl Default for CriteriosDBHE {
+    /// Primera versión de la tabla de límites, sin zona climática ni uso
+    ///
+    /// Si la tabla es incorrecta la versión queda vacía y el error se informa al comprobar.
+    fn default() -> Self {
+        Self {
+            version: limites_dbhe()
+                .ok()
+                .and_then(|limites| limites.first())
+                .map(|limite| limite.version.clone())
+                .unwrap_or_default(),
+            zona: None,
+            uso: None,
+        }
+    }
+}
+
+/// Comprobación de la demanda de un servicio frente a su límite
+#[derive(Debug, Clone)]
+pub struct Indicador {
+    /// Servicio comprobado
+    pub servicio: Servicio,
+    /// Demanda del edificio [kWh/m²·año]
+    pub demanda: f32,
+    /// Límite de demanda [kWh/m²·año] (None si no hay límite para el uso y la versión)
+    pub limite: Option<f32>,
+}
+
+impl Indicador {
+    /// Cumple el límite de demanda (None si no hay límite)
+    pub fn cumple(&self) -> Option<bool> {
+        self.limite.map(|limite| self.demanda <= limite)
+    }
+
+    /// Margen hasta el límite [kWh/m²·año] (negativo si se supera)
+    pub fn margen(&self) -> Option<f32> {
+        self.limite.map(|limite| limite - self.demanda)
+    }
+
+    /// Margen hasta el límite, en porcentaje del límite [%]
+    pub fn margen_pct(&self) -> Option<f32> {
+        match self.limite {
+            Some(limite) if limite > 0.0 => Some(100.0 * (limite - self.demanda) / limite),
+            _ => None,
+        }
+    }
+}
+
+/// Comprueba las demandas de calefacción y refrigeración del edificio frente a los límites del
+/// DB-HE, según los criterios indicados
+///
+/// Devuelve None si no se ha definido la zona climática o el uso del edificio.
+pub fn comprobar_dbhe(
+    edificio: &EdificioLIDER,
+    criterios: &CriteriosDBHE,
+) -> Result<Option<[Indicador; 2]>, Error> {
+    let (zona, uso) = match (criterios.zona.as_ref(), criterios.uso) {
+        (Some(zona), Some(uso)) => (zona, uso),
+        _ => return Ok(None),
+    };
+    let limites = limites_dbhe()?;
+    let limite = |servicio: Servicio, zona: &str| {
+        limites
+            .iter()
+            .find(|l| {
+                l.version == criterios.version
+                    && l.uso == uso
+                    && l.servicio == servicio
+                    && l.zona == zona
+            })
+            .map(|l| l.valor(edificio.superficie))
+    };
+    Ok(Some([
+        Indicador {
+            servicio: Servicio::Calefaccion,
+            demanda: edificio.calefaccion,
+            limite: limite(Servicio::Calefaccion, zona.invierno()),
+        },
+        Indicador {
+            servicio: Servicio::Refrigeracion,
+            demanda: edificio.refrigeracion,
+            limite: limite(Servicio::Refrigeracion, zona.verano()),
+        },
+    ]))
+}
+
+/// Detecta la zona climática y el uso del edificio en el archivo de proyecto de HULC (.ctehexml)
+/// situado en el directorio del archivo de resultados
+pub fn detectar_zona_uso(respath: &Path) -> (Option<ZonaClimatica>, Option<UsoEdificio>) {
+    let proyecto = respath.parent().and_then(|dir| {
+        std::fs::read_dir(dir)
+            .ok()?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .find(|path| {
+                path.extension()
+                    .and_then(|e| e.to_str())
+                    .map(|e| e.eq_ignore_ascii_case("ctehexml"))
+                    == Some(true)
+            })
+    });
+    match proyecto.and_then(|path| std::fs::read(path).ok()) {
+        Some(bytes) => zona_uso_proyecto(&String::from_utf8_lossy(&bytes)),
+        None => (None, None),
+    }
+}
+
+/// Zona climática y uso del edificio en el contenido de un archivo .ctehexml
+///
+/// La zona climática se busca en las etiquetas ZonaClimatica o ArchivoClimatico (p.e. "zonaD3.met")
+/// y el uso en la etiqueta tipoUso (los usos de vivienda se consideran residenciales).
+fn zona_uso_proyecto(texto: &str) -> (Option<ZonaClimatica>, Option<UsoEdificio>) {
+    let zona = ["ZonaClimatica", "ArchivoClimatico"]
+        .iter()
+        .filter_map(|etiqueta| valor_etiqueta(texto, etiqueta))
+        .find_map(|valor| buscar_zona(&valor));
+    let uso = valor_etiqueta(texto, "tipoUso").and_then(|valor| {
+        let valor = valor.to_lowercase();
+        if valor.is_empty() {
+            None
+        } else if ["unifamiliar", "bloque", "vivienda", "residencial"]
+            .iter()
+            .any(|u| valor.contains(u))
+        {
+            Some(UsoEdificio::Residencial)
+        } else {
+            Some(UsoEdificio::Terciario)
+        }
+    });
+    (zona, uso)
+}
+
+/// Contenido de la primera etiqueta XML con el nombre indicado (sin distinguir mayúsculas)
+fn valor_etiqueta(texto: &str, etiqueta: &str) -> Option<String> {
+    let minusculas = texto.to_ascii_lowercase();
+    let apertura = format!("<{}>", etiqueta.to_ascii_lowercase());
+    let cierre = format!("</{}>", etiqueta.to_ascii_lowercase());
+    let inicio = minusculas.find(&apertura)? + apertura.len();
+    let fin = inicio + minusculas[inicio..].find(&cierre)?;
+    texto.get(inicio..fin).map(|valor| valor.trim().to_string())
+}
+
+/// Primera zona climática válida contenida en un texto (p.e. "zonaD3.met" o "alfa3_canarias")
+///
+/// La letra de la zona de invierno debe estar en mayúsculas, salvo α.
+fn buscar_zona(texto: &str) -> Option<ZonaClimatica> {
+    let texto = texto.replace("alfa", "α").replace("alpha", "α");
+    let chars: Vec<char> = texto.chars().collect();
+    chars
+        .windows(2)
+        .filter(|par| par[0] == 'α' || par[0].is_ascii_uppercase())
+        .find_map(|par| par.iter().collect::<String>().parse().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limites_demanda_dbhe() {
+        // Tabla de límites
+        assert!(versiones_dbhe()
+            .unwrap()
+            .contains(&"DB-HE 2013".to_string()));
+        assert!(leer_limites("# Comentario\nDB-HE 2013,residencial,calefaccion,D").is_err());
+        assert!(leer_limites("DB-HE 2013,vivienda,calefaccion,D,27,2000").is_err());
+
+        // Detección de zona y uso en el proyecto
+        let proyecto = "<DatosGenerales><tipoUso>Unifamiliar</tipoUso>\
+            <ArchivoClimatico>zonaD3.met</ArchivoClimatico></DatosGenerales>";
+        let (zona, uso) = zona_uso_proyecto(proyecto);
+        let zona = zona.unwrap();
+        assert_eq!(zona.to_string(), "D3");
+        assert_eq!((zona.invierno(), zona.verano()), ("D", "3"));
+        assert_eq!(uso, Some(UsoEdificio::Residencial));
+        assert_eq!("alfa3".parse::<ZonaClimatica>().unwrap().invierno(), "α");
+
+        // Residencial en D3 con 100m²: calefacción 27 + 2000 / 100 = 47, refrigeración 15
+        let edificio = EdificioLIDER {
+            superficie: 100.0,
+            calefaccion: 50.0,
+            refrigeracion: 10.0,
+            ..Default::default()
+        };
+        let criterios = CriteriosDBHE {
+            version: "DB-HE 2013".to_string(),
+            zona: Some(zona),
+            uso,
+        };
+        let [cal, refr] = comprobar_dbhe(&edificio, &criterios).unwrap().unwrap();
+        assert_eq!(cal.limite, Some(47.0));
+        assert_eq!(cal.cumple(), Some(false));
+        assert!((cal.margen().unwrap() + 3.0).abs() < 1e-4);
+        assert_eq!(refr.limite, Some(15.0));
+        assert_eq!(refr.cumple(), Some(true));
+
+        // Sin límites absolutos para uso terciario
+        let criterios = CriteriosDBHE {
+            uso: Some(UsoEdificio::Terciario),
+            ..criterios
+        };
+        let [cal, _] = comprobar_dbhe(&edificio, &criterios).unwrap().unwrap();
+        assert_eq!(cal.cumple(), None);
+    }
+}
@@ -0,0 +1,123 @@
+//! Curvas de duración de carga de zonas, plantas y edificio
+//!
+//! Se obtienen a partir de la carga total horaria (sensible + latente) de las zonas en el archivo .bin,
+//! aplicando sus multiplicadores. Con el mismo convenio que las demandas, las cargas negativas son
+//! de calefacción y las positivas de refrigeración.
+
+use std::fmt::Write;
+
+use crate::graphs::nan_safe_cmp;
+use crate::parsers::bin::ZonaLider;
+
+/// Curvas de duración de carga de calefacción y refrigeración [W]
+///
+/// Cada curva contiene las cargas horarias ordenadas de mayor a menor, en valor absoluto.
+/// Las horas sin carga de cada tipo aparecen con valor nulo al final de la curva.
+#[derive(Debug, Clone, Default)]
+pub struct CurvasDuracion {
+    /// Cargas horarias de calefacción, en valor absoluto y ordenadas de mayor a menor [W]
+    pub calefaccion: Vec<f32>,
+    /// Cargas horarias de refrigeración, ordenadas de mayor a menor [W]
+    pub refrigeracion: Vec<f32>,
+}
+
+/// Carga horaria total (sensible + latente) de la zona, aplicando su multiplicador [W]
+pub fn cargas_zona(zona: &ZonaLider) -> Vec<f32> {
+    let mult = zona.multiplicador as f32;
+    zona.q_sen
+        .iter()
+        .zip(zona.q_lat.iter())
+        .map(|(sen, lat)| (sen + lat) * mult)
+        .collect()
+}
+
+impl CurvasDuracion {
+    /// Curvas de duración a partir de una serie de cargas horarias [W]
+    pub fn from_cargas(cargas: &[f32]) -> Self {
+        let sorted = |vals: Vec<f32>| {
+            let mut vals = vals;
+            vals.sort_by(|a, b| nan_safe_cmp(b, a));
+            vals
+        };
+        Self {
+            calefaccion: sorted(cargas.iter().map(|q| (-q).max(0.0)).collect()),
+            refrigeracion: sorted(cargas.iter().map(|q| q.max(0.0)).collect()),
+        }
+    }
+
+    /// Curvas de duración de un conjunto de zonas (planta o edificio)
+    ///
+    /// Se suman las cargas horarias de todas las zonas antes de ordenarlas,
+    /// de modo que se tiene en cuenta la simultaneidad de las cargas.
+    pub fn from_zonas<'a, I: IntoIterator<Item = &'a ZonaLider>>(zonas: I) -> Self {
+        let cargas = zonas
+            .into_iter()
+            .map(cargas_zona)
+            .reduce(|acc, cargas| acc.iter().zip(cargas).map(|(a, b)| a + b).collect())
+            .unwrap_or_default();
+        Self::from_cargas(&cargas)
+    }
+
+    /// Carga máxima de calefacción y refrigeración [W]
+    pub fn picos(&self) -> (f32, f32) {
+        (
+            self.calefaccion.first().copied().unwrap_or(0.0),
+            self.refrigeracion.first().copied().unwrap_or(0.0),
+        )
+    }
+
+    /// Demanda anual de calefacción y refrigeración [kWh/año]
+    pub fn demandas(&self) -> (f32, f32) {
+        (
+            self.calefaccion.iter().sum::<f32>() / 1000.0,
+            self.refrigeracion.iter().sum::<f32>() / 1000.0,
+        )
+    }
+
+    /// Horas con carga de calefacción y de refrigeración [h]
+    pub fn horas(&self) -> (usize, usize) {
+        (
+            self.calefaccion.iter().filter(|v| **v > 0.0).count(),
+            self.refrigeracion.iter().filter(|v| **v > 0.0).count(),
+        )
+    }
+
+    /// Curvas en formato CSV, con una fila por hora de la curva ordenada
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from("hora,calefaccion [W],refrigeracion [W]\n");
+        for (i, (cal, refr)) in self
+            .calefaccion
+            .iter()
+            .zip(self.refrigeracion.iter())
+            .enumerate()
+        {
+            writeln!(out, "{},{:.2},{:.2}", i + 1, cal, refr).unwrap();
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[allow(clippy::float_cmp)]
+    #[test]
+    fn curvas_duracion_zonas() {
+        let zona = ZonaLider {
+            multiplicador: 2,
+            q_sen: vec![-100.0, 50.0, 0.0, -300.0, 200.0],
+            q_lat: vec![0.0, 10.0, 0.0, 0.0, 0.0],
+            ..Default::default()
+        };
+        let curvas = CurvasDuracion::from_zonas(vec![&zona, &zona]);
+        assert_eq!(curvas.calefaccion, &[1200.0, 400.0, 0.0, 0.0, 0.0]);
+        assert_eq!(curvas.refrigeracion, &[800.0, 240.0, 0.0, 0.0, 0.0]);
+        assert_eq!(curvas.picos(), (1200.0, 800.0));
+        assert_eq!(curvas.horas(), (2, 2));
+        assert_eq!(curvas.demandas(), (1.6, 1.04));
+        assert!(curvas
+            .to_csv()
+            .starts_with("hora,calefaccion [W],refrigeracion [W]\n1,1200.00,800.00\n"));
+    }
+}
@@ -0,0 +1,109 @@
+//! Informes de validación de los datos de resultados
+//!
+//! Recogen las incidencias detectadas al comprobar la coherencia de los datos,
+//! indicando su gravedad, el objeto afectado y el tipo de comprobación
+
+use std::fmt::{self, Display};
+
+/// Gravedad de una incidencia
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Gravedad {
+    /// Diferencia que conviene revisar, pero que no invalida los datos
+    Aviso,
+    /// Incoherencia que indica datos erróneos o archivos que no se corresponden
+    Error,
+}
+
+impl Display for Gravedad {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Aviso => write!(f, "AVISO"),
+            Self::Error => write!(f, "ERROR"),
+        }
+    }
+}
+
+/// Incidencia detectada en una comprobación
+#[derive(Debug, Clone)]
+pub struct Incidencia {
+    /// Gravedad de la incidencia
+    pub gravedad: Gravedad,
+    /// Comprobación que ha fallado (p.e. "Superficie")
+    pub comprobacion: &'static str,
+    /// Objeto afectado (edificio, planta, zona o elemento)
+    pub objeto: String,
+    /// Descripción de la incidencia, con los valores comparados
+    pub mensaje: String,
+}
+
+/// Informe de validación
+#[derive(Debug, Clone, Default)]
+pub struct Informe {
+    /// Título del informe
+    pub titulo: String,
+    /// Incidencias detectadas
+    pub incidencias: Vec<Incidencia>,
+}
+
+impl Informe {
+    /// Crea un informe vacío con el título dado
+    pub fn new(titulo: &str) -> Self {
+        Self {
+            titulo: titulo.to_string(),
+            incidencias: Vec::new(),
+        }
+    }
+
+    /// Añade una incidencia al informe
+    pub fn push(
+        &mut self,
+        gravedad: Gravedad,
+        comprobacion: &'static str,
+        objeto: &str,
+        mensaje: String,
+    ) {
+        self.incidencias.push(Incidencia {
+            gravedad,
+            comprobacion,
+            objeto: objeto.to_string(),
+            mensaje,
+        });
+    }
+
+    /// Número de incidencias con la gravedad indicada
+    pub fn count(&self, gravedad: Gravedad) -> usize {
+        self.incidencias
+            .iter()
+            .filter(|i| i.gravedad == gravedad)
+            .count()
+    }
+
+    /// ¿No hay errores en el informe?
+    pub fn is_ok(&self) -> bool {
+        self.count(Gravedad::Error) == 0
+    }
+
+    /// Resumen en una línea del resultado de la validación
+    pub fn resumen(&self) -> String {
+        format!(
+            "{}: {} errores, {} avisos",
+            self.titulo,
+            self.count(Gravedad::Error),
+            self.count(Gravedad::Aviso)
+        )
+    }
+}
+
+impl Display for Informe {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", self.resumen())?;
+        for i in &self.incidencias {
+            writeln!(
+                f,
+                "[{}] {} - {}: {}",
+                i.gravedad, i.comprobacion, i.objeto, i.mensaje
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,17 @@
+//! Cálculos y análisis a partir de los datos de resultados de LIDER (.res) y de zonas (.bin)
+
+pub mod balance;
+pub mod confort;
+pub mod consistencia;
+pub mod contribuciones;
+pub mod cumplimiento;
+pub mod duracion;
+pub mod informe;
+pub mod ranking;
+pub mod red;
+pub mod rts;
+pub mod unidades;
+pub mod ventilacion;
+
+/// Días de cada mes de un año no bisiesto
+pub(crate) const DIAS_MESES: [usize; 12] = [31, 28, 31, 30, 31, 30, 31, 31, 30, 31, 30, 31];
@@ -0,0 +1,167 @@
+//! Clasificación de los elementos constructivos del edificio según su contribución a la demanda
+//!
+//! Los flujos de los elementos del archivo .res están referidos a la superficie de su zona.
+//! Para poder compararlos entre zonas se refieren a la superficie del edificio, aplicando
+//! el multiplicador de la zona: valor = flujo · superficie zona · multiplicador / superficie edificio.
+
+use crate::graphs::nan_safe_cmp;
+use crate::parsers::types::{EdificioLIDER, Flujos, TipoElemento};
+
+/// Criterio de clasificación de los elementos
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CriterioRanking {
+    /// Pérdidas en temporada de calefacción (calneg)
+    #[default]
+    PerdidasCalefaccion,
+    /// Ganancias en temporada de refrigeración (refpos)
+    GananciasRefrigeracion,
+}
+
+impl std::str::FromStr for CriterioRanking {
+    type Err = crate::utils::Error;
+
+    /// Obtiene el criterio a partir del identificador usado en la interfaz (p.e. "calneg")
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "calneg" => Ok(Self::PerdidasCalefaccion),
+            "refpos" => Ok(Self::GananciasRefrigeracion),
+            _ => Err(format!("Criterio de clasificación desconocido: {}", s).into()),
+        }
+    }
+}
+
+impl CriterioRanking {
+    /// Descripción del criterio
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::PerdidasCalefaccion => "Pérdidas en temporada de calefacción",
+            Self::GananciasRefrigeracion => "Ganancias en temporada de refrigeración",
+        }
+    }
+
+    /// Valor del criterio para unos flujos, positivo cuanto peor es el comportamiento [kWh/m²·año]
+    pub fn valor(self, flujos: &Flujos) -> f32 {
+        match self {
+            Self::PerdidasCalefaccion => -flujos.calneg,
+            Self::GananciasRefrigeracion => flujos.refpos,
+        }
+    }
+}
+
+/// Posición de un elemento en la clasificación
+#[derive(Debug, Clone)]
+pub struct PosicionRanking {
+    /// Planta del elemento
+    pub planta: String,
+    /// Zona del elemento
+    pub zona: String,
+    /// Nombre del elemento
+    pub elemento: String,
+    /// Tipo de elemento
+    pub tipo: TipoElemento,
+    /// Multiplicador de la zona
+    pub multiplicador: i32,
+    /// Valor del criterio referido a la superficie del edificio [kWh/m²·año]
+    pub valor: f32,
+    /// Fracción del valor total de todos los elementos del edificio [-]
+    pub fraccion: f32,
+}
+
+/// Clasificación de los elementos del edificio, de peor a mejor comportamiento según el criterio
+///
+/// Se pueden filtrar los elementos de un tipo o de una planta. La fracción de cada elemento se
+/// calcula respecto al total de todos los elementos del edificio, sin filtrar.
+pub fn ranking(
+    edificio: &EdificioLIDER,
+    criterio: CriterioRanking,
+    tipo: Option<TipoElemento>,
+    planta: Option<&str>,
+) -> Vec<PosicionRanking> {
+    if edificio.superficie.abs() < f32::EPSILON {
+        return Vec::new();
+    }
+    let posiciones: Vec<(bool, PosicionRanking)> = edificio
+        .plantas
+        .iter()
+        .flat_map(|p| p.zonas.iter())
+        .filter_map(|nombre| edificio.zonas.get(nombre))
+        .flat_map(|zona| {
+            let factor = zona.superficie * zona.multiplicador as f32 / edificio.superficie;
+            zona.elementos.iter().map(move |el| {
+                let incluido = (tipo.is_none() || tipo == Some(el.tipo))
+                    && (planta.is_none() || planta == Some(zona.planta.as_str()));
+                let posicion = PosicionRanking {
+                    planta: zona.planta.clone(),
+                    zona: zona.nombre.clone(),
+                    elemento: el.nombre.clone(),
+                    tipo: el.tipo,
+                    multiplicador: zona.multiplicador,
+                    valor: criterio.valor(&el.flujos) * factor,
+                    fraccion: 0.0,
+                };
+                (incluido, posicion)
+            })
+        })
+        .collect();
+
+    let total: f32 = posiciones.iter().map(|(_, p)| p.valor.max(0.0)).sum();
+    let mut posiciones: Vec<PosicionRanking> = posiciones
+        .into_iter()
+        .filter(|(incluido, _)| *incluido)
+        .map(|(_, mut p)| {
+            if total > f32::EPSILON {
+                p.fraccion = p.valor / total;
+            }
+            p
+        })
+        .collect();
+    posiciones.sort_by(|a, b| nan_safe_cmp(&b.valor, &a.valor));
+    posiciones
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::testfile;
+
+    #[test]
+    fn ranking_elementos() {
+        let edificio = EdificioLIDER::from_file(testfile("test.res")).unwrap();
+        let nelementos: usize = edificio.zonas.values().map(|z| z.elementos.len()).sum();
+
+        let todos = ranking(
+            &edificio,
+            CriterioRanking::GananciasRefrigeracion,
+            None,
+            None,
+        );
+        assert_eq!(todos.len(), nelementos);
+        assert!(todos.windows(2).all(|w| w[0].valor >= w[1].valor));
+        let suma: f32 = todos.iter().map(|p| p.fraccion).sum();
+        assert!((suma - 1.0).abs() < 1e-4);
+        // Los huecos son los elementos con más ganancias en refrigeración
+        assert_eq!(todos[0].tipo, TipoElemento::Hueco);
+
+        let huecos = ranking(
+            &edificio,
+            CriterioRanking::GananciasRefrigeracion,
+            Some(TipoElemento::Hueco),
+            Some("P02"),
+        );
+        assert!(!huecos.is_empty());
+        assert!(huecos
+            .iter()
+            .all(|p| p.tipo == TipoElemento::Hueco && p.planta == "P02"));
+
+        // Valor referido a la superficie del edificio
+        let zona = &edificio.zonas[&huecos[0].zona];
+        let elemento = zona
+            .elementos
+            .iter()
+            .find(|e| e.nombre == huecos[0].elemento)
+            .unwrap();
+        let valor = elemento.flujos.refpos * zona.superficie * zona.multiplicador as f32
+            / edificio.superficie;
+        assert!((huecos[0].valor - valor).abs() < 1e-4);
+    }
+}
@@ -0,0 +1,129 @@
+//! Red de adyacencia entre zonas
+//!
+//! Se obtiene a partir de las zonas adyacentes y los coeficientes UA de acoplamiento
+//! entre zonas (ua_int) y con el exterior (ua_ext) del archivo .bin.
+//! Permite localizar zonas mal conectadas o con un acoplamiento excesivo.
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::parsers::bin::BinData;
+
+/// Zona de la red de adyacencia
+#[derive(Debug, Clone, Default)]
+pub struct NodoZona {
+    /// Nombre de la zona
+    pub nombre: String,
+    /// Superficie de la zona [m²]
+    pub area: f32,
+    /// Multiplicador de la zona
+    pub multiplicador: i32,
+    /// UA con el exterior [W/K]
+    pub ua_ext: f32,
+}
+
+/// Acoplamiento entre dos zonas adyacentes de la red
+#[derive(Debug, Clone, Default)]
+pub struct EnlaceZonas {
+    /// Índice de la primera zona en la lista de nodos
+    pub a: usize,
+    /// Índice de la segunda zona en la lista de nodos
+    pub b: usize,
+    /// UA entre las zonas [W/K]
+    pub ua: f32,
+}
+
+/// Red de adyacencia de las zonas de un archivo .bin
+#[derive(Debug, Clone, Default)]
+pub struct RedZonas {
+    /// Zonas, ordenadas por nombre
+    pub nodos: Vec<NodoZona>,
+    /// Acoplamientos entre pares de zonas (sin repetir)
+    pub enlaces: Vec<EnlaceZonas>,
+}
+
+impl RedZonas {
+    /// Construye la red de adyacencia a partir de los datos de zonas
+    ///
+    /// HULC no siempre incluye la adyacencia en las dos zonas del par, por lo que se
+    /// consideran ambos sentidos y, si aparecen los dos, se toma el valor medio de UA.
+    /// Se descartan las adyacencias con zonas que no están en el archivo.
+    pub fn from_bindata(bindata: &BinData) -> Self {
+        let nodos: Vec<NodoZona> = bindata
+            .zonas
+            .values()
+            .map(|z| NodoZona {
+                nombre: z.nombre.clone(),
+                area: z.area,
+                multiplicador: z.multiplicador,
+                ua_ext: z.ua_ext,
+            })
+            .collect();
+        let idx: HashMap<&str, usize> = nodos
+            .iter()
+            .enumerate()
+            .map(|(i, n)| (n.nombre.as_str(), i))
+            .collect();
+
+        // Valores de UA para cada par de zonas (índice menor, índice mayor)
+        let mut pares = BTreeMap::<(usize, usize), Vec<f32>>::new();
+        for zona in bindata.zonas.values() {
+            let a = match idx.get(zona.nombre.as_str()) {
+                Some(a) => *a,
+                None => continue,
+            };
+            for (adyacente, ua) in zona.adyacentes.iter().zip(zona.ua_int.iter()) {
+                match idx.get(adyacente.as_str()).copied() {
+                    Some(b) if b != a => pares.entry((a.min(b), a.max(b))).or_default().push(*ua),
+                    _ => continue,
+                }
+            }
+        }
+        let enlaces = pares
+            .into_iter()
+            .map(|((a, b), uas)| EnlaceZonas {
+                a,
+                b,
+                ua: uas.iter().sum::<f32>() / uas.len() as f32,
+            })
+            .collect();
+
+        Self { nodos, enlaces }
+    }
+
+    /// UA total de cada zona con sus zonas adyacentes [W/K]
+    pub fn ua_int_total(&self) -> Vec<f32> {
+        let mut totales = vec![0.0; self.nodos.len()];
+        for enlace in &self.enlaces {
+            totales[enlace.a] += enlace.ua;
+            totales[enlace.b] += enlace.ua;
+        }
+        totales
+    }
+
+    /// Valor máximo de UA entre zonas [W/K]
+    pub fn ua_max(&self) -> f32 {
+        self.enlaces.iter().map(|e| e.ua).fold(0.0, f32::max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::testfile;
+
+    #[test]
+    fn red_zonas_testfile() {
+        let red = RedZonas::from_bindata(&BinData::from_file(testfile("test.bin")).unwrap());
+        assert_eq!(red.nodos.len(), 10);
+        // Pares de zonas distintos, incluidos los que solo aparecen en una de las zonas
+        assert_eq!(red.enlaces.len(), 23);
+        let totales = red.ua_int_total();
+        let p02_e06 = red
+            .nodos
+            .iter()
+            .position(|n| n.nombre == "P02_E06")
+            .unwrap();
+        assert!((totales[p02_e06] - 78.9).abs() < 0.1);
+        assert!((red.ua_max() - 26.38).abs() < 0.01);
+    }
+}
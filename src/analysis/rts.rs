@@ -0,0 +1,223 @@
+//! Recálculo de cargas sensibles de zona con los factores de respuesta (p, g) del archivo .bin
+//!
+//! Los factores de respuesta relacionan la variación de la carga sensible de la zona con las variaciones
+//! de su temperatura y de sus ganancias térmicas respecto a las del cálculo original (IDAE, "Guía técnica.
+//! Procedimientos y aspectos de la simulación de instalaciones térmicas en edificios", pp.50-51 y Anexo 6):
+//!
+//!     Σ p_i · ΔQ(t - i) = Σ g_j · (T(t - j) - T'(t - j)) + Σ p_i · ΔG(t)
+//!
+//! donde ΔQ = Q' - Q es la variación de la carga sensible [W], T la temperatura original del local
+//! y T' la temperatura alternativa [ºC], y ΔG la ganancia térmica adicional [W]. La ganancia adicional
+//! se trata como ganancia con la misma respuesta de la zona (p), de modo que en régimen permanente
+//! se traslada íntegramente a la carga.
+//!
+//! Con el mismo convenio que en el archivo .bin, las cargas negativas son de calefacción y las
+//! positivas de refrigeración. Se supone que el año es periódico, de modo que el historial
+//! de las primeras horas corresponde al final del año.
+
+use crate::parsers::bin::ZonaLider;
+use crate::utils::Error;
+
+/// Número de pasadas por el año para estabilizar el historial de la respuesta de la zona
+const PASADAS: usize = 2;
+
+/// Conductancia equivalente de la zona en régimen permanente (Σg / Σp) [W/K]
+///
+/// Variación de la carga sensible en régimen permanente por cada grado de descenso de la temperatura del local
+pub fn conductancia(zona: &ZonaLider) -> f32 {
+    let sum_p: f32 = zona.p.iter().sum();
+    let sum_g: f32 = zona.g.iter().sum();
+    if sum_p.abs() < f32::EPSILON {
+        0.0
+    } else {
+        sum_g / sum_p
+    }
+}
+
+/// Comprueba que la serie horaria tiene la misma longitud que las de la zona
+fn check_len(zona: &ZonaLider, serie: &[f32], nombre: &str) -> Result<(), Error> {
+    if serie.len() == zona.q_sen.len() {
+        Ok(())
+    } else {
+        Err(format!(
+            "La serie de {} tiene {} valores y la zona {} tiene {} horas",
+            nombre,
+            serie.len(),
+            zona.nombre,
+            zona.q_sen.len()
+        )
+        .into())
+    }
+}
+
+/// Variación horaria de la carga sensible de la zona [W]
+///
+/// dt: variación de la temperatura del local respecto a la original (T' - T) [ºC]
+/// dg: ganancia térmica adicional [W]
+pub fn variacion_carga(zona: &ZonaLider, dt: &[f32], dg: &[f32]) -> Result<Vec<f32>, Error> {
+    check_len(zona, dt, "temperaturas")?;
+    check_len(zona, dg, "ganancias")?;
+    let p0 = zona.p.first().copied().unwrap_or(0.0);
+    if p0.abs() < f32::EPSILON {
+        return Err(format!(
+            "Factores de respuesta (p) no válidos en la zona {}",
+            zona.nombre
+        )
+        .into());
+    }
+    let sum_p: f32 = zona.p.iter().sum();
+    let n = dt.len();
+    let mut dq = vec![0.0f32; n];
+    for t in 0..(PASADAS * n) {
+        let idx = |k: usize| (t + PASADAS * n - k) % n;
+        let temp: f32 = zona
+            .g
+            .iter()
+            .enumerate()
+            .map(|(j, g)| -g * dt[idx(j)])
+            .sum();
+        let hist: f32 = zona
+            .p
+            .iter()
+            .enumerate()
+            .skip(1)
+            .map(|(i, p)| p * dq[idx(i)])
+            .sum();
+        dq[t % n] = (temp + sum_p * dg[t % n] - hist) / p0;
+    }
+    Ok(dq)
+}
+
+/// Cargas sensibles de la zona con temperaturas del local y ganancias térmicas alternativas [W]
+///
+/// t_alt: temperaturas del local [ºC]
+/// ganancias: ganancias térmicas adicionales (o negativas, si se reducen) [W]
+pub fn cargas_alternativas(
+    zona: &ZonaLider,
+    t_alt: &[f32],
+    ganancias: &[f32],
+) -> Result<Vec<f32>, Error> {
+    check_len(zona, t_alt, "temperaturas")?;
+    let dt: Vec<f32> = t_alt
+        .iter()
+        .zip(zona.t_real.iter())
+        .map(|(alt, t)| alt - t)
+        .collect();
+    let dq = variacion_carga(zona, &dt, ganancias)?;
+    Ok(zona.q_sen.iter().zip(dq).map(|(q, dq)| q + dq).collect())
+}
+
+/// Temperaturas del local con un horario de consignas alternativo [ºC]
+///
+/// En las horas con demanda de calefacción o refrigeración el local pasa a estar a la nueva
+/// consigna baja o alta. En el resto se mantiene la temperatura original salvo que, estando
+/// disponible el sistema, quede fuera de las nuevas consignas.
+pub fn temperaturas_consigna(zona: &ZonaLider, t_min: &[f32], t_max: &[f32]) -> Vec<f32> {
+    (0..zona.t_real.len().min(t_min.len()).min(t_max.len()))
+        .map(|i| {
+            let (t, q) = (zona.t_real[i], zona.q_sen[i]);
+            if q < 0.0 || (zona.da_cal[i] == 1 && t < t_min[i]) {
+                t_min[i]
+            } else if q > 0.0 || (zona.da_ref[i] == 1 && t > t_max[i]) {
+                t_max[i]
+            } else {
+                t
+            }
+        })
+        .collect()
+}
+
+/// Cargas sensibles de la zona con un horario de consignas y ganancias térmicas alternativos [W]
+///
+/// Solo se mantiene la carga recalculada cuando es coherente con el modo de funcionamiento: en las horas
+/// con demanda, si no cambia de signo, y en las horas en oscilación libre, si las nuevas consignas fuerzan
+/// a calentar o enfriar el local. En otro caso el local queda en oscilación libre y la carga se anula.
+pub fn cargas_con_consignas(
+    zona: &ZonaLider,
+    t_min: &[f32],
+    t_max: &[f32],
+    ganancias: &[f32],
+) -> Result<Vec<f32>, Error> {
+    check_len(zona, t_min, "consignas bajas")?;
+    check_len(zona, t_max, "consignas altas")?;
+    let t_alt = temperaturas_consigna(zona, t_min, t_max);
+    let cargas = cargas_alternativas(zona, &t_alt, ganancias)?;
+    Ok(cargas
+        .iter()
+        .zip(zona.q_sen.iter())
+        .zip(t_alt.iter().zip(zona.t_real.iter()))
+        .map(|((nueva, orig), (t_alt, t))| {
+            let coherente = if *orig != 0.0 {
+                nueva * orig > 0.0
+            } else {
+                (t_alt < t && *nueva > 0.0) || (t_alt > t && *nueva < 0.0)
+            };
+            if coherente {
+                *nueva
+            } else {
+                0.0
+            }
+        })
+        .collect())
+}
+
+/// Demandas anuales de calefacción y refrigeración de una serie de cargas horarias [kWh/año]
+///
+/// Las cargas negativas son de calefacción y las positivas de refrigeración. Ambas demandas se
+/// devuelven en valor absoluto.
+pub fn demandas(cargas: &[f32]) -> (f32, f32) {
+    let (cal, refr) = cargas.iter().fold((0.0, 0.0), |(cal, refr), q| {
+        (cal + (-q).max(0.0), refr + q.max(0.0))
+    });
+    (cal / 1000.0, refr / 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::testfile;
+
+    fn testzona() -> ZonaLider {
+        let bindata = crate::parsers::bin::BinData::from_file(testfile("test.bin")).unwrap();
+        bindata.zonas["P01_E01"].clone()
+    }
+
+    #[test]
+    fn rts_regimen_permanente() {
+        let zona = testzona();
+        let n = zona.q_sen.len();
+        let k = conductancia(&zona);
+        assert!((k - 21.78).abs() < 0.01);
+
+        // Sin cambios se obtienen las cargas originales
+        let cargas = cargas_alternativas(&zona, &zona.t_real, &vec![0.0; n]).unwrap();
+        assert!(cargas
+            .iter()
+            .zip(zona.q_sen.iter())
+            .all(|(a, b)| (a - b).abs() < 1e-3));
+
+        // Bajar 1ºC la temperatura del local aumenta la carga en K W en régimen permanente
+        let dq = variacion_carga(&zona, &vec![-1.0; n], &vec![0.0; n]).unwrap();
+        assert!((dq[n - 1] - k).abs() < 0.01);
+
+        // Una ganancia constante se traslada íntegramente a la carga
+        let dq = variacion_carga(&zona, &vec![0.0; n], &vec![100.0; n]).unwrap();
+        assert!((dq[n - 1] - 100.0).abs() < 0.01);
+
+        // Error con series de longitud incorrecta
+        assert!(cargas_alternativas(&zona, &zona.t_real, &[0.0; 24]).is_err());
+    }
+
+    #[test]
+    fn rts_consignas() {
+        let zona = testzona();
+        let n = zona.q_sen.len();
+        let (_, base) = demandas(&zona.q_sen);
+        // Subir la consigna de refrigeración reduce la demanda de refrigeración
+        let t_max: Vec<f32> = zona.t_max.iter().map(|t| t + 1.0).collect();
+        let cargas = cargas_con_consignas(&zona, &zona.t_min, &t_max, &vec![0.0; n]).unwrap();
+        assert_eq!(cargas.len(), n);
+        assert!(demandas(&cargas).1 < base);
+        assert_eq!(demandas(&[-1000.0, 0.0, 500.0, -2000.0]), (3.0, 0.5));
+    }
+}
@@ -0,0 +1,65 @@
+//! Unidades de presentación de los flujos de calor y las demandas
+//!
+//! Los valores del archivo .res son intensidades [kWh/m²·año] referidas a la superficie de cada
+//! objeto (edificio, planta o zona). También pueden expresarse como energía absoluta [kWh/año],
+//! aplicando los multiplicadores de las zonas, o como intensidad referida a la superficie del
+//! edificio, que indica la contribución de cada objeto a la demanda total del edificio.
+
+/// Unidades de los flujos de calor y las demandas
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Unidades {
+    /// Intensidad referida a la superficie del objeto [kWh/m²·año]
+    #[default]
+    Intensidad,
+    /// Energía absoluta, con multiplicadores [kWh/año]
+    Absoluta,
+    /// Intensidad referida a la superficie del edificio [kWh/m²·año]
+    Edificio,
+}
+
+impl std::str::FromStr for Unidades {
+    type Err = crate::utils::Error;
+
+    /// Obtiene las unidades a partir del identificador usado en la interfaz (p.e. "absoluta")
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "intensidad" => Ok(Self::Intensidad),
+            "absoluta" => Ok(Self::Absoluta),
+            "edificio" => Ok(Self::Edificio),
+            _ => Err(format!("Unidades desconocidas: {}", s).into()),
+        }
+    }
+}
+
+impl Unidades {
+    /// Unidades de los valores anuales
+    pub fn anual(self) -> &'static str {
+        match self {
+            Self::Intensidad => "kWh/m²·año",
+            Self::Absoluta => "kWh/año",
+            Self::Edificio => "kWh/m²edif·año",
+        }
+    }
+
+    /// Unidades de los valores mensuales
+    pub fn mensual(self) -> &'static str {
+        match self {
+            Self::Intensidad => "kWh/m²·mes",
+            Self::Absoluta => "kWh/mes",
+            Self::Edificio => "kWh/m²edif·mes",
+        }
+    }
+
+    /// Factor de conversión de las intensidades de un objeto a estas unidades
+    ///
+    /// superficie es la superficie del objeto, con multiplicadores, y superficie_edificio la
+    /// superficie total del edificio [m²]
+    pub fn factor(self, superficie: f32, superficie_edificio: f32) -> f32 {
+        match self {
+            Self::Intensidad => 1.0,
+            Self::Absoluta => superficie,
+            Self::Edificio if superficie_edificio.abs() < f32::EPSILON => 0.0,
+            Self::Edificio => superficie / superficie_edificio,
+        }
+    }
+}
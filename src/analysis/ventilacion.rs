@@ -0,0 +1,299 @@
+//! Caudales de ventilación e infiltraciones de las zonas y balance energético asociado
+//!
+//! El archivo .bin contiene el caudal másico horario de ventilación e infiltraciones de cada zona [kg/s].
+//! Se convierte a caudal volumétrico [m³/h] y a renovaciones hora [1/h] con el volumen de la zona,
+//! y, con la temperatura exterior, se obtienen las ganancias y pérdidas de calor por ventilación,
+//! que pueden compararse con el concepto de ventilación e infiltraciones del archivo .res.
+
+use super::informe::{Gravedad, Informe};
+use crate::parsers::bin::{BinData, ZonaLider};
+use crate::parsers::types::{EdificioLIDER, ZonaLIDER};
+use crate::utils::Error;
+
+/// Densidad del aire [kg/m³]
+pub const DENSIDAD_AIRE: f32 = 1.225;
+/// Calor específico del aire [J/kg·K]
+pub const CALOR_ESPECIFICO_AIRE: f32 = 1005.0;
+
+/// Diferencia absoluta admisible entre los balances de ventilación calculado y del .res [kWh/m²·año]
+const TOLERANCIA_VENTILACION: f32 = 1.0;
+
+/// Diferencia relativa admisible entre los balances de ventilación calculado y del .res [-]
+const TOLERANCIA_VENTILACION_REL: f32 = 0.10;
+
+/// Caudal másico [kg/s]
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default)]
+pub struct CaudalMasico(pub f32);
+
+/// Caudal volumétrico [m³/h]
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default)]
+pub struct CaudalVolumetrico(pub f32);
+
+/// Renovaciones hora [1/h]
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default)]
+pub struct Renovaciones(pub f32);
+
+impl CaudalMasico {
+    /// Caudal volumétrico equivalente: caudal[m³/h] = caudal[kg/s] * 3600 s/h / 1.225 kg/m³
+    pub fn volumetrico(self) -> CaudalVolumetrico {
+        CaudalVolumetrico(self.0 * 3600.0 / DENSIDAD_AIRE)
+    }
+
+    /// Potencia aportada al local por el caudal con un salto de temperaturas (exterior - local) [W]
+    pub fn potencia(self, salto: f32) -> f32 {
+        self.0 * CALOR_ESPECIFICO_AIRE * salto
+    }
+}
+
+impl From<CaudalMasico> for CaudalVolumetrico {
+    fn from(caudal: CaudalMasico) -> Self {
+        caudal.volumetrico()
+    }
+}
+
+impl CaudalVolumetrico {
+    /// Renovaciones hora de un local de volumen dado [m³]
+    pub fn renovaciones(self, volumen: f32) -> Renovaciones {
+        if volumen.abs() < f32::EPSILON {
+            Renovaciones(0.0)
+        } else {
+            Renovaciones(self.0 / volumen)
+        }
+    }
+}
+
+/// Caudales másicos horarios de ventilación e infiltraciones de la zona
+pub fn caudales(zona: &ZonaLider) -> Vec<CaudalMasico> {
+    zona.v_ventinf.iter().map(|v| CaudalMasico(*v)).collect()
+}
+
+/// Caudal másico medio de ventilación e infiltraciones de la zona
+pub fn caudal_medio(zona: &ZonaLider) -> CaudalMasico {
+    if zona.v_ventinf.is_empty() {
+        return CaudalMasico(0.0);
+    }
+    CaudalMasico(zona.v_ventinf.iter().sum::<f32>() / zona.v_ventinf.len() as f32)
+}
+
+/// Balance anual de calor por ventilación e infiltraciones de una zona (sin multiplicador)
+#[derive(Debug, Clone, Default)]
+pub struct BalanceVentilacion {
+    /// Nombre de la zona
+    pub nombre: String,
+    /// Multiplicador de la zona
+    pub multiplicador: i32,
+    /// Superficie de la zona [m²]
+    pub area: f32,
+    /// Volumen de la zona [m³]
+    pub volumen: f32,
+    /// Caudal medio anual de ventilación e infiltraciones
+    pub caudal_medio: CaudalVolumetrico,
+    /// Renovaciones hora medias anuales
+    pub renovaciones: Renovaciones,
+    /// Ganancias de calor por ventilación (aire exterior más caliente que el local) [kWh/año]
+    pub ganancias: f32,
+    /// Pérdidas de calor por ventilación (aire exterior más frío que el local), con signo negativo [kWh/año]
+    pub perdidas: f32,
+}
+
+impl BalanceVentilacion {
+    /// Balance de ventilación de la zona con las temperaturas exteriores horarias [ºC]
+    pub fn from_zona(zona: &ZonaLider, t_ext: &[f32]) -> Result<Self, Error> {
+        if t_ext.len() != zona.t_real.len() {
+            return Err(format!(
+                "Las temperaturas exteriores tienen {} valores y la zona {} tiene {} horas",
+                t_ext.len(),
+                zona.nombre,
+                zona.t_real.len()
+            )
+            .into());
+        }
+        let (mut ganancias, mut perdidas) = (0.0, 0.0);
+        for ((caudal, t_local), t_ext) in caudales(zona).iter().zip(&zona.t_real).zip(t_ext) {
+            // Energía horaria [Wh] -> [kWh]
+            let q = caudal.potencia(t_ext - t_local) / 1000.0;
+            if q > 0.0 {
+                ganancias += q;
+            } else {
+                perdidas += q;
+            }
+        }
+        let caudal_medio = caudal_medio(zona).volumetrico();
+        Ok(Self {
+            nombre: zona.nombre.clone(),
+            multiplicador: zona.multiplicador,
+            area: zona.area,
+            volumen: zona.volumen,
+            caudal_medio,
+            renovaciones: caudal_medio.renovaciones(zona.volumen),
+            ganancias,
+            perdidas,
+        })
+    }
+
+    /// Balance neto anual de calor por ventilación [kWh/año]
+    pub fn neto(&self) -> f32 {
+        self.ganancias + self.perdidas
+    }
+
+    /// Balance neto anual de calor por ventilación por m² de la zona [kWh/m²·año]
+    pub fn neto_m2(&self) -> f32 {
+        if self.area.abs() < f32::EPSILON {
+            0.0
+        } else {
+            self.neto() / self.area
+        }
+    }
+
+    /// Balance neto anual del concepto de ventilación e infiltraciones en el archivo .res [kWh/m²·año]
+    ///
+    /// Suma de los balances netos de los periodos de calefacción y refrigeración
+    pub fn neto_res(zona: &ZonaLIDER) -> f32 {
+        zona.conceptos.vent.calnet + zona.conceptos.vent.refnet
+    }
+}
+
+/// Balances de ventilación de todas las zonas de un archivo .bin
+pub fn balances(bindata: &BinData, t_ext: &[f32]) -> Result<Vec<BalanceVentilacion>, Error> {
+    bindata
+        .zonas
+        .values()
+        .map(|z| BalanceVentilacion::from_zona(z, t_ext))
+        .collect()
+}
+
+/// Balance de ventilación del edificio, aplicando los multiplicadores de las zonas
+///
+/// Los caudales y energías se suman y las renovaciones hora se refieren al volumen total
+pub fn balance_edificio(balances: &[BalanceVentilacion]) -> BalanceVentilacion {
+    let mut total = BalanceVentilacion {
+        nombre: "Edificio".to_string(),
+        multiplicador: 1,
+        ..Default::default()
+    };
+    for b in balances {
+        let mult = b.multiplicador as f32;
+        total.area += b.area * mult;
+        total.volumen += b.volumen * mult;
+        total.caudal_medio.0 += b.caudal_medio.0 * mult;
+        total.ganancias += b.ganancias * mult;
+        total.perdidas += b.perdidas * mult;
+    }
+    total.renovaciones = total.caudal_medio.renovaciones(total.volumen);
+    total
+}
+
+/// Compara los balances de ventilación con el concepto de ventilación e infiltraciones del .res
+///
+/// Se comparan los balances netos por m² de cada zona y del edificio. Las diferencias que superan la
+/// tolerancia absoluta y relativa se indican como avisos, ya que el balance se estima a partir del
+/// caudal y las temperaturas horarias y no reproduce exactamente el cálculo del programa.
+pub fn comprobar_ventilacion(edificio: &EdificioLIDER, balances: &[BalanceVentilacion]) -> Informe {
+    let mut informe =
+        Informe::new("Balance de ventilación e infiltraciones frente al archivo .res");
+    let mut comparar = |objeto: &str, calculado: f32, res: f32| {
+        let diferencia = (calculado - res).abs();
+        if diferencia > TOLERANCIA_VENTILACION
+            && diferencia > TOLERANCIA_VENTILACION_REL * res.abs()
+        {
+            informe.push(
+                Gravedad::Aviso,
+                "Ventilación",
+                objeto,
+                format!(
+                    "Balance neto de ventilación calculado {:.2} kWh/m²·año y en el .res {:.2} kWh/m²·año",
+                    calculado, res
+                ),
+            );
+        }
+    };
+    let mut sin_zona = Vec::new();
+    for balance in balances {
+        match edificio.zonas.get(&balance.nombre) {
+            Some(zona) => comparar(
+                &balance.nombre,
+                balance.neto_m2(),
+                BalanceVentilacion::neto_res(zona),
+            ),
+            None => sin_zona.push(&balance.nombre),
+        }
+    }
+    let vent = edificio.conceptos().vent;
+    comparar(
+        &edificio.nombre,
+        balance_edificio(balances).neto_m2(),
+        vent.calnet + vent.refnet,
+    );
+    for nombre in sin_zona {
+        informe.push(
+            Gravedad::Aviso,
+            "Zonas",
+            nombre,
+            "La zona del archivo .bin no existe en el archivo .res".to_string(),
+        );
+    }
+    informe
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::testfile;
+
+    #[test]
+    fn balance_ventilacion() {
+        let caudal = CaudalMasico(1.225 / 3.6);
+        assert!((caudal.volumetrico().0 - 1000.0).abs() < 0.01);
+        assert!((caudal.volumetrico().renovaciones(500.0).0 - 2.0).abs() < 0.001);
+
+        let zona = ZonaLider {
+            nombre: "Z1".to_string(),
+            multiplicador: 2,
+            area: 10.0,
+            volumen: 30.0,
+            v_ventinf: vec![0.01; 4],
+            t_real: vec![20.0, 20.0, 25.0, 25.0],
+            ..Default::default()
+        };
+        let t_ext = [10.0, 10.0, 30.0, 30.0];
+        let balance = BalanceVentilacion::from_zona(&zona, &t_ext).unwrap();
+        let renovaciones = balance.renovaciones.0;
+        // 0.01 kg/s * 1005 J/kgK * 10 K * 2 h = 201 Wh
+        assert!((balance.perdidas + 0.201).abs() < 1e-4);
+        assert!((balance.ganancias - 0.1005).abs() < 1e-4);
+        assert!((balance.neto_m2() + 0.01005).abs() < 1e-5);
+
+        let edificio = balance_edificio(&[balance.clone(), balance]);
+        assert!((edificio.perdidas + 4.0 * 0.201).abs() < 1e-4);
+        assert!((edificio.renovaciones.0 - renovaciones).abs() < 1e-4);
+
+        assert!(BalanceVentilacion::from_zona(&zona, &[0.0; 3]).is_err());
+    }
+
+    #[test]
+    fn ventilacion_frente_a_res() {
+        let bindata = BinData::from_file(testfile("test.bin")).unwrap();
+        let edificio = EdificioLIDER::from_file(testfile("test.res")).unwrap();
+
+        // Balances que reproducen el concepto de ventilación del .res
+        let mut balances = balances(&bindata, &vec![20.0; 8760]).unwrap();
+        for balance in balances.iter_mut() {
+            let neto_res = BalanceVentilacion::neto_res(&edificio.zonas[&balance.nombre]);
+            balance.ganancias = 0.0;
+            balance.perdidas = neto_res * balance.area;
+        }
+        let informe = comprobar_ventilacion(&edificio, &balances);
+        assert!(informe.incidencias.is_empty(), "{}", informe);
+
+        // Zona con un balance muy distinto al del .res, que también altera el del edificio
+        balances[0].perdidas = 2.0 * balances[0].perdidas - 10.0 * balances[0].area;
+        let informe = comprobar_ventilacion(&edificio, &balances);
+        let objetos: Vec<_> = informe
+            .incidencias
+            .iter()
+            .map(|i| i.objeto.as_str())
+            .collect();
+        assert_eq!(objetos, &[balances[0].nombre.as_str(), "Edificio"]);
+        assert!(informe.is_ok());
+    }
+}
@@ -1,5 +1,20 @@
+use crate::analysis::balance::comprobar_balance_res;
+use crate::analysis::consistencia::comprobar_res_bin;
+use crate::analysis::contribuciones::{contribuciones, Componente, Contribucion};
+use crate::analysis::cumplimiento::{comprobar_dbhe, detectar_zona_uso, CriteriosDBHE, Indicador};
+use crate::analysis::duracion::CurvasDuracion;
+use crate::analysis::informe::Informe;
+use crate::analysis::ranking::{ranking, CriterioRanking, PosicionRanking};
+use crate::analysis::red::RedZonas;
+use crate::analysis::unidades::Unidades;
+use crate::graphs::carpetplot::VariableHoraria;
 pub use crate::parsers::types::TipoObjeto;
-use crate::parsers::{bin::BinData, res::EdificioLIDER, types::FlujosVec};
+use crate::parsers::{
+    bin::BinData,
+    res::EdificioLIDER,
+    types::{FlujosVec, TipoElemento},
+};
+use crate::utils::Error;
 use std::{
     convert::From,
     ffi::OsString,
@@ -25,6 +40,18 @@ pub struct AppState {
     pub curr_zone: String,
     /// Muestra detalle de componentes (cal+, cal-, ref+, ref-, además de calnet y refnet)
     pub show_detail: bool,
+    /// Variable horaria representada en el mapa horario de zona
+    pub carpet_var: VariableHoraria,
+    /// Criterio de clasificación de los elementos
+    pub ranking_criterio: CriterioRanking,
+    /// Tipo de elemento de la clasificación (todos si es None)
+    pub ranking_tipo: Option<TipoElemento>,
+    /// Planta de la clasificación de elementos (todas si es None)
+    pub ranking_planta: Option<String>,
+    /// Unidades de los flujos y demandas
+    pub unidades: Unidades,
+    /// Versión del DB-HE, zona climática y uso del edificio para comprobar su cumplimiento
+    pub dbhe: CriteriosDBHE,
 }
 
 impl AppState {
@@ -63,7 +90,7 @@ impl AppState {
             Some(binfiles) => {
                 match self.filename() {
                     Some(filename) => {
-                        let samename = filename.with_extension(".bin").into_os_string();
+                        let samename = filename.with_extension("bin").into_os_string();
 
                         let mut rccname = OsString::from("ResumenRCC_");
                         rccname.push(&samename);
@@ -101,6 +128,19 @@ impl AppState {
                     if pth.exists() {
                         self.respath = path.clone();
                         self.edificio = EdificioLIDER::from_file(pth).ok();
+                        // Versión del DB-HE del programa que generó el archivo, si se conoce
+                        self.dbhe.version = match self
+                            .edificio
+                            .as_ref()
+                            .and_then(|edificio| edificio.version.dbhe())
+                        {
+                            Some(version) => version.to_string(),
+                            None => CriteriosDBHE::default().version,
+                        };
+                        // Zona climática y uso del proyecto, si se detectan
+                        let (zona, uso) = detectar_zona_uso(pth);
+                        self.dbhe.zona = zona;
+                        self.dbhe.uso = uso;
                         // println!("Cargado edificio: {:#?}", &self.edificio);
                         if let Some(binpath) = self.find_bin() {
                             self.bindata = BinData::from_file(&binpath).ok();
@@ -115,20 +155,80 @@ impl AppState {
         }
     }
 
+    /// Comprueba las demandas del edificio frente a los límites del DB-HE
+    /// No está definido si no se ha cargado el archivo o no se conocen la zona climática y el uso
+    pub fn check_dbhe(&self) -> Result<Option<[Indicador; 2]>, Error> {
+        match &self.edificio {
+            Some(edificio) => comprobar_dbhe(edificio, &self.dbhe),
+            None => Ok(None),
+        }
+    }
+
+    /// Comprueba el balance energético interno de los datos del archivo .res
+    /// No está definido si no se ha cargado el archivo
+    pub fn check_res(&self) -> Option<Informe> {
+        self.edificio.as_ref().map(comprobar_balance_res)
+    }
+
+    /// Comprueba la coherencia entre los datos del archivo .res y del archivo .bin asociado
+    /// No está definido si no se han cargado ambos archivos
+    pub fn check_res_bin(&self) -> Option<Informe> {
+        match (&self.edificio, &self.bindata) {
+            (Some(edificio), Some(bindata)) => Some(comprobar_res_bin(edificio, bindata)),
+            _ => None,
+        }
+    }
+
     /// Devuelve parámetros básicos del objeto de nombre y zona dados
     /// (multiplicador, superficie, calefaccion, refrigeracion)
+    /// Las demandas se expresan en las unidades activas
     pub fn basicdata(&self) -> Option<(i32, f32, f32, f32)> {
         if self.curr_obj_type == TipoObjeto::None {
             return None;
         };
+        let factor = self.unit_factor();
         self.edificio
             .as_ref()
             .map(|e| e.basicdata(self.curr_obj_type as u8, &self.curr_name))
+            .map(|(mul, sup, cal, refr)| (mul, sup, cal * factor, refr * factor))
     }
 
-    /// Datos mensuales de demanda de calefacción y refrigeración
+    /// Factor de conversión de las intensidades del objeto activo a las unidades activas
+    /// Los elementos constructivos usan la superficie de su zona
+    pub fn unit_factor(&self) -> f32 {
+        let edificio = match self.edificio.as_ref() {
+            Some(edificio) => edificio,
+            None => return 1.0,
+        };
+        let sup_zona = |nombre: &str| {
+            edificio
+                .zonas
+                .get(nombre)
+                .map(|z| z.superficie * z.multiplicador as f32)
+                .unwrap_or_default()
+        };
+        let superficie = match self.curr_obj_type {
+            TipoObjeto::Edificio => edificio.superficie,
+            TipoObjeto::Planta => edificio
+                .plantas
+                .iter()
+                .find(|p| p.nombre == self.curr_name)
+                .map(|p| p.superficie(edificio))
+                .unwrap_or_default(),
+            TipoObjeto::Zona => sup_zona(&self.curr_name),
+            TipoObjeto::Elemento | TipoObjeto::Grupo | TipoObjeto::Conjunto => {
+                sup_zona(&self.curr_zone)
+            }
+            TipoObjeto::None => return 1.0,
+        };
+        self.unidades.factor(superficie, edificio.superficie)
+    }
+
+    /// Datos mensuales de demanda de calefacción y refrigeración, en las unidades activas
     /// No está definido para elementos constructivos o sin edificio definido
     pub fn calref_monthly_data(&self) -> (Vec<f32>, Vec<f32>) {
+        let factor = self.unit_factor();
+        let scale = |v: Vec<f32>| -> Vec<f32> { v.into_iter().map(|x| x * factor).collect() };
         match self.curr_obj_type {
             TipoObjeto::Edificio => self
                 .edificio
@@ -145,14 +245,171 @@ impl AppState {
                     .get(&self.curr_name)
                     .map(|z| (z.calefaccion_meses.clone(), z.refrigeracion_meses.clone()))
             }),
-            TipoObjeto::Elemento | TipoObjeto::None => None,
+            TipoObjeto::Elemento | TipoObjeto::Grupo | TipoObjeto::Conjunto | TipoObjeto::None => {
+                None
+            }
         }
+        .map(|(cal, refr)| (scale(cal), scale(refr)))
         .unwrap_or((vec![0.0; 12], vec![0.0; 12]))
     }
 
-    /// Valores de flujos de calor por conceptos
+    /// Nombres de las zonas del objeto activo
+    /// Todas las zonas para el edificio, las zonas de la planta o la zona activa.
+    /// No está definido para elementos constructivos o sin edificio definido
+    pub fn selected_zones(&self) -> Vec<String> {
+        match self.curr_obj_type {
+            TipoObjeto::Edificio => self
+                .edificio
+                .as_ref()
+                .map(|e| e.plantas.iter().flat_map(|p| p.zonas.clone()).collect()),
+            TipoObjeto::Planta => self.edificio.as_ref().and_then(|e| {
+                e.plantas
+                    .iter()
+                    .find(|p| p.nombre == self.curr_name)
+                    .map(|p| p.zonas.clone())
+            }),
+            TipoObjeto::Zona => Some(vec![self.curr_name.clone()]),
+            TipoObjeto::Elemento | TipoObjeto::Grupo | TipoObjeto::Conjunto | TipoObjeto::None => {
+                None
+            }
+        }
+        .unwrap_or_default()
+    }
+
+    /// Planta del objeto activo
+    /// La planta activa, la de la zona activa o la de la zona del elemento activo.
+    pub fn selected_floor(&self) -> Option<String> {
+        let zona = match self.curr_obj_type {
+            TipoObjeto::Planta => return Some(self.curr_name.clone()),
+            TipoObjeto::Zona => &self.curr_name,
+            TipoObjeto::Elemento | TipoObjeto::Grupo | TipoObjeto::Conjunto => &self.curr_zone,
+            TipoObjeto::Edificio | TipoObjeto::None => return None,
+        };
+        self.edificio
+            .as_ref()
+            .and_then(|e| e.zonas.get(zona))
+            .map(|z| z.planta.clone())
+    }
+
+    /// Curvas de duración de carga del objeto activo
+    /// No está definido para elementos constructivos o sin datos horarios (.bin)
+    pub fn duration_curves(&self) -> Option<CurvasDuracion> {
+        let bindata = self.bindata.as_ref()?;
+        let zonas = self.selected_zones();
+        if zonas.is_empty() {
+            return None;
+        }
+        Some(CurvasDuracion::from_zonas(
+            zonas.iter().filter_map(|z| bindata.zonas.get(z)),
+        ))
+    }
+
+    /// Red de adyacencia entre las zonas del edificio
+    /// No está definida sin datos de zonas (.bin)
+    pub fn adjacency_network(&self) -> Option<RedZonas> {
+        self.bindata.as_ref().map(RedZonas::from_bindata)
+    }
+
+    /// Clasificación de los elementos del edificio con el criterio y filtros activos
+    /// Los valores están referidos a la superficie del edificio, salvo en unidades absolutas
+    pub fn element_ranking(&self) -> Vec<PosicionRanking> {
+        self.edificio
+            .as_ref()
+            .map(|e| {
+                let factor = self.unidades.factor(e.superficie, e.superficie);
+                ranking(
+                    e,
+                    self.ranking_criterio,
+                    self.ranking_tipo,
+                    self.ranking_planta.as_deref(),
+                )
+                .into_iter()
+                .map(|p| PosicionRanking {
+                    valor: p.valor * factor,
+                    ..p
+                })
+                .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Elementos de las zonas del objeto activo que contribuyen a un concepto de demanda, en las unidades activas
+    /// No está definido para elementos constructivos o sin edificio definido
+    pub fn concept_contributions(
+        &self,
+        concepto: usize,
+        componente: Componente,
+    ) -> Vec<Contribucion> {
+        let factor = self.unit_factor();
+        self.edificio
+            .as_ref()
+            .map(|e| contribuciones(e, &self.selected_zones(), concepto, componente))
+            .unwrap_or_default()
+            .into_iter()
+            .map(|c| Contribucion {
+                valor: c.valor * factor,
+                ..c
+            })
+            .collect()
+    }
+
+    /// Límites de la escala de los flujos por conceptos, en las unidades activas
+    ///
+    /// Para intensidades se usan los límites de todas las zonas del edificio, para mantener
+    /// la escala al cambiar de objeto, y para valores absolutos los del objeto activo.
+    pub fn concepts_limits(&self, only_net_fluxes: bool) -> (f32, f32) {
+        let edificio = match self.edificio.as_ref() {
+            Some(edificio) => edificio,
+            None => return (-15.0, 15.0),
+        };
+        if self.unidades != Unidades::Absoluta {
+            return edificio.minmaxconceptos(only_net_fluxes);
+        }
+        let flujos = self.concepts_data();
+        let mut valores = vec![flujos.calnet, flujos.refnet];
+        if !only_net_fluxes {
+            valores.extend(vec![
+                flujos.calpos,
+                flujos.calneg,
+                flujos.refpos,
+                flujos.refneg,
+            ]);
+        }
+        minmax(valores.iter().flatten())
+    }
+
+    /// Límites de la escala de las demandas mensuales, en las unidades activas
+    ///
+    /// Para intensidades se usan los límites de todas las zonas del edificio y para valores
+    /// absolutos los del objeto activo.
+    pub fn monthly_limits(&self) -> (f32, f32) {
+        let edificio = match self.edificio.as_ref() {
+            Some(edificio) => edificio,
+            None => return (-15.0, 15.0),
+        };
+        if self.unidades != Unidades::Absoluta {
+            return edificio.minmaxmeses();
+        }
+        let (cal, refr) = self.calref_monthly_data();
+        minmax(cal.iter().chain(refr.iter()))
+    }
+
+    /// Zonas a resaltar en las vistas de todo el edificio
+    /// Las zonas de la planta activa, la zona activa o la zona del elemento activo.
+    pub fn highlighted_zones(&self) -> Vec<String> {
+        match self.curr_obj_type {
+            TipoObjeto::Planta | TipoObjeto::Zona => self.selected_zones(),
+            TipoObjeto::Elemento | TipoObjeto::Grupo | TipoObjeto::Conjunto => {
+                vec![self.curr_zone.clone()]
+            }
+            TipoObjeto::Edificio | TipoObjeto::None => Vec::new(),
+        }
+    }
+
+    /// Valores de flujos de calor por conceptos, en las unidades activas
     /// Cuando no hay selección se devuelve todo a cero
     pub fn concepts_data(&self) -> FlujosVec {
+        let factor = self.unit_factor();
         match self.curr_obj_type {
             TipoObjeto::Edificio => self
                 .edificio
@@ -186,8 +443,29 @@ impl AppState {
                     })
                 })
                 .map(|c| c.to_flows()),
+            TipoObjeto::Grupo => self
+                .edificio
+                .as_ref()
+                .and_then(|e| {
+                    e.zonas.get(&self.curr_zone).and_then(|z| {
+                        TipoElemento::from_grupo(&self.curr_name).map(|t| z.flujos_grupo(t))
+                    })
+                })
+                .map(|c| c.to_flows()),
+            TipoObjeto::Conjunto => self
+                .edificio
+                .as_ref()
+                .and_then(|e| e.zonas.get(&self.curr_zone))
+                .map(|z| z.flujos_conjunto(&self.curr_name))
+                .map(|c| c.to_flows()),
             TipoObjeto::None => None,
         }
+        .map(|flujos| flujos * factor)
         .unwrap_or_default()
     }
 }
+
+/// Valores mínimo y máximo de una serie, incluyendo el cero
+fn minmax<'a>(valores: impl Iterator<Item = &'a f32>) -> (f32, f32) {
+    valores.fold((0.0, 0.0), |(min, max), v| (min.min(*v), max.max(*v)))
+}
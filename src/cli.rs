@@ -0,0 +1,318 @@
+//! Interfaz de línea de comandos de ViSol
+//!
+//! Permite realizar algunas operaciones sin abrir la interfaz gráfica
+
+use std::path::PathBuf;
+
+use crate::analysis::{
+    balance::comprobar_balance_res,
+    consistencia::comprobar_res_bin,
+    rts::{cargas_con_consignas, conductancia, demandas},
+    unidades::Unidades,
+    ventilacion::{balance_edificio, balances, comprobar_ventilacion, BalanceVentilacion},
+};
+use crate::appstate::AppState;
+use crate::config::Config;
+use crate::export::horarios::{export_horarios, Disposicion};
+use crate::export::tablas::export_tablas;
+use crate::parsers::bin::BinData;
+use crate::parsers::met::temperaturas_exteriores;
+use crate::parsers::types::EdificioLIDER;
+use crate::report::export_informe;
+use crate::utils::Error;
+
+const USAGE: &str = "Uso:
+    visol                       Abre la interfaz gráfica
+    visol horarios [--largo] [--zonas ZONA1,ZONA2,...] ARCHIVO.bin SALIDA.csv|SALIDA.parquet
+                                Exporta los datos horarios de las zonas (todas por defecto)
+                                en formato ancho o largo (--largo), a CSV o Parquet según la extensión
+    visol tablas [--unidades intensidad|absoluta|edificio] ARCHIVO.res SALIDA.csv|SALIDA.xlsx
+                                Exporta los flujos por conceptos (edificio, plantas y zonas) y por elementos
+                                a CSV o XLSX (una hoja por nivel) según la extensión, en kWh/m²·año
+                                del objeto (por defecto), kWh/año o kWh/m²·año del edificio
+    visol informe [--zonas] ARCHIVO.res SALIDA.pdf|SALIDA.html
+                                Genera el informe de resultados (edificio y plantas) en PDF o en un
+                                HTML autocontenido según la extensión, con una sección por zona
+                                (--zonas) y datos horarios si hay archivo .bin
+    visol consignas [--cal INC] [--ref INC] [--ganancias W] ARCHIVO.bin
+                                Estima las demandas de las zonas (sensibles) con las consignas de
+                                calefacción y refrigeración desplazadas INC ºC y con W vatios de
+                                ganancias adicionales por zona, usando sus factores de respuesta
+    visol ventilacion ARCHIVO.bin CLIMA.met [ARCHIVO.res]
+                                Calcula caudales, renovaciones hora y balance anual de calor por
+                                ventilación e infiltraciones de las zonas y el edificio, y lo compara
+                                con el concepto de ventilación del archivo .res (si se indica)
+    visol comprobar ARCHIVO.res [ARCHIVO.bin]
+                                Comprueba el balance energético del archivo .res (conceptos, elementos,
+                                demandas mensuales y superficies) y, si se indica el archivo .bin, que
+                                ambos corresponden al mismo cálculo (zonas, superficies, multiplicadores
+                                y demandas). Termina con código 2 si se detectan errores
+    visol ayuda                 Muestra esta ayuda";
+
+/// Ejecuta la orden indicada en los argumentos de la línea de comandos
+///
+/// Devuelve None si no se ha indicado ninguna orden (se lanza la interfaz gráfica)
+/// o el código de salida del programa en caso contrario.
+pub fn run(args: &[String]) -> Option<i32> {
+    let res = match args.get(1)?.as_str() {
+        "horarios" => cmd_horarios(&args[2..]),
+        "tablas" => cmd_tablas(&args[2..]),
+        "informe" => cmd_informe(&args[2..]),
+        "consignas" => cmd_consignas(&args[2..]),
+        "ventilacion" => cmd_ventilacion(&args[2..]),
+        "comprobar" => match cmd_comprobar(&args[2..]) {
+            Ok(false) => return Some(2),
+            res => res.map(|_| ()),
+        },
+        "ayuda" | "-h" | "--help" => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        _ => return None,
+    };
+    match res {
+        Ok(_) => Some(0),
+        Err(e) => {
+            eprintln!("Error: {}\n\n{}", e, USAGE);
+            Some(1)
+        }
+    }
+}
+
+/// Exporta datos horarios de zonas de un archivo .bin
+fn cmd_horarios(args: &[String]) -> Result<(), Error> {
+    let mut disposicion = Disposicion::Ancha;
+    let mut zonas = Vec::new();
+    let mut paths = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--largo" => disposicion = Disposicion::Larga,
+            "--zonas" => {
+                zonas = args
+                    .next()
+                    .ok_or("Falta la lista de zonas")?
+                    .split(',')
+                    .map(|z| z.trim().to_string())
+                    .collect()
+            }
+            _ => paths.push(arg),
+        }
+    }
+    match paths.as_slice() {
+        [binpath, outpath] => {
+            let bindata = BinData::from_file(binpath)?;
+            for zona in export_horarios(&bindata, &zonas, disposicion, outpath)? {
+                eprintln!("Aviso: zona sin datos horarios: {}", zona);
+            }
+            println!("Exportados datos horarios: {}", outpath);
+            Ok(())
+        }
+        _ => Err("Número incorrecto de argumentos".into()),
+    }
+}
+
+/// Exporta las tablas de flujos por conceptos y elementos de un archivo .res
+fn cmd_tablas(args: &[String]) -> Result<(), Error> {
+    let mut unidades = Unidades::default();
+    let mut paths = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--unidades" => unidades = args.next().ok_or("Faltan las unidades")?.parse()?,
+            _ => paths.push(arg),
+        }
+    }
+    match paths.as_slice() {
+        [respath, outpath] => {
+            let edificio = EdificioLIDER::from_file(respath)?;
+            export_tablas(&edificio, unidades, outpath)?;
+            println!("Exportadas tablas de flujos: {}", outpath);
+            Ok(())
+        }
+        _ => Err("Número incorrecto de argumentos".into()),
+    }
+}
+
+/// Genera el informe de resultados en PDF o HTML de un archivo .res (y su .bin, si existe)
+fn cmd_informe(args: &[String]) -> Result<(), Error> {
+    let mut incluir_zonas = false;
+    let mut paths = Vec::new();
+    for arg in args {
+        match arg.as_str() {
+            "--zonas" => incluir_zonas = true,
+            _ => paths.push(arg),
+        }
+    }
+    match paths.as_slice() {
+        [respath, outpath] => {
+            let mut state = AppState::new();
+            state.load_data(&Some(PathBuf::from(respath)));
+            if state.edificio.is_none() {
+                return Err(
+                    format!("No se ha podido leer el archivo de resultados: {}", respath).into(),
+                );
+            }
+            export_informe(&state, &Config::default(), incluir_zonas, outpath)?;
+            println!("Generado informe de resultados: {}", outpath);
+            Ok(())
+        }
+        _ => Err("Número incorrecto de argumentos".into()),
+    }
+}
+
+/// Estima las demandas sensibles de las zonas de un archivo .bin con consignas y ganancias alternativas
+fn cmd_consignas(args: &[String]) -> Result<(), Error> {
+    let (mut inc_cal, mut inc_ref, mut ganancias) = (0.0f32, 0.0f32, 0.0f32);
+    let mut paths = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || -> Result<f32, Error> {
+            let value = args.next().ok_or(format!("Falta el valor de {}", arg))?;
+            value
+                .parse()
+                .map_err(|_| format!("Valor numérico incorrecto para {}: {}", arg, value).into())
+        };
+        match arg.as_str() {
+            "--cal" => inc_cal = value()?,
+            "--ref" => inc_ref = value()?,
+            "--ganancias" => ganancias = value()?,
+            _ => paths.push(arg),
+        }
+    }
+    let binpath = match paths.as_slice() {
+        [binpath] => binpath,
+        _ => return Err("Número incorrecto de argumentos".into()),
+    };
+    let bindata = BinData::from_file(binpath)?;
+
+    println!(
+        "Consignas de calefacción {:+.1} ºC, de refrigeración {:+.1} ºC y ganancias {:+.0} W por zona",
+        inc_cal, inc_ref, ganancias
+    );
+    println!("Demandas sensibles [kWh/año] (incluidos multiplicadores)");
+    println!(
+        "{:<20} {:>10} {:>12} {:>12} {:>12} {:>12}",
+        "Zona", "K [W/K]", "Cal. orig.", "Cal. nueva", "Ref. orig.", "Ref. nueva"
+    );
+    let mut total = [0.0f32; 4];
+    for zona in bindata.zonas.values() {
+        let t_min: Vec<f32> = zona.t_min.iter().map(|t| t + inc_cal).collect();
+        let t_max: Vec<f32> = zona.t_max.iter().map(|t| t + inc_ref).collect();
+        let cargas = cargas_con_consignas(zona, &t_min, &t_max, &vec![ganancias; t_min.len()])?;
+        let mult = zona.multiplicador as f32;
+        let (cal_orig, ref_orig) = demandas(&zona.q_sen);
+        let (cal_nueva, ref_nueva) = demandas(&cargas);
+        let demandas = [
+            cal_orig * mult,
+            cal_nueva * mult,
+            ref_orig * mult,
+            ref_nueva * mult,
+        ];
+        println!(
+            "{:<20} {:>10.1} {:>12.1} {:>12.1} {:>12.1} {:>12.1}",
+            zona.nombre,
+            conductancia(zona),
+            demandas[0],
+            demandas[1],
+            demandas[2],
+            demandas[3]
+        );
+        total
+            .iter_mut()
+            .zip(demandas.iter())
+            .for_each(|(t, d)| *t += d);
+    }
+    println!(
+        "{:<20} {:>10} {:>12.1} {:>12.1} {:>12.1} {:>12.1}",
+        "TOTAL", "", total[0], total[1], total[2], total[3]
+    );
+    Ok(())
+}
+
+/// Balance de calor por ventilación e infiltraciones de las zonas de un archivo .bin
+fn cmd_ventilacion(args: &[String]) -> Result<(), Error> {
+    let (binpath, metpath, respath) = match args {
+        [binpath, metpath] => (binpath, metpath, None),
+        [binpath, metpath, respath] => (binpath, metpath, Some(respath)),
+        _ => return Err("Número incorrecto de argumentos".into()),
+    };
+    let bindata = BinData::from_file(binpath)?;
+    let t_ext = temperaturas_exteriores(metpath)?;
+    let edificio = respath.map(EdificioLIDER::from_file).transpose()?;
+
+    let balances = balances(&bindata, &t_ext)?;
+    let total = balance_edificio(&balances);
+
+    println!("Balance de calor por ventilación e infiltraciones (sin multiplicadores, salvo el edificio)");
+    println!(
+        "{:<20} {:>10} {:>10} {:>8} {:>12} {:>12} {:>12} {:>12}",
+        "Zona",
+        "V [m³]",
+        "q [m³/h]",
+        "n [1/h]",
+        "Gan. [kWh]",
+        "Pérd. [kWh]",
+        "Neto [kWh/m²]",
+        ".res [kWh/m²]"
+    );
+    for balance in balances.iter().chain(std::iter::once(&total)) {
+        let neto_res = edificio.as_ref().and_then(|e| {
+            if balance.nombre == total.nombre {
+                Some(e.conceptos().vent.calnet + e.conceptos().vent.refnet)
+            } else {
+                e.zonas
+                    .get(&balance.nombre)
+                    .map(BalanceVentilacion::neto_res)
+            }
+        });
+        println!(
+            "{:<20} {:>10.1} {:>10.1} {:>8.2} {:>12.1} {:>12.1} {:>12.2} {:>12}",
+            balance.nombre,
+            balance.volumen,
+            balance.caudal_medio.0,
+            balance.renovaciones.0,
+            balance.ganancias,
+            balance.perdidas,
+            balance.neto_m2(),
+            neto_res
+                .map(|v| format!("{:.2}", v))
+                .unwrap_or_else(|| "-".to_string())
+        );
+    }
+    if let Some(edificio) = &edificio {
+        println!();
+        print!("{}", comprobar_ventilacion(edificio, &balances));
+    }
+    Ok(())
+}
+
+/// Comprueba el balance energético de un archivo .res y su coherencia con un archivo .bin
+///
+/// Devuelve si los datos son correctos (sin errores en ninguno de los informes)
+fn cmd_comprobar(args: &[String]) -> Result<bool, Error> {
+    let (respath, binpath) = match args {
+        [respath] => (respath, None),
+        [respath, binpath] => (respath, Some(binpath)),
+        _ => return Err("Número incorrecto de argumentos".into()),
+    };
+    let edificio = EdificioLIDER::from_file(respath)?;
+    let informe = comprobar_balance_res(&edificio);
+    print!("{}", informe);
+    let mut correcto = informe.is_ok();
+    if correcto {
+        println!("El balance energético del archivo .res es correcto");
+    }
+    if let Some(binpath) = binpath {
+        let bindata = BinData::from_file(binpath)?;
+        let informe = comprobar_res_bin(&edificio, &bindata);
+        println!();
+        print!("{}", informe);
+        if informe.is_ok() {
+            println!("Los archivos corresponden al mismo cálculo");
+        } else {
+            correcto = false;
+        }
+    }
+    Ok(correcto)
+}
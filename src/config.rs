@@ -1,22 +1,60 @@
-// TODO: implementar serialización y / o lectura desde disco ui/visol.cfg
-
 //! Configuración de la aplicación ViSOL
+//!
+//! La configuración se guarda en el directorio de configuración del usuario como un archivo de
+//! texto con líneas clave=valor, con el mismo formato que res/visol.cfg
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::graphs::style::{Palette, Theme};
+use crate::utils::Error;
 
 /// Datos de configuración de la aplicación
 #[derive(Debug, Clone)]
 pub struct Config {
-    /// Límite automático de la demanda
+    /// Límite automático de las escalas de las gráficas
+    ///
+    /// Sin límite automático cada gráfica usa los límites fijos de su escala
     pub autolimits: bool,
-    /// Límite superior de las escalas
+    /// Límite superior de la escala de demandas anuales (sin límite automático)
     pub maxlimit: i32,
-    /// Límite inferior de las escalas
+    /// Límite inferior de la escala de demandas anuales (sin límite automático)
     pub minlimit: i32,
+    /// Límite superior de la escala de demandas mensuales (sin límite automático)
+    pub maxlimit_meses: i32,
+    /// Límite inferior de la escala de demandas mensuales (sin límite automático)
+    pub minlimit_meses: i32,
+    /// Límite superior de la escala de cargas térmicas, en kW (sin límite automático)
+    pub maxlimit_cargas: i32,
+    /// Límite inferior de la escala de cargas térmicas, en kW (sin límite automático)
+    pub minlimit_cargas: i32,
+    /// Límite superior de la escala de temperaturas, en ºC (sin límite automático)
+    pub maxlimit_temp: i32,
+    /// Límite inferior de la escala de temperaturas, en ºC (sin límite automático)
+    pub minlimit_temp: i32,
+    /// Límite superior de la escala de caudales, en m³/h (sin límite automático)
+    pub maxlimit_caudal: i32,
+    /// Límite inferior de la escala de caudales, en m³/h (sin límite automático)
+    pub minlimit_caudal: i32,
+    /// Porcentaje mínimo de las cuñas de las gráficas de tarta (las menores se agrupan en "Otros")
+    pub pie_min_pct: f64,
+    /// Paleta de colores de las gráficas
+    pub palette: Palette,
+    /// Tema de las gráficas (claro, oscuro o según el tema de GTK)
+    pub theme: Theme,
+    /// Familia tipográfica de las gráficas
+    pub font: String,
+    /// Factor de escala de los textos de las gráficas
+    pub font_scale: f64,
     /// Resolución de salida de los pantallazos
     pub out_dpi: i32,
     /// Formato de fecha/hora de los pantallazos
     pub out_fmt: String,
     /// Nombre base de los pantallazos
     pub out_basename: String,
+    /// Último directorio en el que se ha guardado un pantallazo
+    pub out_dir: Option<PathBuf>,
 }
 
 impl Default for Config {
@@ -25,9 +63,271 @@ impl Default for Config {
             autolimits: true,
             maxlimit: 50,
             minlimit: -150,
+            maxlimit_meses: 10,
+            minlimit_meses: -30,
+            maxlimit_cargas: 10,
+            minlimit_cargas: -10,
+            maxlimit_temp: 35,
+            minlimit_temp: 10,
+            maxlimit_caudal: 500,
+            minlimit_caudal: 0,
+            pie_min_pct: 3.0,
+            palette: Palette::default(),
+            theme: Theme::default(),
+            font: "Arial".into(),
+            font_scale: 1.0,
             out_dpi: 150,
             out_fmt: "%Y%m%d_%H%M%S".into(),
             out_basename: "ViSol".into(),
+            out_dir: None,
+        }
+    }
+}
+
+impl Config {
+    /// Ruta del archivo de configuración del usuario
+    pub fn user_path() -> Option<PathBuf> {
+        glib::get_user_config_dir().map(|dir| dir.join("visol").join("visol.cfg"))
+    }
+
+    /// Lee la configuración desde el archivo indicado
+    pub fn load<T: AsRef<Path>>(path: T) -> Result<Self, Error> {
+        let path = path.as_ref();
+        std::fs::read_to_string(path)
+            .map_err(|e| format!("No se ha podido leer {}: {}", path.display(), e))?
+            .parse()
+    }
+
+    /// Guarda la configuración en el archivo indicado, creando su directorio si no existe
+    pub fn save<T: AsRef<Path>>(&self, path: T) -> Result<(), Error> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, self.to_string())
+            .map_err(|e| format!("No se ha podido guardar {}: {}", path.display(), e).into())
+    }
+}
+
+/// Interpreta el valor de una clave de la configuración
+fn valor<T: FromStr>(clave: &str, valor: &str) -> Result<T, Error>
+where
+    T::Err: fmt::Display,
+{
+    valor
+        .parse()
+        .map_err(|e| format!("Valor incorrecto de {} ({}): {}", clave, valor, e).into())
+}
+
+impl FromStr for Config {
+    type Err = Error;
+
+    /// Lee la configuración a partir de líneas clave=valor
+    ///
+    /// Se ignoran las líneas vacías, los comentarios (#) y las claves desconocidas. Las claves
+    /// ausentes mantienen su valor por defecto.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut config = Config::default();
+        for line in s.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut partes = line.splitn(2, '=');
+            let clave = partes.next().unwrap_or_default().trim();
+            let v = partes
+                .next()
+                .ok_or_else(|| format!("Línea de configuración sin valor: {}", line))?
+                .trim();
+            match clave {
+                "autolimits" => {
+                    config.autolimits = match v.to_lowercase().as_str() {
+                        "true" => true,
+                        "false" => false,
+                        _ => return Err(format!("Valor incorrecto de autolimits: {}", v).into()),
+                    }
+                }
+                "maxlimit" => config.maxlimit = valor(clave, v)?,
+                "minlimit" => config.minlimit = valor(clave, v)?,
+                "maxlimit_meses" => config.maxlimit_meses = valor(clave, v)?,
+                "minlimit_meses" => config.minlimit_meses = valor(clave, v)?,
+                "maxlimit_cargas" => config.maxlimit_cargas = valor(clave, v)?,
+                "minlimit_cargas" => config.minlimit_cargas = valor(clave, v)?,
+                "maxlimit_temp" => config.maxlimit_temp = valor(clave, v)?,
+                "minlimit_temp" => config.minlimit_temp = valor(clave, v)?,
+                "maxlimit_caudal" => config.maxlimit_caudal = valor(clave, v)?,
+                "minlimit_caudal" => config.minlimit_caudal = valor(clave, v)?,
+                "pie_min_pct" => config.pie_min_pct = valor(clave, v)?,
+                "palette" => config.palette = valor(clave, v)?,
+                "theme" => config.theme = valor(clave, v)?,
+                "font" => config.font = v.to_string(),
+                "font_scale" => config.font_scale = valor(clave, v)?,
+                "out_dpi" => config.out_dpi = valor(clave, v)?,
+                "out_fmt" => config.out_fmt = v.to_string(),
+                "out_basename" => config.out_basename = v.to_string(),
+                "out_dir" if v.is_empty() => config.out_dir = None,
+                "out_dir" => config.out_dir = Some(PathBuf::from(v)),
+                _ => (),
+            }
+        }
+        Ok(config)
+    }
+}
+
+impl fmt::Display for Config {
+    /// Escribe la configuración como líneas clave=valor
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "# Archivo de configuración de ViSoL")?;
+        let autolimits = if self.autolimits { "True" } else { "False" };
+        writeln!(f, "autolimits={}", autolimits)?;
+        writeln!(f, "maxlimit={}", self.maxlimit)?;
+        writeln!(f, "minlimit={}", self.minlimit)?;
+        writeln!(f, "maxlimit_meses={}", self.maxlimit_meses)?;
+        writeln!(f, "minlimit_meses={}", self.minlimit_meses)?;
+        writeln!(f, "maxlimit_cargas={}", self.maxlimit_cargas)?;
+        writeln!(f, "minlimit_cargas={}", self.minlimit_cargas)?;
+        writeln!(f, "maxlimit_temp={}", self.maxlimit_temp)?;
+        writeln!(f, "minlimit_temp={}", self.minlimit_temp)?;
+        writeln!(f, "maxlimit_caudal={}", self.maxlimit_caudal)?;
+        writeln!(f, "minlimit_caudal={}", self.minlimit_caudal)?;
+        writeln!(f, "pie_min_pct={}", self.pie_min_pct)?;
+        writeln!(f, "palette={}", self.palette)?;
+        writeln!(f, "theme={}", self.theme)?;
+        writeln!(f, "font={}", self.font)?;
+        writeln!(f, "font_scale={}", self.font_scale)?;
+        writeln!(f, "out_dpi={}", self.out_dpi)?;
+        writeln!(f, "out_fmt={}", self.out_fmt)?;
+        writeln!(f, "out_basename={}", self.out_basename)?;
+        match &self.out_dir {
+            Some(dir) => writeln!(f, "out_dir={}", dir.display()),
+            None => writeln!(f, "out_dir="),
         }
     }
 }
+
+/// Escalas del eje vertical de las gráficas con límites configurables
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Escala {
+    /// Demandas anuales (por conceptos y por zonas)
+    Anual,
+    /// Demandas mensuales
+    Mensual,
+    /// Cargas térmicas [kW]
+    Cargas,
+    /// Temperaturas [ºC]
+    Temperaturas,
+    /// Caudales [m³/h]
+    Caudales,
+}
+
+impl Config {
+    /// Límites fijos de la escala indicada, o None con límite automático
+    pub fn fixed_limits(&self, escala: Escala) -> Option<(f32, f32)> {
+        if self.autolimits {
+            return None;
+        }
+        let (min, max) = match escala {
+            Escala::Anual => (self.minlimit, self.maxlimit),
+            Escala::Mensual => (self.minlimit_meses, self.maxlimit_meses),
+            Escala::Cargas => (self.minlimit_cargas, self.maxlimit_cargas),
+            Escala::Temperaturas => (self.minlimit_temp, self.maxlimit_temp),
+            Escala::Caudales => (self.minlimit_caudal, self.maxlimit_caudal),
+        };
+        Some((min.min(max) as f32, max.max(min) as f32))
+    }
+
+    /// Límites de la escala indicada a partir de los límites de los datos
+    ///
+    /// Con límite automático se usan los límites de los datos y en otro caso los límites fijos de
+    /// la configuración. Las gráficas recortan los valores que quedan fuera de la escala.
+    pub fn limits(&self, escala: Escala, min: f32, max: f32) -> (f32, f32) {
+        self.fixed_limits(escala).unwrap_or((min, max))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ESCALAS: [Escala; 5] = [
+        Escala::Anual,
+        Escala::Mensual,
+        Escala::Cargas,
+        Escala::Temperaturas,
+        Escala::Caudales,
+    ];
+
+    #[test]
+    fn limites_automaticos() {
+        let config = Config::default();
+        for escala in &ESCALAS {
+            assert_eq!(config.fixed_limits(*escala), None);
+            assert_eq!(config.limits(*escala, -1234.5, 6789.0), (-1234.5, 6789.0));
+        }
+    }
+
+    #[test]
+    fn limites_fijos() {
+        let config = Config {
+            autolimits: false,
+            ..Config::default()
+        };
+        let esperados = [
+            (-150.0, 50.0),
+            (-30.0, 10.0),
+            (-10.0, 10.0),
+            (10.0, 35.0),
+            (0.0, 500.0),
+        ];
+        for (escala, esperado) in ESCALAS.iter().zip(esperados.iter()) {
+            // Los datos fuera de los límites fijos no los amplían
+            assert_eq!(config.limits(*escala, -1234.5, 6789.0), *esperado);
+            assert_eq!(config.limits(*escala, -0.5, 0.5), *esperado);
+        }
+        // Límites invertidos en la configuración
+        let config = Config {
+            maxlimit: -150,
+            minlimit: 50,
+            ..config
+        };
+        assert_eq!(config.limits(Escala::Anual, 0.0, 1.0), (-150.0, 50.0));
+    }
+
+    #[test]
+    fn guardar_y_leer() {
+        let config = Config {
+            autolimits: false,
+            maxlimit_caudal: 800,
+            palette: Palette::ColorBlind,
+            theme: Theme::Dark,
+            font_scale: 1.25,
+            out_basename: "Captura = ViSol".into(),
+            out_dir: Some(std::env::temp_dir().join("visol capturas")),
+            ..Config::default()
+        };
+        let path = std::env::temp_dir()
+            .join(format!("visol-test-{}", std::process::id()))
+            .join("visol.cfg");
+        config.save(&path).unwrap();
+        let leida = Config::load(&path).unwrap();
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+        assert_eq!(leida.to_string(), config.to_string());
+        assert_eq!(leida.out_dir, config.out_dir);
+        assert_eq!(leida.out_basename, "Captura = ViSol");
+        assert_eq!(leida.palette, Palette::ColorBlind);
+        assert_eq!(leida.limits(Escala::Caudales, 0.0, 1.0), (0.0, 800.0));
+        // Sin directorio de capturas
+        let leida: Config = Config::default().to_string().parse().unwrap();
+        assert_eq!(leida.out_dir, None);
+    }
+
+    #[test]
+    fn leer_archivo_ejemplo() {
+        let path = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/res/visol.cfg");
+        let config = Config::load(&path).unwrap();
+        assert!(config.autolimits);
+        assert_eq!((config.minlimit, config.maxlimit), (-150, 50));
+        assert_eq!(config.out_basename, "ViSol");
+        assert!("maxlimit=mucho".parse::<Config>().is_err());
+        assert!("autolimits=Quizás".parse::<Config>().is_err());
+    }
+}
@@ -0,0 +1,46 @@
+# Archivo de prueba mnimo con el formato de HULC 2018
+Numero de plantas
+1
+"P01"
+
+RESULTADOS A NIVEL DE ZONAS
+Numero de zonas
+1
+Zona 1, "P01_E01"
+50.000000
+Concepto, Cal_positivo (kWh/m2 del espacio), Cal_negativo (kWh/m2 del espacio), Cal_neto (kWh/m2 del espacio), Ref_positivo (kWh/m2 del espacio), Ref_negativo (kWh/m2 del espacio), Ref_neto (kWh/m2 del espacio)
+Paredes Exteriores, 0.088976, -7.583841, -7.494865, 3.856611, -0.989612, 2.866998
+Cubiertas, 0.000000, 0.000000, 0.000000, 0.000000, 0.000000, 0.000000
+Suelos, 0.406834, -7.804407, -7.397573, 3.895575, -5.341285, -1.445710
+Puentes Trmicos, 0.147165, -1.928817, -1.781653, 0.358106, -0.195567, 0.162539
+Solar Ventanas, 0.000000, 0.000000, 0.000000, 0.000000, 0.000000, 0.000000
+Transmisin Ventanas, 0.000000, 0.000000, 0.000000, 0.000000, 0.000000, 0.000000
+Fuentes Internas, 21.984842, 0.000000, 21.984842, 9.563227, 0.000000, 9.563227
+Ventilacin ms Infiltracin, 0.041854, -19.526559, -19.484705, 1.450676, -9.240917, -7.790241
+TOTAL, 22.669671, -36.843624, -14.173953, 19.124195, -15.767381, 3.356814
+
+Numero de Componentes
+2
+Componente, Cal_positivo (kWh/m2 del edificio), Cal_negativo (kWh/m2 del edificio), Cal_neto (kWh/m2 del edificio), Ref_positivo (kWh/m2 del edificio), Ref_negativo (kWh/m2 del edificio), Ref_neto (kWh/m2 del edificio)
+"P01_E01_PE001", 0.088976, -7.583841, -7.494865, 3.856611, -0.989612, 2.866998
+"P01_E01_FTER001", 0.406834, -7.804407, -7.397573, 3.895575, -5.341285, -1.445710
+
+RESULTADOS A NIVEL EDIFICIO
+Calefaccin anual (kWh/m2 del edificio), Refrigeracin anual (kWh/m2 del edificio)
+-14.173953, 3.356814
+Calefaccin mensual (kWh/m2 del edificio)
+-3.531873, -2.483930, -1.506210, -0.595257, -0.219823, 0.000000, 0.000000, 0.000000, 0.000000, -0.191381, -2.134926, -3.510553
+Refrigeracin mensual (kWh/m2 del edificio)
+0.000000, 0.000000, 0.000000, 0.000000, 0.000000, 0.345432, 1.178216, 1.269423, 0.563743, 0.000000, 0.000000, 0.000000
+
+Numero de zonas
+1
+Nombre, m2, multiplicador, Calefaccin (kWh/m2 del espacio), Refrigeracin (kWh/m2 del espacio)
+"P01_E01", 50.000000, 1.000000, -14.173953, 3.356814
+TOTAL, 50.000000, -14.173953, 3.356814
+
+Calefaccin mensual por zonas (kWh/m2 del espacio)
+-3.531873, -2.483930, -1.506210, -0.595257, -0.219823, 0.000000, 0.000000, 0.000000, 0.000000, -0.191381, -2.134926, -3.510553
+
+Refrigeracin mensual por zonas (kWh/m2 del espacio)
+0.000000, 0.000000, 0.000000, 0.000000, 0.000000, 0.345432, 1.178216, 1.269423, 0.563743, 0.000000, 0.000000, 0.000000
@@ -0,0 +1,435 @@
+//! Exportación de datos horarios de zonas (.bin) a CSV y Parquet
+//!
+//! Se exportan, para las zonas indicadas, las series horarias de temperatura (real, consignas),
+//! cargas (sensible, latente), caudal de ventilación e infiltraciones y disponibilidad de cal. y ref.
+//!
+//! Los datos pueden exportarse en:
+//! - formato ancho: una fila por hora y una columna por zona y variable
+//! - formato largo (tidy): una fila por hora, zona y variable (fecha, zona, variable, unidad, valor)
+
+use std::{fmt::Write, fs::File, path::Path, sync::Arc};
+
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+use parquet::{
+    basic::{ConvertedType, Repetition, Type as PhysicalType},
+    data_type::{ByteArray, ByteArrayType, FloatType, Int64Type},
+    file::{properties::WriterProperties, writer::SerializedFileWriter},
+    schema::types::Type,
+};
+
+use super::{campo_csv, Formato};
+use crate::parsers::bin::{BinData, ZonaLider};
+use crate::utils::Error;
+
+/// Año de referencia para las marcas de tiempo (año no bisiesto de 8760 horas)
+///
+/// Los archivos .bin corresponden a un año tipo sin fecha, que comienza el 1 de enero a las 0:00
+const ANYO_REFERENCIA: i32 = 2001;
+
+/// Variable horaria exportada
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Variable {
+    /// Temperatura del local [ºC]
+    TReal,
+    /// Temperatura de consigna alta [ºC]
+    TMax,
+    /// Temperatura de consigna baja [ºC]
+    TMin,
+    /// Carga sensible [W]
+    QSen,
+    /// Carga latente [W]
+    QLat,
+    /// Caudal de ventilación e infiltraciones [kg/s]
+    VVentInf,
+    /// Disponibilidad de calefacción (1|0 para on|off)
+    DaCal,
+    /// Disponibilidad de refrigeración (1|0 para on|off)
+    DaRef,
+}
+
+/// Variables horarias exportadas, en el orden de exportación
+pub const VARIABLES: [Variable; 8] = [
+    Variable::TReal,
+    Variable::TMax,
+    Variable::TMin,
+    Variable::QSen,
+    Variable::QLat,
+    Variable::VVentInf,
+    Variable::DaCal,
+    Variable::DaRef,
+];
+
+impl Variable {
+    /// Nombre de la variable en los datos exportados
+    pub fn nombre(self) -> &'static str {
+        match self {
+            Self::TReal => "t_real",
+            Self::TMax => "t_max",
+            Self::TMin => "t_min",
+            Self::QSen => "q_sen",
+            Self::QLat => "q_lat",
+            Self::VVentInf => "v_ventinf",
+            Self::DaCal => "da_cal",
+            Self::DaRef => "da_ref",
+        }
+    }
+
+    /// Unidades de la variable
+    pub fn unidad(self) -> &'static str {
+        match self {
+            Self::TReal | Self::TMax | Self::TMin => "ºC",
+            Self::QSen | Self::QLat => "W",
+            Self::VVentInf => "kg/s",
+            Self::DaCal | Self::DaRef => "-",
+        }
+    }
+
+    /// Serie horaria de la variable en la zona
+    fn serie(self, zona: &ZonaLider) -> Vec<f32> {
+        match self {
+            Self::TReal => zona.t_real.clone(),
+            Self::TMax => zona.t_max.clone(),
+            Self::TMin => zona.t_min.clone(),
+            Self::QSen => zona.q_sen.clone(),
+            Self::QLat => zona.q_lat.clone(),
+            Self::VVentInf => zona.v_ventinf.clone(),
+            Self::DaCal => zona.da_cal.iter().map(|v| *v as f32).collect(),
+            Self::DaRef => zona.da_ref.iter().map(|v| *v as f32).collect(),
+        }
+    }
+}
+
+/// Disposición de los datos exportados
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Disposicion {
+    /// Una fila por hora y una columna por zona y variable
+    Ancha,
+    /// Una fila por hora, zona y variable (tidy)
+    Larga,
+}
+
+/// Marcas de tiempo de inicio de cada hora del año de referencia
+pub fn timestamps(nhoras: usize) -> Vec<NaiveDateTime> {
+    let start = NaiveDate::from_ymd_opt(ANYO_REFERENCIA, 1, 1)
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .unwrap();
+    (0..nhoras)
+        .map(|h| start + Duration::hours(h as i64))
+        .collect()
+}
+
+/// Zonas a exportar, en el orden indicado, y zonas indicadas que no están en los datos horarios
+///
+/// Si no se indica ninguna zona se exportan todas. Las zonas sin datos horarios se omiten, como
+/// en las curvas de duración.
+fn select_zonas<'a>(
+    bindata: &'a BinData,
+    zonas: &[String],
+) -> Result<(Vec<&'a ZonaLider>, Vec<String>), Error> {
+    if zonas.is_empty() {
+        return Ok((bindata.zonas.values().collect(), Vec::new()));
+    }
+    let (presentes, omitidas): (Vec<_>, Vec<_>) =
+        zonas.iter().partition(|z| bindata.zonas.contains_key(*z));
+    if presentes.is_empty() {
+        return Err(format!(
+            "No se encuentra ninguna de las zonas en los datos horarios: {}",
+            zonas.join(", ")
+        )
+        .into());
+    }
+    Ok((
+        presentes.iter().map(|z| &bindata.zonas[*z]).collect(),
+        omitidas.into_iter().cloned().collect(),
+    ))
+}
+
+/// Exporta los datos horarios de las zonas indicadas al archivo path
+///
+/// El formato (CSV o Parquet) se deduce de la extensión del archivo (.csv o .parquet).
+/// Si no se indica ninguna zona se exportan todas las del archivo .bin.
+///
+/// Devuelve las zonas indicadas que se han omitido por no estar en los datos horarios.
+pub fn export_horarios<S: AsRef<Path>>(
+    bindata: &BinData,
+    zonas: &[String],
+    disposicion: Disposicion,
+    path: S,
+) -> Result<Vec<String>, Error> {
+    let path = path.as_ref();
+    let (_, omitidas) = select_zonas(bindata, zonas)?;
+    match Formato::from_path(path) {
+        Formato::Csv => std::fs::write(path, to_csv(bindata, zonas, disposicion)?)?,
+        Formato::Parquet => to_parquet(bindata, zonas, disposicion, File::create(path)?)?,
+        Formato::Xlsx => {
+            return Err(
+                "Formato no disponible para los datos horarios (use .csv o .parquet)".into(),
+            )
+        }
+    };
+    Ok(omitidas)
+}
+
+/// Datos horarios de las zonas en formato CSV
+///
+/// Se omiten las zonas sin datos horarios
+pub fn to_csv(
+    bindata: &BinData,
+    zonas: &[String],
+    disposicion: Disposicion,
+) -> Result<String, Error> {
+    let (zonas, _) = select_zonas(bindata, zonas)?;
+    let nhoras = zonas.first().map(|z| z.t_real.len()).unwrap_or(0);
+    let fechas: Vec<String> = timestamps(nhoras)
+        .iter()
+        .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+        .collect();
+
+    let mut out = String::new();
+    match disposicion {
+        Disposicion::Ancha => {
+            let mut series = Vec::with_capacity(zonas.len() * VARIABLES.len());
+            out.push_str("fecha");
+            for zona in &zonas {
+                for variable in VARIABLES.iter() {
+                    let columna = format!(
+                        "{}:{} [{}]",
+                        zona.nombre,
+                        variable.nombre(),
+                        variable.unidad()
+                    );
+                    write!(out, ",{}", campo_csv(&columna))?;
+                    series.push(variable.serie(zona));
+                }
+            }
+            out.push('\n');
+            for (i, fecha) in fechas.iter().enumerate() {
+                out.push_str(fecha);
+                for serie in &series {
+                    write!(out, ",{}", serie[i])?;
+                }
+                out.push('\n');
+            }
+        }
+        Disposicion::Larga => {
+            out.push_str("fecha,zona,variable,unidad,valor\n");
+            for zona in &zonas {
+                let nombre = campo_csv(&zona.nombre);
+                for variable in VARIABLES.iter() {
+                    for (fecha, valor) in fechas.iter().zip(variable.serie(zona)) {
+                        writeln!(
+                            out,
+                            "{},{},{},{},{}",
+                            fecha,
+                            nombre,
+                            variable.nombre(),
+                            variable.unidad(),
+                            valor
+                        )?;
+                    }
+                }
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// Escribe los datos horarios de las zonas en formato Parquet
+///
+/// Las fechas se guardan como marcas de tiempo en milisegundos (sin zona horaria) y se omiten las
+/// zonas sin datos horarios
+pub fn to_parquet<W: std::io::Write + Send>(
+    bindata: &BinData,
+    zonas: &[String],
+    disposicion: Disposicion,
+    writer: W,
+) -> Result<(), Error> {
+    let (zonas, _) = select_zonas(bindata, zonas)?;
+    let nhoras = zonas.first().map(|z| z.t_real.len()).unwrap_or(0);
+    let epoch = NaiveDate::from_ymd_opt(1970, 1, 1)
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .unwrap();
+    let millis: Vec<i64> = timestamps(nhoras)
+        .iter()
+        .map(|t| (*t - epoch).num_milliseconds())
+        .collect();
+
+    let fecha_field = Type::primitive_type_builder("fecha", PhysicalType::INT64)
+        .with_repetition(Repetition::REQUIRED)
+        .with_converted_type(ConvertedType::TIMESTAMP_MILLIS)
+        .build()?;
+    let float_field = |name: &str| {
+        Type::primitive_type_builder(name, PhysicalType::FLOAT)
+            .with_repetition(Repetition::REQUIRED)
+            .build()
+    };
+    let str_field = |name: &str| {
+        Type::primitive_type_builder(name, PhysicalType::BYTE_ARRAY)
+            .with_repetition(Repetition::REQUIRED)
+            .with_converted_type(ConvertedType::UTF8)
+            .build()
+    };
+
+    match disposicion {
+        Disposicion::Ancha => {
+            let mut fields = vec![Arc::new(fecha_field)];
+            let mut series = Vec::with_capacity(zonas.len() * VARIABLES.len());
+            for zona in &zonas {
+                for variable in VARIABLES.iter() {
+                    let name = format!(
+                        "{}:{} [{}]",
+                        zona.nombre,
+                        variable.nombre(),
+                        variable.unidad()
+                    );
+                    fields.push(Arc::new(float_field(&name)?));
+                    series.push(variable.serie(zona));
+                }
+            }
+            let schema = Type::group_type_builder("horarios")
+                .with_fields(fields)
+                .build()?;
+            let mut writer = SerializedFileWriter::new(
+                writer,
+                Arc::new(schema),
+                Arc::new(WriterProperties::builder().build()),
+            )?;
+            let mut row_group = writer.next_row_group()?;
+            if let Some(mut col) = row_group.next_column()? {
+                col.typed::<Int64Type>().write_batch(&millis, None, None)?;
+                col.close()?;
+            }
+            for serie in &series {
+                if let Some(mut col) = row_group.next_column()? {
+                    col.typed::<FloatType>().write_batch(serie, None, None)?;
+                    col.close()?;
+                }
+            }
+            row_group.close()?;
+            writer.close()?;
+        }
+        Disposicion::Larga => {
+            let fields = vec![
+                Arc::new(fecha_field),
+                Arc::new(str_field("zona")?),
+                Arc::new(str_field("variable")?),
+                Arc::new(str_field("unidad")?),
+                Arc::new(float_field("valor")?),
+            ];
+            let schema = Type::group_type_builder("horarios")
+                .with_fields(fields)
+                .build()?;
+            let mut writer = SerializedFileWriter::new(
+                writer,
+                Arc::new(schema),
+                Arc::new(WriterProperties::builder().build()),
+            )?;
+            // Un grupo de filas por zona y variable
+            for zona in &zonas {
+                for variable in VARIABLES.iter() {
+                    let valores = variable.serie(zona);
+                    let n = valores.len();
+                    let columnas_str: [Vec<ByteArray>; 3] = [
+                        vec![ByteArray::from(zona.nombre.as_str()); n],
+                        vec![ByteArray::from(variable.nombre()); n],
+                        vec![ByteArray::from(variable.unidad()); n],
+                    ];
+                    let mut row_group = writer.next_row_group()?;
+                    if let Some(mut col) = row_group.next_column()? {
+                        col.typed::<Int64Type>()
+                            .write_batch(&millis[..n], None, None)?;
+                        col.close()?;
+                    }
+                    for values in columnas_str.iter() {
+                        if let Some(mut col) = row_group.next_column()? {
+                            col.typed::<ByteArrayType>()
+                                .write_batch(values, None, None)?;
+                            col.close()?;
+                        }
+                    }
+                    if let Some(mut col) = row_group.next_column()? {
+                        col.typed::<FloatType>().write_batch(&valores, None, None)?;
+                        col.close()?;
+                    }
+                    row_group.close()?;
+                }
+            }
+            writer.close()?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::testfile;
+
+    fn testdata() -> BinData {
+        BinData::from_file(testfile("test.bin")).unwrap()
+    }
+
+    #[test]
+    fn export_horarios_csv() {
+        let data = testdata();
+        let zonas = vec!["P01_E01".to_string(), "P01_E02".to_string()];
+
+        let wide = to_csv(&data, &zonas, Disposicion::Ancha).unwrap();
+        let mut lines = wide.lines();
+        let header = lines.next().unwrap();
+        assert!(header.starts_with("fecha,P01_E01:t_real [ºC],P01_E01:t_max [ºC]"));
+        assert_eq!(header.split(',').count(), 1 + 2 * VARIABLES.len());
+        assert!(lines.next().unwrap().starts_with("2001-01-01 00:00,"));
+        assert_eq!(
+            lines.last().unwrap().split(',').next(),
+            Some("2001-12-31 23:00")
+        );
+        assert_eq!(wide.lines().count(), 1 + 8760);
+
+        let long = to_csv(&data, &zonas, Disposicion::Larga).unwrap();
+        assert_eq!(long.lines().count(), 1 + 2 * VARIABLES.len() * 8760);
+        assert!(long
+            .lines()
+            .nth(1)
+            .unwrap()
+            .starts_with("2001-01-01 00:00,P01_E01,t_real,ºC,"));
+
+        // Las zonas sin datos horarios se omiten, salvo que no haya ninguna
+        let con_ausente = vec!["P01_E01".to_string(), "NOEXISTE".to_string()];
+        let (_, omitidas) = select_zonas(&data, &con_ausente).unwrap();
+        assert_eq!(omitidas, vec!["NOEXISTE".to_string()]);
+        let wide = to_csv(&data, &con_ausente, Disposicion::Ancha).unwrap();
+        assert_eq!(
+            wide.lines().next().unwrap().split(',').count(),
+            1 + VARIABLES.len()
+        );
+        assert!(to_csv(&data, &["NOEXISTE".to_string()], Disposicion::Ancha).is_err());
+    }
+
+    #[test]
+    fn campos_csv() {
+        assert_eq!(campo_csv("P01_E01"), "P01_E01");
+        assert_eq!(campo_csv("Zona 1, norte"), "\"Zona 1, norte\"");
+        assert_eq!(campo_csv("Zona \"A\""), "\"Zona \"\"A\"\"\"");
+    }
+
+    #[test]
+    fn export_horarios_parquet() {
+        use parquet::file::reader::{FileReader, SerializedFileReader};
+
+        let data = testdata();
+        let zonas = vec!["P01_E01".to_string()];
+        let path = std::env::temp_dir().join("visol_test_horarios.parquet");
+        for (disposicion, filas, columnas) in &[
+            (Disposicion::Ancha, 8760, 1 + VARIABLES.len()),
+            (Disposicion::Larga, VARIABLES.len() * 8760, 5),
+        ] {
+            export_horarios(&data, &zonas, *disposicion, &path).unwrap();
+            let reader = SerializedFileReader::new(File::open(&path).unwrap()).unwrap();
+            let meta = reader.metadata().file_metadata();
+            assert_eq!(meta.num_rows(), *filas as i64);
+            assert_eq!(meta.schema_descr().num_columns(), *columnas);
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,43 @@
+//! Exportación de datos de resultados de LIDER (.res) y de zonas (.bin) a otros formatos
+
+use std::path::Path;
+
+pub mod horarios;
+pub mod tablas;
+
+/// Campo CSV, entre comillas si contiene comas, comillas o saltos de línea
+pub(crate) fn campo_csv(valor: &str) -> String {
+    if valor.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", valor.replace('"', "\"\""))
+    } else {
+        valor.to_string()
+    }
+}
+
+/// Formato del archivo de salida
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Formato {
+    /// Texto separado por comas
+    Csv,
+    /// Apache Parquet
+    Parquet,
+    /// Libro de Excel
+    Xlsx,
+}
+
+impl Formato {
+    /// Formato a partir de la extensión del archivo (.parquet, .xlsx y CSV en otro caso)
+    pub fn from_path<S: AsRef<Path>>(path: S) -> Self {
+        match path
+            .as_ref()
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(str::to_lowercase)
+            .as_deref()
+        {
+            Some("parquet") => Self::Parquet,
+            Some("xlsx") => Self::Xlsx,
+            _ => Self::Csv,
+        }
+    }
+}
@@ -0,0 +1,351 @@
+//! Exportación de las tablas de flujos por conceptos y elementos (.res) a CSV y XLSX
+//!
+//! Se exportan los flujos de calor (calpos, calneg, calnet, refpos, refneg, refnet) en cuatro niveles:
+//! - Edificio: flujos por conceptos del edificio
+//! - Plantas: flujos por conceptos de cada planta
+//! - Zonas: flujos por conceptos de cada zona
+//! - Elementos: flujos de cada elemento constructivo de cada zona
+//!
+//! En CSV se genera una única tabla, con una columna que indica el nivel, y en XLSX una hoja por nivel.
+//! Los flujos se expresan en las unidades indicadas: kWh/m²·año referidos a la superficie del objeto
+//! (edificio, planta o zona), kWh/año con multiplicadores o kWh/m²·año referidos a la superficie del edificio.
+
+use std::{fmt::Write, path::Path};
+
+use rust_xlsxwriter::{Format, Workbook};
+
+use super::{campo_csv, Formato};
+use crate::analysis::unidades::Unidades;
+use crate::parsers::types::{EdificioLIDER, Flujos};
+use crate::utils::Error;
+
+/// Nombres de las columnas de flujos
+const COLUMNAS_FLUJOS: [&str; 6] = ["calpos", "calneg", "calnet", "refpos", "refneg", "refnet"];
+
+/// Cabeceras de las columnas de flujos, con unidades
+pub fn columnas_flujos(unidades: Unidades) -> Vec<String> {
+    COLUMNAS_FLUJOS
+        .iter()
+        .map(|col| format!("{} [{}]", col, unidades.anual()))
+        .collect()
+}
+
+/// Nivel de agregación de los flujos
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Nivel {
+    /// Flujos por conceptos del edificio
+    Edificio,
+    /// Flujos por conceptos de las plantas
+    Plantas,
+    /// Flujos por conceptos de las zonas
+    Zonas,
+    /// Flujos por elementos constructivos de las zonas
+    Elementos,
+}
+
+impl Nivel {
+    /// Niveles de agregación, de mayor a menor
+    pub const TODOS: [Nivel; 4] = [
+        Nivel::Edificio,
+        Nivel::Plantas,
+        Nivel::Zonas,
+        Nivel::Elementos,
+    ];
+
+    /// Nombre del nivel (y de su hoja en XLSX)
+    pub fn nombre(self) -> &'static str {
+        match self {
+            Self::Edificio => "Edificio",
+            Self::Plantas => "Plantas",
+            Self::Zonas => "Zonas",
+            Self::Elementos => "Elementos",
+        }
+    }
+
+    /// Cabecera de la columna que identifica cada fila del nivel
+    fn columna_nombre(self) -> &'static str {
+        match self {
+            Self::Elementos => "elemento",
+            _ => "concepto",
+        }
+    }
+}
+
+/// Fila de la tabla de flujos
+#[derive(Debug, Clone)]
+pub struct Fila {
+    /// Nivel de agregación
+    pub nivel: Nivel,
+    /// Nombre de la planta (vacío a nivel de edificio)
+    pub planta: String,
+    /// Nombre de la zona (vacío a nivel de edificio y planta)
+    pub zona: String,
+    /// Superficie del objeto (edificio, planta o zona) [m²]
+    pub superficie: f32,
+    /// Multiplicador de la zona (1 a nivel de edificio y planta)
+    pub multiplicador: i32,
+    /// Nombre del concepto o del elemento constructivo
+    pub nombre: String,
+    /// Flujos del concepto o elemento, en las unidades de la tabla
+    pub flujos: Flujos,
+}
+
+/// Filas de flujos por conceptos y elementos del edificio, en todos los niveles
+pub fn filas(edificio: &EdificioLIDER, unidades: Unidades) -> Vec<Fila> {
+    let mut filas = Vec::new();
+    let fila = |nivel,
+                planta: &str,
+                zona: &str,
+                superficie,
+                multiplicador,
+                nombre: &str,
+                flujos: Flujos| {
+        let factor = unidades.factor(superficie * multiplicador as f32, edificio.superficie);
+        Fila {
+            nivel,
+            planta: planta.to_string(),
+            zona: zona.to_string(),
+            superficie,
+            multiplicador,
+            nombre: nombre.to_string(),
+            flujos: flujos * factor,
+        }
+    };
+
+    for (concepto, flujos) in edificio.conceptos().to_named_list().iter() {
+        filas.push(fila(
+            Nivel::Edificio,
+            "",
+            "",
+            edificio.superficie,
+            1,
+            concepto,
+            *flujos,
+        ));
+    }
+
+    for planta in &edificio.plantas {
+        let superficie = planta.superficie(edificio);
+        for (concepto, flujos) in planta.conceptos(edificio).to_named_list().iter() {
+            filas.push(fila(
+                Nivel::Plantas,
+                &planta.nombre,
+                "",
+                superficie,
+                1,
+                concepto,
+                *flujos,
+            ));
+        }
+    }
+
+    let zonas = || {
+        edificio
+            .plantas
+            .iter()
+            .flat_map(|p| p.zonas.iter())
+            .filter_map(move |z| edificio.zonas.get(z))
+    };
+    for zona in zonas() {
+        for (concepto, flujos) in zona.conceptos.to_named_list().iter() {
+            filas.push(fila(
+                Nivel::Zonas,
+                &zona.planta,
+                &zona.nombre,
+                zona.superficie,
+                zona.multiplicador,
+                concepto,
+                *flujos,
+            ));
+        }
+    }
+    for zona in zonas() {
+        for elemento in &zona.elementos {
+            filas.push(fila(
+                Nivel::Elementos,
+                &zona.planta,
+                &zona.nombre,
+                zona.superficie,
+                zona.multiplicador,
+                &elemento.nombre,
+                elemento.flujos,
+            ));
+        }
+    }
+    filas
+}
+
+/// Valores de los flujos en el orden de las columnas de flujos
+pub fn valores(flujos: &Flujos) -> [f32; 6] {
+    [
+        flujos.calpos,
+        flujos.calneg,
+        flujos.calnet,
+        flujos.refpos,
+        flujos.refneg,
+        flujos.refnet,
+    ]
+}
+
+/// Exporta las tablas de flujos del edificio al archivo indicado
+///
+/// El formato (CSV o XLSX) se deduce de la extensión del archivo (.csv o .xlsx).
+pub fn export_tablas<S: AsRef<Path>>(
+    edificio: &EdificioLIDER,
+    unidades: Unidades,
+    path: S,
+) -> Result<(), Error> {
+    let path = path.as_ref();
+    match Formato::from_path(path) {
+        Formato::Csv => std::fs::write(path, to_csv(edificio, unidades)?)?,
+        Formato::Xlsx => to_xlsx(edificio, unidades)?.save(path)?,
+        Formato::Parquet => {
+            return Err("Formato no disponible para las tablas de flujos (use .csv o .xlsx)".into())
+        }
+    };
+    Ok(())
+}
+
+/// Tablas de flujos del edificio en formato CSV (una fila por nivel, objeto y concepto o elemento)
+pub fn to_csv(edificio: &EdificioLIDER, unidades: Unidades) -> Result<String, Error> {
+    let mut out = format!(
+        "nivel,planta,zona,superficie [m²],multiplicador,nombre,{}\n",
+        columnas_flujos(unidades).join(",")
+    );
+    for fila in filas(edificio, unidades) {
+        write!(
+            out,
+            "{},{},{},{:.2},{},{}",
+            fila.nivel.nombre(),
+            campo_csv(&fila.planta),
+            campo_csv(&fila.zona),
+            fila.superficie,
+            fila.multiplicador,
+            campo_csv(&fila.nombre)
+        )?;
+        for valor in valores(&fila.flujos).iter() {
+            write!(out, ",{:.6}", valor)?;
+        }
+        writeln!(out)?;
+    }
+    Ok(out)
+}
+
+/// Tablas de flujos del edificio como libro XLSX, con una hoja por nivel
+pub fn to_xlsx(edificio: &EdificioLIDER, unidades: Unidades) -> Result<Workbook, Error> {
+    let filas = filas(edificio, unidades);
+    let columnas = columnas_flujos(unidades);
+    let bold = Format::new().set_bold();
+    let mut workbook = Workbook::new();
+
+    for nivel in Nivel::TODOS.iter() {
+        let sheet = workbook.add_worksheet();
+        sheet.set_name(nivel.nombre())?;
+
+        // Columnas de identificación, según el nivel
+        let mut cabecera = match nivel {
+            Nivel::Edificio => vec![],
+            Nivel::Plantas => vec!["planta"],
+            Nivel::Zonas | Nivel::Elementos => vec!["planta", "zona", "multiplicador"],
+        };
+        cabecera.extend(&["superficie [m²]", nivel.columna_nombre()]);
+        let ncols = cabecera.len() as u16;
+        cabecera.extend(columnas.iter().map(String::as_str));
+        for (col, titulo) in cabecera.iter().enumerate() {
+            sheet.write_string_with_format(0, col as u16, *titulo, &bold)?;
+            sheet.set_column_width(col as u16, titulo.chars().count().max(12) as f64)?;
+        }
+        sheet.set_freeze_panes(1, 0)?;
+
+        for (i, fila) in filas.iter().filter(|f| f.nivel == *nivel).enumerate() {
+            let row = i as u32 + 1;
+            let mut col = 0;
+            if *nivel != Nivel::Edificio {
+                sheet.write_string(row, col, &fila.planta)?;
+                col += 1;
+            }
+            if matches!(nivel, Nivel::Zonas | Nivel::Elementos) {
+                sheet.write_string(row, col, &fila.zona)?;
+                sheet.write_number(row, col + 1, fila.multiplicador)?;
+                col += 2;
+            }
+            sheet.write_number(row, col, fila.superficie)?;
+            sheet.write_string(row, col + 1, &fila.nombre)?;
+            for (j, valor) in valores(&fila.flujos).iter().enumerate() {
+                sheet.write_number(row, ncols + j as u16, *valor)?;
+            }
+        }
+    }
+    Ok(workbook)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::testfile;
+
+    fn testdata() -> EdificioLIDER {
+        EdificioLIDER::from_file(testfile("test.res")).unwrap()
+    }
+
+    #[test]
+    fn export_tablas_csv() {
+        let edificio = testdata();
+        let csv = to_csv(&edificio, Unidades::Intensidad).unwrap();
+        let mut lines = csv.lines();
+        let header = lines.next().unwrap();
+        assert!(header.starts_with("nivel,planta,zona,superficie [m²],multiplicador,nombre,"));
+        assert!(header.ends_with("refnet [kWh/m²·año]"));
+
+        let filas = filas(&edificio, Unidades::Intensidad);
+        let count = |nivel| filas.iter().filter(|f| f.nivel == nivel).count();
+        assert_eq!(count(Nivel::Edificio), 9);
+        assert_eq!(count(Nivel::Plantas), 2 * 9);
+        assert_eq!(count(Nivel::Zonas), 10 * 9);
+        assert_eq!(
+            count(Nivel::Elementos),
+            edificio.zonas.values().map(|z| z.elementos.len()).sum()
+        );
+        assert_eq!(csv.lines().count(), 1 + filas.len());
+        assert!(csv.contains("Elementos,P01,P01_E01,25.04,1,P01_E01_PE001,0.048589,-3.932690"));
+
+        // En valores absolutos los flujos del edificio son la suma de los de las zonas
+        let csv = to_csv(&edificio, Unidades::Absoluta).unwrap();
+        assert!(csv.lines().next().unwrap().ends_with("refnet [kWh/año]"));
+        let absolutos = super::filas(&edificio, Unidades::Absoluta);
+        let total = |nivel| -> f32 {
+            absolutos
+                .iter()
+                .filter(|f| f.nivel == nivel && f.nombre == "TOTAL")
+                .map(|f| f.flujos.calnet)
+                .sum()
+        };
+        let edificio_total = total(Nivel::Edificio);
+        assert!((edificio_total - total(Nivel::Zonas)).abs() < 1e-3 * edificio_total.abs());
+        let esperado = edificio.conceptos().total.calnet * edificio.superficie;
+        assert!((edificio_total - esperado).abs() < 1e-3 * esperado.abs());
+    }
+
+    #[test]
+    fn export_tablas_csv_nombres() {
+        // Los nombres con comas o comillas se escriben entre comillas
+        let mut edificio = testdata();
+        let zona = edificio.zonas.get_mut("P01_E01").unwrap();
+        zona.elementos[0].nombre = "Muro, fachada \"norte\"".to_string();
+        let csv = to_csv(&edificio, Unidades::Intensidad).unwrap();
+        assert!(
+            csv.contains("Elementos,P01,P01_E01,25.04,1,\"Muro, fachada \"\"norte\"\"\",0.048589")
+        );
+    }
+
+    #[test]
+    fn export_tablas_xlsx() {
+        let edificio = testdata();
+        let buf = to_xlsx(&edificio, Unidades::Intensidad)
+            .unwrap()
+            .save_to_buffer()
+            .unwrap();
+        // Los archivos XLSX son archivos ZIP
+        assert!(buf.starts_with(b"PK"));
+    }
+}
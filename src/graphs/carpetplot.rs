@@ -0,0 +1,264 @@
+//! Mapa horario (carpet plot) de variables de zona
+//!
+//! Representa los valores horarios de una zona en una matriz día del año (eje X) x hora del día (eje Y)
+//! Permite detectar de un vistazo problemas de horarios (consignas, ventilación nocturna, etc)
+
+use super::{
+    draw_months, draw_subtitle_and_box, draw_watermark, draw_ytitle, linear_scale, nice_ticks,
+    style::{chart_style, mix, set_color, ChartStyle, Color},
+    tick_decimals, ylabels,
+};
+use crate::analysis::ventilacion::caudales;
+use crate::parsers::bin::ZonaLider;
+
+/// Variable horaria de zona a representar en el mapa horario
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VariableHoraria {
+    /// Temperatura del local [ºC]
+    #[default]
+    TReal,
+    /// Carga sensible [W]
+    QSen,
+    /// Carga latente [W]
+    QLat,
+    /// Caudal de ventilación e infiltraciones [m³/h]
+    VVentInf,
+    /// Disponibilidad de calefacción (1|0 para on|off)
+    DaCal,
+    /// Disponibilidad de refrigeración (1|0 para on|off)
+    DaRef,
+}
+
+impl std::str::FromStr for VariableHoraria {
+    type Err = crate::utils::Error;
+
+    /// Obtiene la variable a partir del identificador usado en la interfaz (p.e. "t_real")
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "t_real" => Ok(Self::TReal),
+            "q_sen" => Ok(Self::QSen),
+            "q_lat" => Ok(Self::QLat),
+            "v_ventinf" => Ok(Self::VVentInf),
+            "da_cal" => Ok(Self::DaCal),
+            "da_ref" => Ok(Self::DaRef),
+            _ => Err(format!("Variable horaria desconocida: {}", s).into()),
+        }
+    }
+}
+
+impl VariableHoraria {
+    /// Descripción de la variable, con unidades
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::TReal => "Temperatura del local [ºC]",
+            Self::QSen => "Carga sensible [W]",
+            Self::QLat => "Carga latente [W]",
+            Self::VVentInf => "Caudal de ventilación e infiltraciones [m³/h]",
+            Self::DaCal => "Disponibilidad de calefacción [on/off]",
+            Self::DaRef => "Disponibilidad de refrigeración [on/off]",
+        }
+    }
+
+    /// ¿Es una variable de tipo on/off?
+    pub fn is_binary(self) -> bool {
+        matches!(self, Self::DaCal | Self::DaRef)
+    }
+
+    /// Valores horarios de la variable para la zona
+    pub fn values(self, zona: &ZonaLider) -> Vec<f32> {
+        match self {
+            Self::TReal => zona.t_real.clone(),
+            Self::QSen => zona.q_sen.clone(),
+            Self::QLat => zona.q_lat.clone(),
+            Self::VVentInf => caudales(zona)
+                .into_iter()
+                .map(|c| c.volumetrico().0)
+                .collect(),
+            Self::DaCal => zona.da_cal.iter().map(|v| *v as f32).collect(),
+            Self::DaRef => zona.da_ref.iter().map(|v| *v as f32).collect(),
+        }
+    }
+
+    /// Paleta de colores de la variable en el estilo indicado
+    ///
+    /// Las variables de disponibilidad usan dos colores (off, on) y las demás una paleta secuencial
+    fn colors(self, style: &ChartStyle) -> Vec<Color> {
+        let off = mix(style.background, style.muted, 0.1);
+        match self {
+            Self::DaCal => vec![off, style.heating[0]],
+            Self::DaRef => vec![off, style.cooling[0]],
+            _ => style.continuous.to_vec(),
+        }
+    }
+}
+
+/// Color para un valor normalizado t en [0, 1], interpolando linealmente en la paleta
+fn colormap(colors: &[Color], t: f64) -> Color {
+    let t = t.clamp(0.0, 1.0);
+    let pos = t * (colors.len() - 1) as f64;
+    let idx = (pos.floor() as usize).min(colors.len() - 2);
+    mix(colors[idx], colors[idx + 1], pos - idx as f64)
+}
+
+/// Dibuja mapa horario (día x hora) de la variable seleccionada de una zona
+pub fn draw_carpetplot(
+    size: (f64, f64),
+    cr: &cairo::Context,
+    zonedata: Option<&ZonaLider>,
+    variable: VariableHoraria,
+) {
+    let style = chart_style();
+    let title = "Mapa horario de zona";
+
+    // Posiciones y cálculos previos
+    let (widget_width, widget_height) = size;
+    let htitle = 0.1 * widget_height;
+    let subtitle_block_height = 0.05 * widget_height;
+    let margin = 0.07 * widget_height;
+
+    let x0 = 2.0 * margin;
+    let x1 = widget_width - 3.0 * margin;
+    let y0 = htitle + subtitle_block_height;
+    let y1 = widget_height - 1.5 * margin;
+    let width = x1 - x0;
+    let height = y1 - y0;
+    let ticksize = width / 10.0 / 12.0;
+
+    cr.save();
+
+    // Fondo
+    set_color(cr, style.background);
+    cr.rectangle(1.0, 1.0, widget_width, widget_height);
+    cr.fill();
+
+    // Título
+    style.select_font(cr, cairo::FontWeight::Bold);
+    cr.set_font_size(style.title_size);
+    set_color(cr, style.muted);
+    let extents = cr.text_extents(title);
+    cr.move_to(
+        (widget_width - extents.width) / 2.0,
+        0.5 * (htitle + extents.height),
+    );
+    cr.show_text(title);
+
+    // En modos que no son de Zona dibujamos una nota
+    if zonedata.is_none() {
+        let txt = "Seleccione una zona";
+        let te = cr.text_extents(txt);
+        cr.move_to((widget_width - te.width) / 2.0, widget_height * 0.5);
+        cr.show_text(txt);
+        cr.restore();
+        return;
+    }
+
+    let values = variable.values(zonedata.unwrap());
+    let colors = variable.colors(&style);
+
+    // Zona sin datos horarios
+    if values.is_empty() {
+        let txt = "Sin datos horarios para la zona seleccionada";
+        let te = cr.text_extents(txt);
+        cr.move_to((widget_width - te.width) / 2.0, widget_height * 0.5);
+        cr.show_text(txt);
+        cr.restore();
+        return;
+    }
+
+    // Dominio de valores y etiquetas de la leyenda
+    let (vmin, vmax, legend_labels) = if variable.is_binary() {
+        (
+            0.0,
+            1.0,
+            vec![(0.0, "off".to_string()), (1.0, "on".to_string())],
+        )
+    } else {
+        let min = values.iter().fold(f32::INFINITY, |a, b| a.min(*b)) as f64;
+        let max = values.iter().fold(f32::NEG_INFINITY, |a, b| a.max(*b)) as f64;
+        let (min, max) = if (max - min).abs() < 0.01 {
+            (min - 1.0, max + 1.0)
+        } else {
+            (min, max)
+        };
+        let range = nice_ticks(min, max, 5);
+        let decimals = tick_decimals(&range);
+        let labels = range
+            .iter()
+            .map(|v| (*v, format!("{:.*}", decimals, v)))
+            .collect();
+        (range[0], range[range.len() - 1], labels)
+    };
+
+    // Celdas día x hora
+    let xscale = linear_scale(0.0, 365.0, x0, x1);
+    let yscale = linear_scale(0.0, 24.0, y0, y1);
+    let cell_width = width / 365.0;
+    let cell_height = height / 24.0;
+    cr.save();
+    cr.set_antialias(cairo::Antialias::None);
+    for (i, value) in values.iter().enumerate() {
+        let (day, hour) = (i / 24, i % 24);
+        set_color(
+            cr,
+            colormap(&colors, (*value as f64 - vmin) / (vmax - vmin)),
+        );
+        cr.rectangle(
+            xscale(day as f64),
+            yscale(hour as f64),
+            cell_width.ceil(),
+            cell_height.ceil(),
+        );
+        cr.fill();
+    }
+    cr.restore();
+
+    // Subtítulo, marco, meses y horas
+    draw_subtitle_and_box(
+        cr,
+        variable.label(),
+        subtitle_block_height,
+        x0,
+        y0,
+        width,
+        height,
+    );
+    draw_ytitle(cr, "Hora", margin * 0.75, (y0 + y1) / 2.0);
+    draw_months(cr, x0, x1, y0, y1);
+    let labels: Vec<(f64, String)> = [0.0, 6.0, 12.0, 18.0, 24.0]
+        .iter()
+        .map(|h| (yscale(*h), format!("{:.0}h", h)))
+        .collect();
+    ylabels(cr, labels.as_slice(), ticksize, x0, true);
+
+    // Leyenda de colores
+    let legend_x = x1 + 0.5 * margin;
+    let legend_width = 0.3 * margin;
+    let legend_scale = linear_scale(vmin, vmax, y1, y0);
+    let gradient = cairo::LinearGradient::new(legend_x, y1, legend_x, y0);
+    let nstops = (colors.len() - 1) as f64;
+    for (i, (r, g, b)) in colors.iter().enumerate() {
+        gradient.add_color_stop_rgb(i as f64 / nstops, *r, *g, *b);
+    }
+    cr.set_source(&gradient);
+    cr.rectangle(legend_x, y0, legend_width, height);
+    cr.fill();
+    cr.set_line_width(0.5);
+    set_color(cr, style.muted);
+    cr.rectangle(legend_x, y0, legend_width, height);
+    cr.stroke();
+    let labels: Vec<(f64, String)> = legend_labels
+        .into_iter()
+        .map(|(v, label)| (legend_scale(v), label))
+        .collect();
+    ylabels(
+        cr,
+        labels.as_slice(),
+        ticksize,
+        legend_x + legend_width,
+        false,
+    );
+
+    draw_watermark(cr, widget_width - widget_height * 0.05, htitle);
+
+    cr.restore();
+}
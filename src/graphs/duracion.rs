@@ -0,0 +1,195 @@
+//! Curvas de duración de carga de calefacción y refrigeración de una zona, planta o el edificio
+//!
+//! El eje horizontal representa las horas del año, ordenadas de mayor a menor carga,
+//! y el eje vertical la carga térmica total (sensible + latente) [kW]
+
+use super::{
+    draw_subtitle_and_box, draw_watermark, draw_ytitle, linear_scale, nice_ticks, rounder,
+    style::{chart_style, set_color, set_color_alpha},
+    tick_decimals, ylabels,
+};
+use crate::analysis::duracion::CurvasDuracion;
+use crate::config::{Config, Escala};
+
+/// Dibuja las curvas de duración de carga del objeto activo
+pub fn draw_duracion(
+    size: (f64, f64),
+    cr: &cairo::Context,
+    curvas: Option<&CurvasDuracion>,
+    nombre: &str,
+    config: &Config,
+) {
+    let style = chart_style();
+    let title = "Curvas de duración de carga";
+
+    // Posiciones y cálculos previos
+    let (widget_width, widget_height) = size;
+    let htitle = 0.1 * widget_height;
+    let subtitle_block_height = 0.05 * widget_height;
+    let margin = 0.07 * widget_height;
+
+    let x0 = 2.0 * margin;
+    let x1 = widget_width - x0;
+    let y0 = htitle + subtitle_block_height;
+    let y1 = widget_height - 1.5 * margin;
+    let width = x1 - x0;
+    let height = y1 - y0;
+    let ticksize = width / 10.0 / 12.0;
+
+    cr.save();
+
+    // Fondo
+    set_color(cr, style.background);
+    cr.rectangle(1.0, 1.0, widget_width, widget_height);
+    cr.fill();
+
+    // Título
+    style.select_font(cr, cairo::FontWeight::Bold);
+    cr.set_font_size(style.title_size);
+    set_color(cr, style.muted);
+    let extents = cr.text_extents(title);
+    cr.move_to(
+        (widget_width - extents.width) / 2.0,
+        0.5 * (htitle + extents.height),
+    );
+    cr.show_text(title);
+
+    // Sin datos horarios (elementos o sin archivo .bin) dibujamos una nota
+    let curvas = match curvas {
+        Some(curvas) if !curvas.calefaccion.is_empty() => curvas,
+        _ => {
+            let txt = "Seleccione el edificio, una planta o una zona con datos horarios";
+            let te = cr.text_extents(txt);
+            cr.move_to((widget_width - te.width) / 2.0, widget_height * 0.5);
+            cr.show_text(txt);
+            cr.restore();
+            return;
+        }
+    };
+
+    // Datos en kW
+    let cal: Vec<f64> = curvas
+        .calefaccion
+        .iter()
+        .map(|v| *v as f64 / 1000.0)
+        .collect();
+    let refr: Vec<f64> = curvas
+        .refrigeracion
+        .iter()
+        .map(|v| *v as f64 / 1000.0)
+        .collect();
+    let nhoras = cal.len() as f64;
+    let (pico_cal, pico_ref) = curvas.picos();
+    let (dem_cal, dem_ref) = curvas.demandas();
+    let (horas_cal, horas_ref) = curvas.horas();
+    let q_max = (pico_cal.max(pico_ref) as f64 / 1000.0).max(0.1);
+
+    let (q_min, q_max) = config.limits(Escala::Cargas, 0.0, q_max as f32);
+    let range = nice_ticks(q_min as f64, q_max as f64, 5);
+    let xscale = linear_scale(0.0, nhoras, x0, x1);
+    let yscale = linear_scale(range[0], range[range.len() - 1], y1, y0);
+
+    // Subtítulo y marco
+    let subtitle = format!("{} - Carga térmica total (sensible + latente) [kW]", nombre);
+    draw_subtitle_and_box(cr, &subtitle, subtitle_block_height, x0, y0, width, height);
+    draw_ytitle(cr, "Carga térmica [kW]", margin * 0.75, (y0 + y1) / 2.0);
+
+    // Etiquetas Y
+    let decimals = tick_decimals(&range);
+    let labels: Vec<(f64, String)> = range
+        .iter()
+        .map(|v| (yscale(*v), format!("{:.*}", decimals, v)))
+        .collect();
+    ylabels(cr, labels.as_slice(), ticksize, x0, true);
+
+    // Etiquetas X, cada 1000 horas
+    cr.save();
+    cr.set_font_size(style.small_size);
+    cr.set_line_width(0.5);
+    set_color(cr, style.muted);
+    for hora in (0..=nhoras as usize).step_by(1000) {
+        let x = rounder(xscale(hora as f64));
+        let txt = format!("{}", hora);
+        let te = cr.text_extents(&txt);
+        cr.move_to(x, y1);
+        cr.rel_line_to(0.0, ticksize);
+        cr.stroke();
+        cr.move_to(x - te.width / 2.0, y1 + ticksize + 1.5 * te.height);
+        cr.show_text(&txt);
+    }
+    let xlabel = "Horas [h]";
+    let te = cr.text_extents(xlabel);
+    cr.move_to((x0 + x1 - te.width) / 2.0, y1 + ticksize + 3.5 * te.height);
+    cr.show_text(xlabel);
+    cr.restore();
+
+    // Curvas (relleno y línea), recortadas al marco si superan los límites de la escala
+    cr.save();
+    cr.rectangle(x0, y0, width, height);
+    cr.clip();
+    for (vals, color) in &[(&cal, style.heating[0]), (&refr, style.cooling[0])] {
+        cr.move_to(x0, rounder(yscale(0.0)));
+        vals.iter()
+            .enumerate()
+            .for_each(|(i, v)| cr.line_to(xscale(i as f64), yscale(*v)));
+        cr.line_to(x1, rounder(yscale(0.0)));
+        set_color_alpha(cr, *color, 0.2);
+        cr.fill();
+
+        cr.set_line_width(1.0);
+        set_color(cr, *color);
+        cr.move_to(x0, yscale(vals[0]));
+        vals.iter()
+            .enumerate()
+            .skip(1)
+            .for_each(|(i, v)| cr.line_to(xscale(i as f64), yscale(*v)));
+        cr.stroke();
+    }
+    cr.restore();
+
+    // Picos, demandas y horas con carga
+    cr.set_font_size(style.mid_size);
+    for (i, (txt, pico, demanda, horas, color)) in [
+        (
+            "Calefacción",
+            pico_cal,
+            dem_cal,
+            horas_cal,
+            style.heating[0],
+        ),
+        (
+            "Refrigeración",
+            pico_ref,
+            dem_ref,
+            horas_ref,
+            style.cooling[0],
+        ),
+    ]
+    .iter()
+    .enumerate()
+    {
+        cr.move_to(x1 - width * 0.45, y0 + (0.1 + 0.07 * i as f64) * height);
+        set_color(cr, *color);
+        style.select_font(cr, cairo::FontWeight::Bold);
+        cr.show_text(&format!("{} - ", txt));
+        set_color(cr, style.label);
+        style.select_font(cr, cairo::FontWeight::Normal);
+        cr.show_text("pico: ");
+        style.select_font(cr, cairo::FontWeight::Bold);
+        cr.show_text(&format!("{:.2}", pico / 1000.0));
+        style.select_font(cr, cairo::FontWeight::Normal);
+        cr.show_text(" kW, demanda: ");
+        style.select_font(cr, cairo::FontWeight::Bold);
+        cr.show_text(&format!("{:.0}", demanda));
+        style.select_font(cr, cairo::FontWeight::Normal);
+        cr.show_text(" kWh/año, ");
+        style.select_font(cr, cairo::FontWeight::Bold);
+        cr.show_text(&format!("{}", horas));
+        style.select_font(cr, cairo::FontWeight::Normal);
+        cr.show_text(" h");
+    }
+
+    draw_watermark(cr, widget_width - widget_height * 0.05, htitle);
+
+    cr.restore();
+}
@@ -0,0 +1,365 @@
+//! Gráficas de la aplicación y datos del estado que representa cada una
+//!
+//! Permite dibujar cualquier gráfica sobre un contexto cairo arbitrario (widget, PDF, SVG...)
+//! con el tamaño indicado, a partir del estado de la aplicación y la configuración, y obtener
+//! los datos representados como texto separado por tabuladores (p.e. para pegarlos en una hoja
+//! de cálculo).
+
+use std::fmt::Write;
+
+use crate::analysis::unidades::Unidades;
+use crate::analysis::ventilacion::caudales;
+use crate::appstate::AppState;
+use crate::config::{Config, Escala};
+use crate::parsers::types::Conceptos;
+use crate::utils::Error;
+
+use super::{
+    carpetplot::draw_carpetplot,
+    duracion::draw_duracion,
+    histoconceptos::draw_histoconceptos,
+    histomeses::draw_histomeses,
+    horarioszona::draw_zonasgraph,
+    piechart::{draw_piechart, PieMode},
+    ranking::draw_ranking,
+    redzonas::draw_redzonas,
+    sankey::draw_sankey,
+    zonasplanta::draw_zonasplanta,
+    Region, MESES,
+};
+
+/// Gráficas disponibles
+#[derive(Debug, Clone, Copy)]
+pub enum Grafica {
+    /// Histograma de flujos por conceptos de demanda
+    HistoConceptos,
+    /// Histograma de demanda mensual
+    HistoMeses,
+    /// Gráfica de tarta de ganancias o pérdidas
+    Tarta(PieMode),
+    /// Diagrama de Sankey de flujos de calor
+    Sankey,
+    /// Histograma por zonas de la planta
+    ZonasPlanta,
+    /// Clasificación de elementos
+    Ranking,
+    /// Valores diarios de zona
+    HorariosZona,
+    /// Mapa horario de una variable de zona
+    MapaHorario,
+    /// Curvas de duración de carga
+    Duracion,
+    /// Red de adyacencia entre zonas
+    RedZonas,
+}
+
+impl std::str::FromStr for Grafica {
+    type Err = crate::utils::Error;
+
+    /// Obtiene la gráfica a partir del nombre de su widget en la interfaz (p.e. "histoconceptos")
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "histoconceptos" => Ok(Self::HistoConceptos),
+            "histomeses" => Ok(Self::HistoMeses),
+            "pieglobalcalpos" => Ok(Self::Tarta(PieMode::CalPos)),
+            "pieglobalcalneg" => Ok(Self::Tarta(PieMode::CalNeg)),
+            "pieglobalrefpos" => Ok(Self::Tarta(PieMode::RefPos)),
+            "pieglobalrefneg" => Ok(Self::Tarta(PieMode::RefNeg)),
+            "sankey" => Ok(Self::Sankey),
+            "zonasplanta" => Ok(Self::ZonasPlanta),
+            "rankingelementos" => Ok(Self::Ranking),
+            "zonasgraph" => Ok(Self::HorariosZona),
+            "carpetplot" => Ok(Self::MapaHorario),
+            "curvasduracion" => Ok(Self::Duracion),
+            "redzonas" => Ok(Self::RedZonas),
+            _ => Err(format!("Gráfica desconocida: {}", s).into()),
+        }
+    }
+}
+
+impl Grafica {
+    /// Dibuja la gráfica con los datos del objeto activo, con el tamaño (ancho, alto) indicado
+    ///
+    /// Devuelve las regiones de la gráfica con información adicional (vacío si no tiene)
+    pub fn draw(
+        self,
+        cr: &cairo::Context,
+        size: (f64, f64),
+        st: &AppState,
+        config: &Config,
+    ) -> Vec<Region> {
+        let zonedata = || {
+            st.bindata
+                .as_ref()
+                .and_then(|data| data.zonas.get(&st.curr_name))
+        };
+        match self {
+            Self::HistoConceptos => {
+                let (min, max) = st.concepts_limits(!st.show_detail);
+                let (min, max) = config.limits(Escala::Anual, min, max);
+                draw_histoconceptos(
+                    size,
+                    cr,
+                    &st.curr_name,
+                    &st.concepts_data(),
+                    min,
+                    max,
+                    st.show_detail,
+                    st.unidades,
+                )
+            }
+            Self::HistoMeses => {
+                let (min, max) = st.monthly_limits();
+                let (min, max) = config.limits(Escala::Mensual, min, max);
+                let (cal_meses, ref_meses) = st.calref_monthly_data();
+                draw_histomeses(size, cr, &cal_meses, &ref_meses, min, max, st.unidades)
+            }
+            Self::Tarta(mode) => draw_piechart(
+                size,
+                cr,
+                &st.concepts_data(),
+                mode,
+                config.pie_min_pct,
+                st.unidades,
+            ),
+            Self::Sankey => {
+                draw_sankey(size, cr, &st.curr_name, &st.concepts_data(), st.unidades);
+                Vec::new()
+            }
+            Self::ZonasPlanta => {
+                let planta = st.selected_floor();
+                draw_zonasplanta(
+                    size,
+                    cr,
+                    st.edificio.as_ref(),
+                    planta.as_deref(),
+                    &st.highlighted_zones(),
+                    st.unidades,
+                    config,
+                );
+                Vec::new()
+            }
+            Self::Ranking => {
+                let posiciones = st.element_ranking();
+                draw_ranking(
+                    size,
+                    cr,
+                    &posiciones,
+                    st.ranking_criterio,
+                    &st.highlighted_zones(),
+                    st.unidades,
+                );
+                Vec::new()
+            }
+            Self::HorariosZona => {
+                draw_zonasgraph(size, cr, zonedata(), config);
+                Vec::new()
+            }
+            Self::MapaHorario => {
+                draw_carpetplot(size, cr, zonedata(), st.carpet_var);
+                Vec::new()
+            }
+            Self::Duracion => {
+                let curvas = st.duration_curves();
+                draw_duracion(size, cr, curvas.as_ref(), &st.curr_name, config);
+                Vec::new()
+            }
+            Self::RedZonas => {
+                let red = st.adjacency_network();
+                draw_redzonas(size, cr, red.as_ref(), &st.highlighted_zones());
+                Vec::new()
+            }
+        }
+    }
+
+    /// Dibuja la gráfica en un documento SVG con el tamaño (ancho, alto) indicado
+    pub fn to_svg(self, size: (f64, f64), st: &AppState, config: &Config) -> Result<String, Error> {
+        let surface = cairo::SvgSurface::for_stream(size.0, size.1, Vec::<u8>::new())?;
+        {
+            let cr = cairo::Context::new(&surface);
+            self.draw(&cr, size, st, config);
+        }
+        let stream = surface.finish_output_stream().map_err(|e| e.error)?;
+        let bytes = stream
+            .downcast::<Vec<u8>>()
+            .map_err(|_| "No se ha podido generar el documento SVG")?;
+        Ok(String::from_utf8(*bytes)?)
+    }
+
+    /// Datos representados en la gráfica, como texto separado por tabuladores con encabezados
+    ///
+    /// Los valores se expresan en las mismas unidades que en la gráfica.
+    pub fn to_tsv(self, st: &AppState) -> String {
+        let mut out = String::new();
+        let unidades = st.unidades.anual();
+        let zonedata = st
+            .bindata
+            .as_ref()
+            .and_then(|data| data.zonas.get(&st.curr_name));
+        match self {
+            Self::HistoConceptos | Self::Tarta(_) | Self::Sankey => {
+                let flujos = st.concepts_data();
+                let nombres: Vec<&str> = if flujos.calnet.len() == 1 {
+                    vec![st.curr_name.as_str()]
+                } else {
+                    Conceptos::default()
+                        .to_named_list()
+                        .iter()
+                        .map(|(nombre, _)| *nombre)
+                        .collect()
+                };
+                writeln!(out, "Concepto\tcalpos [{u}]\tcalneg [{u}]\tcalnet [{u}]\trefpos [{u}]\trefneg [{u}]\trefnet [{u}]", u = unidades).unwrap();
+                for (i, nombre) in nombres.iter().enumerate() {
+                    writeln!(
+                        out,
+                        "{}\t{:.2}\t{:.2}\t{:.2}\t{:.2}\t{:.2}\t{:.2}",
+                        nombre,
+                        flujos.calpos[i],
+                        flujos.calneg[i],
+                        flujos.calnet[i],
+                        flujos.refpos[i],
+                        flujos.refneg[i],
+                        flujos.refnet[i]
+                    )
+                    .unwrap();
+                }
+            }
+            Self::HistoMeses => {
+                let (cal, refr) = st.calref_monthly_data();
+                let unidades = st.unidades.mensual();
+                writeln!(
+                    out,
+                    "Mes\tCalefacción [{u}]\tRefrigeración [{u}]",
+                    u = unidades
+                )
+                .unwrap();
+                for (mes, (cal, refr)) in MESES.iter().zip(cal.iter().zip(refr.iter())) {
+                    writeln!(out, "{}\t{:.2}\t{:.2}", mes, cal, refr).unwrap();
+                }
+            }
+            Self::ZonasPlanta => {
+                writeln!(
+                    out,
+                    "Zona\tMultiplicador\tSuperficie [m²]\tCalefacción [{u}]\tRefrigeración [{u}]",
+                    u = unidades
+                )
+                .unwrap();
+                if let (Some(edificio), Some(planta)) = (st.edificio.as_ref(), st.selected_floor())
+                {
+                    let zonas = edificio
+                        .plantas
+                        .iter()
+                        .filter(|p| p.nombre == planta)
+                        .flat_map(|p| p.zonas.iter())
+                        .filter_map(|z| edificio.zonas.get(z));
+                    for zona in zonas {
+                        let factor = st.unidades.factor(
+                            zona.superficie * zona.multiplicador as f32,
+                            edificio.superficie,
+                        );
+                        writeln!(
+                            out,
+                            "{}\t{}\t{:.2}\t{:.2}\t{:.2}",
+                            zona.nombre,
+                            zona.multiplicador,
+                            zona.superficie,
+                            zona.conceptos.total.calnet * factor,
+                            zona.conceptos.total.refnet * factor
+                        )
+                        .unwrap();
+                    }
+                }
+            }
+            Self::Ranking => {
+                let unidades = match st.unidades {
+                    Unidades::Absoluta => unidades,
+                    _ => "kWh/m²·año",
+                };
+                writeln!(
+                    out,
+                    "Posición\tElemento\tTipo\tZona\tPlanta\tMultiplicador\t{} [{}]\tFracción [%]",
+                    st.ranking_criterio.label(),
+                    unidades
+                )
+                .unwrap();
+                for (i, p) in st.element_ranking().iter().enumerate() {
+                    writeln!(
+                        out,
+                        "{}\t{}\t{}\t{}\t{}\t{}\t{:.2}\t{:.1}",
+                        i + 1,
+                        p.elemento,
+                        p.tipo.nombre(),
+                        p.zona,
+                        p.planta,
+                        p.multiplicador,
+                        p.valor,
+                        100.0 * p.fraccion
+                    )
+                    .unwrap();
+                }
+            }
+            Self::HorariosZona => {
+                writeln!(out, "Hora\tTemperatura [ºC]\tConsigna baja [ºC]\tConsigna alta [ºC]\tCarga sensible [W]\tCarga latente [W]\tCaudal de ventilación e infiltraciones [m³/h]").unwrap();
+                if let Some(zona) = zonedata {
+                    let caudales = caudales(zona);
+                    for (i, t_real) in zona.t_real.iter().enumerate() {
+                        let valor = |v: &[f32]| v.get(i).copied().unwrap_or_default();
+                        writeln!(
+                            out,
+                            "{}\t{:.2}\t{:.2}\t{:.2}\t{:.2}\t{:.2}\t{:.2}",
+                            i + 1,
+                            t_real,
+                            valor(&zona.t_min),
+                            valor(&zona.t_max),
+                            valor(&zona.q_sen),
+                            valor(&zona.q_lat),
+                            caudales
+                                .get(i)
+                                .map(|c| c.volumetrico().0)
+                                .unwrap_or_default()
+                        )
+                        .unwrap();
+                    }
+                }
+            }
+            Self::MapaHorario => {
+                let horas: Vec<String> = (0..24).map(|h| format!("{}h", h)).collect();
+                writeln!(out, "Día\t{}", horas.join("\t")).unwrap();
+                if let Some(zona) = zonedata {
+                    for (dia, valores) in st.carpet_var.values(zona).chunks(24).enumerate() {
+                        let valores: Vec<String> =
+                            valores.iter().map(|v| format!("{:.2}", v)).collect();
+                        writeln!(out, "{}\t{}", dia + 1, valores.join("\t")).unwrap();
+                    }
+                }
+            }
+            Self::Duracion => {
+                writeln!(out, "Hora\tCalefacción [W]\tRefrigeración [W]").unwrap();
+                if let Some(curvas) = st.duration_curves() {
+                    for (i, (cal, refr)) in curvas
+                        .calefaccion
+                        .iter()
+                        .zip(curvas.refrigeracion.iter())
+                        .enumerate()
+                    {
+                        writeln!(out, "{}\t{:.2}\t{:.2}", i + 1, cal, refr).unwrap();
+                    }
+                }
+            }
+            Self::RedZonas => {
+                writeln!(out, "Zona\tZona adyacente\tUA [W/K]").unwrap();
+                if let Some(red) = st.adjacency_network() {
+                    for enlace in &red.enlaces {
+                        writeln!(
+                            out,
+                            "{}\t{}\t{:.2}",
+                            red.nodos[enlace.a].nombre, red.nodos[enlace.b].nombre, enlace.ua
+                        )
+                        .unwrap();
+                    }
+                }
+            }
+        }
+        out
+    }
+}
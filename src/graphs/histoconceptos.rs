@@ -4,75 +4,74 @@
 
 use std::f64::consts::PI;
 
-use gtk::WidgetExt;
-
-use super::{draw_watermark, linear_scale, NORMAL_SIZE, SMALL_SIZE, TITLE_SIZE};
+use super::{
+    draw_watermark, linear_scale, nice_ticks,
+    style::{chart_style, set_color},
+    tick_decimals, Forma, Region, CONCEPT_LABELS,
+};
+use crate::analysis::contribuciones::Componente;
+use crate::analysis::unidades::Unidades;
 use crate::parsers::types::FlujosVec;
 
-const COLOR_RED: (f64, f64, f64) = (1.0, 0.0, 0.0);
-const COLOR_RED2: (f64, f64, f64) = (1.0, 0.4, 0.4);
-const COLOR_RED3: (f64, f64, f64) = (1.0, 0.6, 0.6);
-const COLOR_BLUE: (f64, f64, f64) = (0.0, 0.0, 1.0);
-const COLOR_BLUE2: (f64, f64, f64) = (0.4, 0.4, 1.0);
-const COLOR_BLUE3: (f64, f64, f64) = (0.6, 0.6, 1.0);
-
 /// Representa histograma de composición de demanda (demandas netas y por componentes): calpos, calneg, calnet, refpos, refneg, refnet
 ///
-/// El eje horizontal representa los conceptos de demanda y el eje vertical la demanda anual para el mismo [kWh/m²a]
+/// El eje horizontal representa los conceptos de demanda y el eje vertical la demanda anual para el mismo, en las unidades indicadas
+///
+/// Devuelve las regiones de las barras dibujadas, con su valor y porcentaje sobre el total de la serie
+#[allow(clippy::too_many_arguments)]
 pub fn draw_histoconceptos(
-    widget: &gtk::DrawingArea,
+    size: (f64, f64),
     cr: &cairo::Context,
     cur_name: &str,
     flujos: &FlujosVec,
     min: f32,
     max: f32,
     show_detail: bool,
-) {
+    unidades: Unidades,
+) -> Vec<Region> {
+    let style = chart_style();
     assert!(flujos.calnet.len() == 9 || flujos.calnet.len() == 1);
     assert!(flujos.refnet.len() == 9 || flujos.refnet.len() == 1);
 
     let xtitles = if flujos.calnet.len() == 9 {
-        vec![
-            "Paredes exteriores",
-            "Cubiertas",
-            "Suelos",
-            "Puentes térmicos",
-            "Solar ventanas",
-            "Transmisión ventanas",
-            "Fuentes internas",
-            "Ventilación e infiltración",
-            "TOTAL",
-        ]
+        let mut xtitles = CONCEPT_LABELS.to_vec();
+        xtitles.push("TOTAL");
+        xtitles
     } else {
         vec![cur_name]
     };
 
     let series = match show_detail {
         false => vec![
-            ("cal", &flujos.calnet, COLOR_RED),
-            ("ref", &flujos.refnet, COLOR_BLUE),
+            ("cal", &flujos.calnet, style.heating[0]),
+            ("ref", &flujos.refnet, style.cooling[0]),
         ],
         true => vec![
-            ("cal", &flujos.calnet, COLOR_RED),
-            ("cal+", &flujos.calpos, COLOR_RED2),
-            ("cal-", &flujos.calneg, COLOR_RED3),
-            ("ref", &flujos.refnet, COLOR_BLUE),
-            ("ref+", &flujos.refpos, COLOR_BLUE2),
-            ("ref-", &flujos.refneg, COLOR_BLUE3),
+            ("cal", &flujos.calnet, style.heating[0]),
+            ("cal+", &flujos.calpos, style.heating[1]),
+            ("cal-", &flujos.calneg, style.heating[2]),
+            ("ref", &flujos.refnet, style.cooling[0]),
+            ("ref+", &flujos.refpos, style.cooling[1]),
+            ("ref-", &flujos.refneg, style.cooling[2]),
         ],
     };
 
-    let min = ((min / 10.0 - 1.0).round() * 10.0) as f64;
-    let max = ((max / 10.0 + 1.0).round() * 10.0) as f64;
+    // Marcas del eje Y, con una división adicional en cada extremo para los rótulos de las barras
+    let mut yticks = nice_ticks(min as f64, max as f64, 10);
+    let ystep = yticks[1] - yticks[0];
+    yticks.insert(0, yticks[0] - ystep);
+    yticks.push(yticks[yticks.len() - 1] + ystep);
+    let ydecimals = tick_decimals(&yticks);
+    let (min, max) = (yticks[0], yticks[yticks.len() - 1]);
+    // Las barras fuera de la escala se recortan a sus límites, sin las divisiones adicionales
+    let (barmin, barmax) = (yticks[1], yticks[yticks.len() - 2]);
 
     let title = "Demandas por componente";
-    let ylabel = "Demanda [kWh/m²·año]";
+    let ylabel = format!("Demanda [{}]", unidades.anual());
     let numseries = series.len() as f64;
 
     // Posiciones
-    let rect = widget.get_allocation();
-    let width = rect.width as f64;
-    let height = rect.height as f64;
+    let (width, height) = size;
     let htitulo = 0.1 * height;
     let margin = 0.05 * height;
     let hgrafica = 0.9 * height - 3.0 * margin;
@@ -80,7 +79,6 @@ pub fn draw_histoconceptos(
     let (og_x, og_y) = (3.0 * margin, 0.1 * height); // Esquina sup. izq.
     let (eg_x, eg_y) = (og_x + wgrafica, og_y + hgrafica); // Esquina inf. der.
     let stepx = wgrafica / xtitles.len() as f64;
-    let stepy = hgrafica / (max - min).abs();
     let ticksize = wgrafica / 100.0;
     // Escalas lineales de X e Y sobre la gráfica
     let scalex = linear_scale(0.0, xtitles.len() as f64, og_x, eg_x);
@@ -92,15 +90,15 @@ pub fn draw_histoconceptos(
     cr.save();
 
     // Fondo
-    cr.set_source_rgb(1.0, 1.0, 1.0);
+    set_color(cr, style.background);
     cr.new_path();
     cr.rectangle(0.0, 0.0, width, height);
     cr.fill();
 
     // Título
-    cr.select_font_face("Arial", cairo::FontSlant::Normal, cairo::FontWeight::Bold);
-    cr.set_font_size(TITLE_SIZE);
-    cr.set_source_rgb(0.5, 0.5, 0.5);
+    style.select_font(cr, cairo::FontWeight::Bold);
+    cr.set_font_size(style.title_size);
+    set_color(cr, style.muted);
     let extents = cr.text_extents(title);
     cr.move_to(
         (width - extents.width) / 2.0,
@@ -109,37 +107,38 @@ pub fn draw_histoconceptos(
     cr.show_text(title);
 
     // Rótulos de ejes
-    cr.select_font_face("Arial", cairo::FontSlant::Normal, cairo::FontWeight::Normal);
+    style.select_font(cr, cairo::FontWeight::Normal);
 
     // YLabel
-    cr.set_font_size(NORMAL_SIZE);
-    cr.set_source_rgb(0.5, 0.5, 0.5);
-    let extents = cr.text_extents(ylabel);
+    cr.set_font_size(style.normal_size);
+    set_color(cr, style.muted);
+    let extents = cr.text_extents(&ylabel);
     cr.move_to(margin, og_y + (hgrafica + extents.width) / 2.0);
     cr.save();
     cr.rotate(-PI / 2.0);
-    cr.show_text(ylabel);
+    cr.show_text(&ylabel);
     cr.restore();
 
     // Etiquetas de componentes
     cr.set_line_width(1.0);
-    let layout = widget.create_pango_layout(None);
-    let fontdesc = pango::FontDescription::from_string(&format!("Arial Normal {}", SMALL_SIZE * 72.0/96.0));
-    layout.set_font_description(Some(&fontdesc));
-    layout.set_alignment(pango::Alignment::Center);
-    layout.set_width(pango::units_from_double((stepx * 0.9).round()));
-
-    for (i, label) in xtitles.iter().enumerate() {
-        layout.set_text(label);
-        let xpos = og_x + (i as f64 + 0.05) * stepx;
-        let ypos = eg_y + ticksize * 2.0;
-        cr.move_to(xpos, ypos);
-        pangocairo::show_layout(cr, &layout);
+    if let Some(layout) = pangocairo::create_layout(cr) {
+        let fontdesc = style.font_description(style.small_size);
+        layout.set_font_description(Some(&fontdesc));
+        layout.set_alignment(pango::Alignment::Center);
+        layout.set_width(pango::units_from_double((stepx * 0.9).round()));
+
+        for (i, label) in xtitles.iter().enumerate() {
+            layout.set_text(label);
+            let xpos = og_x + (i as f64 + 0.05) * stepx;
+            let ypos = eg_y + ticksize * 2.0;
+            cr.move_to(xpos, ypos);
+            pangocairo::show_layout(cr, &layout);
+        }
     }
 
     // Ticks en x
     cr.set_line_width(1.0);
-    cr.set_source_rgb(0.0, 0.0, 0.0);
+    set_color(cr, style.text);
     for i in 0..=xtitles.len() {
         cr.move_to(og_x + (i as f64) * stepx, eg_y);
         cr.rel_line_to(0.0, ticksize);
@@ -152,15 +151,12 @@ pub fn draw_histoconceptos(
     cr.rel_line_to(wgrafica, 0.0);
     cr.stroke();
 
-    // Líneas de y cada 10 kWh/m2·a
-    for i in 0.. {
-        let y = og_y + i as f64 * stepy * 10.0;
-        if y > eg_y {
-            break;
-        }
+    // Líneas de las marcas del eje Y
+    for ytick in &yticks {
+        let y = scaley(*ytick);
         cr.set_line_width(1.0);
-        cr.set_source_rgb(0.0, 0.0, 0.0);
-        let txt = format!("{:.0}", max - i as f64 * 10.0);
+        set_color(cr, style.text);
+        let txt = format!("{:.*}", ydecimals, ytick);
         let txt_ext = cr.text_extents(&txt);
         cr.move_to(og_x - 2.0 * ticksize - txt_ext.width, y);
         cr.show_text(&txt);
@@ -168,18 +164,19 @@ pub fn draw_histoconceptos(
         cr.rel_line_to(ticksize, 0.0);
         cr.stroke_preserve();
         cr.set_line_width(0.5);
-        cr.set_source_rgb(0.5, 0.5, 0.5);
+        set_color(cr, style.muted);
         cr.rel_line_to(wgrafica, 0.0);
         cr.stroke()
     }
 
     // Barras de las series
-    cr.set_source_rgb(0.0, 0.0, 0.0);
+    set_color(cr, style.text);
     cr.set_line_width(0.5);
-    cr.select_font_face("Arial", cairo::FontSlant::Normal, cairo::FontWeight::Normal);
-    cr.set_font_size(SMALL_SIZE);
+    style.select_font(cr, cairo::FontWeight::Normal);
+    cr.set_font_size(style.small_size);
     let extents = cr.text_extents("cal+");
     let (name_width, name_height) = (extents.width, extents.height);
+    let mut regiones = Vec::new();
 
     for (i_serie, (serie_name, vals, color)) in series.iter().enumerate() {
         let i_serie = i_serie as f64;
@@ -196,21 +193,31 @@ pub fn draw_histoconceptos(
             -name_height,
         );
         cr.stroke_preserve();
-        cr.set_source_rgb(color.0, color.1, color.2);
+        set_color(cr, *color);
         cr.fill();
 
         // Barras
         for (i_concepto, val) in vals.iter().enumerate() {
             // barra
-            cr.set_source_rgb(color.0, color.1, color.2);
+            set_color(cr, *color);
             cr.new_path();
             let x = scalex(i_concepto as f64 + i_serie / numseries);
-            let y = scaley(*val as f64);
+            let y = scaley((*val as f64).clamp(barmin, barmax));
             let height = y - y0;
             cr.rectangle(x, y, stepx / numseries, -height);
             cr.fill_preserve();
+            let (total, concepto) = if vals.len() == 9 {
+                (vals.last(), Some((i_concepto, *serie_name)))
+            } else {
+                (None, None)
+            };
+            regiones.push(Region {
+                forma: Forma::Rectangulo(x, y, stepx / numseries, -height),
+                texto: tooltip_text(xtitles[i_concepto], serie_name, *val, total, unidades),
+                concepto,
+            });
             // etiqueta
-            cr.set_source_rgb(0.0, 0.0, 0.0);
+            set_color(cr, style.text);
             cr.stroke();
             if val.abs() >= f32::EPSILON {
                 let txt = format!("{:.1}", val);
@@ -233,4 +240,25 @@ pub fn draw_histoconceptos(
 
     // Restauramos contexto
     cr.restore();
+
+    regiones
+}
+
+/// Texto descriptivo de una barra: concepto, serie, valor y porcentaje sobre el total de la serie
+fn tooltip_text(
+    concepto: &str,
+    serie: &str,
+    val: f32,
+    total: Option<&f32>,
+    unidades: Unidades,
+) -> String {
+    let serie = serie
+        .parse::<Componente>()
+        .map(Componente::label)
+        .unwrap_or(serie);
+    let mut txt = format!("{} - {}\n{:.2} {}", concepto, serie, val, unidades.anual());
+    if let Some(total) = total.filter(|t| t.abs() >= f32::EPSILON) {
+        txt.push_str(&format!(" ({:.1}% del total)", 100.0 * val / total));
+    }
+    txt
 }
@@ -4,9 +4,12 @@
 
 use std::f64::consts::PI;
 
-use gtk::WidgetExt;
-
-use super::{draw_watermark, linear_scale, MESES, NORMAL_SIZE, SMALL_SIZE, TITLE_SIZE};
+use super::{
+    draw_watermark, linear_scale, nice_ticks,
+    style::{chart_style, set_color},
+    tick_decimals, Forma, Region, MESES,
+};
+use crate::analysis::unidades::Unidades;
 
 // Pintar gráficas en gtkdrawingarea:
 // Ejemplos en: https://stackoverflow.com/questions/10250748/draw-an-image-on-drawing-area
@@ -16,29 +19,38 @@ use super::{draw_watermark, linear_scale, MESES, NORMAL_SIZE, SMALL_SIZE, TITLE_
 ///
 /// Se incluye la demanda de calefacción (neg) y refrigeración (pos).
 ///
-/// El eje horizontal representa los periodos [meses] y el eje vertical la demanda existente [kWh/m²mes]
+/// El eje horizontal representa los periodos [meses] y el eje vertical la demanda existente, en las unidades indicadas
 /// No está disponible para componentes
+///
+/// Devuelve las regiones de las barras dibujadas, con su valor y porcentaje sobre la demanda anual
 pub fn draw_histomeses(
-    widget: &gtk::DrawingArea,
+    size: (f64, f64),
     cr: &cairo::Context,
     calefaccion_meses: &[f32],
     refrigeracion_meses: &[f32],
     min: f32,
     max: f32,
-) {
+    unidades: Unidades,
+) -> Vec<Region> {
+    let style = chart_style();
     assert!(calefaccion_meses.len() == 12);
     assert!(refrigeracion_meses.len() == 12);
-    let min = ((min / 10.0 - 1.0).round() * 10.0) as f64;
-    let max = ((max / 10.0 + 1.0).round() * 10.0) as f64;
+    // Marcas del eje Y, con una división adicional en cada extremo para los rótulos de las barras
+    let mut yticks = nice_ticks(min as f64, max as f64, 10);
+    let ystep = yticks[1] - yticks[0];
+    yticks.insert(0, yticks[0] - ystep);
+    yticks.push(yticks[yticks.len() - 1] + ystep);
+    let ydecimals = tick_decimals(&yticks);
+    let (min, max) = (yticks[0], yticks[yticks.len() - 1]);
+    // Las barras fuera de la escala se recortan a sus límites, sin las divisiones adicionales
+    let (barmin, barmax) = (yticks[1], yticks[yticks.len() - 2]);
 
     let title = "Demanda neta mensual";
     let xlabel = "Mes";
-    let ylabel = "Demanda [kWh/m²·mes]";
+    let ylabel = format!("Demanda [{}]", unidades.mensual());
 
     // Posiciones
-    let rect = widget.get_allocation();
-    let width = rect.width as f64;
-    let height = rect.height as f64;
+    let (width, height) = size;
     let htitulo = 0.1 * height;
     let margin = 0.05 * height;
     let hgrafica = 0.9 * height - 2.0 * margin;
@@ -46,7 +58,6 @@ pub fn draw_histomeses(
     let (og_x, og_y) = (3.0 * margin, 0.1 * height); // Esquina sup. izq.
     let (eg_x, eg_y) = (og_x + wgrafica, og_y + hgrafica); // Esquina inf. der.
     let stepx = wgrafica / MESES.len() as f64;
-    let stepy = hgrafica / (max - min).abs();
     let ticksize = stepx / 10.0;
     // Escalas lineales de X e Y sobre la gráfica
     let scalex = linear_scale(0.0, MESES.len() as f64, og_x, eg_x);
@@ -58,15 +69,15 @@ pub fn draw_histomeses(
     cr.save();
 
     // Fondo
-    cr.set_source_rgb(1.0, 1.0, 1.0);
+    set_color(cr, style.background);
     cr.new_path();
     cr.rectangle(0.0, 0.0, width, height);
     cr.fill();
 
     // Título
-    cr.select_font_face("Arial", cairo::FontSlant::Normal, cairo::FontWeight::Bold);
-    cr.set_font_size(TITLE_SIZE);
-    cr.set_source_rgb(0.5, 0.5, 0.5);
+    style.select_font(cr, cairo::FontWeight::Bold);
+    cr.set_font_size(style.title_size);
+    set_color(cr, style.muted);
     let extents = cr.text_extents(title);
     cr.move_to(
         (width - extents.width) / 2.0,
@@ -75,16 +86,16 @@ pub fn draw_histomeses(
     cr.show_text(title);
 
     // Leyendas
-    cr.set_source_rgb(0.0, 0.0, 0.0);
+    set_color(cr, style.text);
     cr.set_line_width(0.5);
-    cr.select_font_face("Arial", cairo::FontSlant::Normal, cairo::FontWeight::Normal);
-    cr.set_font_size(SMALL_SIZE);
+    style.select_font(cr, cairo::FontWeight::Normal);
+    cr.set_font_size(style.small_size);
 
     let extents = cr.text_extents("cal+");
     let (name_width, name_height) = (extents.width, extents.height);
     // cal
     cr.move_to(x0, htitulo + 2.0 * name_height);
-    cr.set_source_rgb(0.0, 0.0, 0.0);
+    set_color(cr, style.text);
     cr.show_text("cal");
     cr.rectangle(
         x0 + name_width * 1.25,
@@ -93,11 +104,11 @@ pub fn draw_histomeses(
         -name_height,
     );
     cr.stroke_preserve();
-    cr.set_source_rgb(1.0, 0.0, 0.0);
+    set_color(cr, style.heating[0]);
     cr.fill();
     // ref
     cr.move_to(x0 + 3.0 * name_width, htitulo + 2.0 * name_height);
-    cr.set_source_rgb(0.0, 0.0, 0.0);
+    set_color(cr, style.text);
     cr.show_text("ref");
     cr.rectangle(
         x0 + name_width * 4.25,
@@ -106,33 +117,33 @@ pub fn draw_histomeses(
         -name_height,
     );
     cr.stroke_preserve();
-    cr.set_source_rgb(0.0, 0.0, 1.0);
+    set_color(cr, style.cooling[0]);
     cr.fill();
 
     // Rótulos de ejes
-    cr.select_font_face("Arial", cairo::FontSlant::Normal, cairo::FontWeight::Normal);
+    style.select_font(cr, cairo::FontWeight::Normal);
 
     // YLabel
     cr.save();
-    cr.set_font_size(NORMAL_SIZE);
-    cr.set_source_rgb(0.5, 0.5, 0.5);
-    let extents = cr.text_extents(ylabel);
+    cr.set_font_size(style.normal_size);
+    set_color(cr, style.muted);
+    let extents = cr.text_extents(&ylabel);
     cr.move_to(margin, og_y + (hgrafica + extents.width) / 2.0);
     cr.rotate(-PI / 2.0);
-    cr.show_text(ylabel);
+    cr.show_text(&ylabel);
     cr.restore();
 
     // XLabel
-    cr.set_font_size(NORMAL_SIZE);
-    cr.set_source_rgb(0.5, 0.5, 0.5);
+    cr.set_font_size(style.normal_size);
+    set_color(cr, style.muted);
     let extents = cr.text_extents(xlabel);
     cr.move_to((width - extents.width) / 2.0, height - margin / 2.0);
     cr.show_text(xlabel);
 
     // Meses
     cr.set_line_width(1.0);
-    cr.set_font_size(SMALL_SIZE);
-    cr.set_source_rgb(0.5, 0.5, 0.5);
+    cr.set_font_size(style.small_size);
+    set_color(cr, style.muted);
     let labelw = cr.text_extents("Sep").width;
     let mut xpos = og_x + (stepx - labelw) / 2.0;
     let ypos = eg_y + ticksize * 2.0;
@@ -144,7 +155,7 @@ pub fn draw_histomeses(
     }
     // Ticks en x
     cr.set_line_width(1.0);
-    cr.set_source_rgb(0.0, 0.0, 0.0);
+    set_color(cr, style.text);
     for i in 0..13 {
         cr.move_to(og_x + (i as f64) * stepx, eg_y);
         cr.rel_line_to(0.0, ticksize);
@@ -157,15 +168,12 @@ pub fn draw_histomeses(
     cr.rel_line_to(wgrafica, 0.0);
     cr.stroke();
 
-    // Líneas de y cada 10 kWh/m2·a
-    for i in 0.. {
-        let y = og_y + i as f64 * stepy * 10.0;
-        if y > eg_y {
-            break;
-        }
+    // Líneas de las marcas del eje Y
+    for ytick in &yticks {
+        let y = scaley(*ytick);
         cr.set_line_width(1.0);
-        cr.set_source_rgb(0.0, 0.0, 0.0);
-        let txt = format!("{:.0}", max - i as f64 * 10.0);
+        set_color(cr, style.text);
+        let txt = format!("{:.*}", ydecimals, ytick);
         let txt_ext = cr.text_extents(&txt);
         cr.move_to(og_x - 2.0 * ticksize - txt_ext.width, y);
         cr.show_text(&txt);
@@ -173,21 +181,46 @@ pub fn draw_histomeses(
         cr.rel_line_to(ticksize, 0.0);
         cr.stroke_preserve();
         cr.set_line_width(0.5);
-        cr.set_source_rgb(0.5, 0.5, 0.5);
+        set_color(cr, style.muted);
         cr.rel_line_to(wgrafica, 0.0);
         cr.stroke()
     }
 
+    // Regiones de las barras
+    let mut regiones = Vec::new();
+    let mut add_region = |x: f64, y: f64, height: f64, i: usize, serie: &str, valores: &[f32]| {
+        let total: f32 = valores.iter().sum();
+        let mut texto = format!(
+            "{} - {}\n{:.2} {}",
+            MESES[i],
+            serie,
+            valores[i],
+            unidades.mensual()
+        );
+        if total.abs() >= f32::EPSILON {
+            texto.push_str(&format!(
+                " ({:.1}% de la demanda anual)",
+                100.0 * valores[i] / total
+            ));
+        }
+        regiones.push(Region {
+            forma: Forma::Rectangulo(x, y, stepx, -height),
+            texto,
+            concepto: None,
+        });
+    };
+
     // Barras calefacción
     for (i, cal) in calefaccion_meses.iter().enumerate() {
         cr.new_path();
         let x = scalex(i as f64);
-        let y = scaley(*cal as f64);
+        let y = scaley((*cal as f64).clamp(barmin, barmax));
         let height = y - y0;
         cr.rectangle(x, y, stepx, -height);
-        cr.set_source_rgb(1.0, 0.0, 0.0);
+        add_region(x, y, height, i, "Calefacción", calefaccion_meses);
+        set_color(cr, style.heating[0]);
         cr.fill_preserve();
-        cr.set_source_rgb(0.0, 0.0, 0.0);
+        set_color(cr, style.text);
         cr.stroke();
         if cal.abs() >= f32::EPSILON {
             let txt = format!("{:.1}", cal);
@@ -202,12 +235,13 @@ pub fn draw_histomeses(
     for (i, refr) in refrigeracion_meses.iter().enumerate() {
         cr.new_path();
         let x = scalex(i as f64);
-        let y = scaley(*refr as f64);
+        let y = scaley((*refr as f64).clamp(barmin, barmax));
         let height = y - y0;
         cr.rectangle(x, y, stepx, -height);
-        cr.set_source_rgb(0.0, 0.0, 1.0);
+        add_region(x, y, height, i, "Refrigeración", refrigeracion_meses);
+        set_color(cr, style.cooling[0]);
         cr.fill_preserve();
-        cr.set_source_rgb(0.0, 0.0, 0.0);
+        set_color(cr, style.text);
         cr.stroke();
         if refr.abs() > f32::EPSILON {
             let txt = format!("{:.1}", refr);
@@ -223,4 +257,6 @@ pub fn draw_histomeses(
 
     // Restauramos contexto
     cr.restore();
+
+    regiones
 }
@@ -1,20 +1,40 @@
+use std::cmp::Ordering;
+use std::f64::consts::PI;
+
 use crate::{APP_NAME, APP_VERSION};
+use style::{chart_style, set_color};
 
+pub mod carpetplot;
+pub mod duracion;
+pub mod grafica;
 pub mod histoconceptos;
 pub mod histomeses;
 pub mod horarioszona;
 pub mod piechart;
+pub mod ranking;
+pub mod redzonas;
+pub mod sankey;
+pub mod style;
+pub mod zonasplanta;
 
-const TITLE_SIZE: f64 = 20.0;
-const NORMAL_SIZE: f64 = 14.0;
-const MID_SIZE: f64 = 12.0;
-const SMALL_SIZE: f64 = 11.0;
 /// Meses del año
 const MESES: [&str; 12] = [
     "Ene", "Feb", "Mar", "Abr", "May", "Jun", "Jul", "Ago", "Sep", "Oct", "Nov", "Dic",
 ];
 // const DIASMESES: [i32; 12] = [31, 28, 31, 30, 31, 30, 31, 31, 30, 31, 30, 31];
 
+/// Etiquetas de los 8 conceptos de demanda, en el orden de HULC
+pub const CONCEPT_LABELS: [&str; 8] = [
+    "Paredes exteriores",
+    "Cubiertas",
+    "Suelos",
+    "Puentes térmicos",
+    "Solar ventanas",
+    "Transmisión ventanas",
+    "Fuentes internas",
+    "Ventilación e infiltración",
+];
+
 /// Rounder to limit line "fuzziness"
 #[inline]
 pub fn rounder(x: f64) -> f64 {
@@ -34,26 +54,68 @@ pub fn linear_scale(domx1: f64, domx2: f64, rangex1: f64, rangex2: f64) -> impl
     move |x: f64| (x - domx1) * m + rangex1
 }
 
-/// Devuelve una lista con tick_count números "bonitos" para una escala que va del valor low al valor high
-pub fn nice_range(low: f64, high: f64, tick_count: usize) -> Vec<f64> {
-    let range = high - low;
-    let unrounded_ticksize = range / (tick_count - 1) as f64;
-    let x = (unrounded_ticksize.log10() - 1.0).ceil();
-    let pow10x = 10.0_f64.powi(x as i32);
-    let rounded_tickrange = (unrounded_ticksize / pow10x).ceil() * pow10x;
-    let newlower = rounded_tickrange * (low / rounded_tickrange).round();
-    let mut range = vec![];
-    for i in 0..(tick_count) {
-        range.push(newlower + i as f64 * rounded_tickrange);
-    }
-    range
+/// Compara dos valores para ordenarlos, considerando iguales los no comparables (NaN)
+pub fn nan_safe_cmp<T: PartialOrd>(a: &T, b: &T) -> Ordering {
+    a.partial_cmp(b).unwrap_or(Ordering::Equal)
+}
+
+/// Marcas "bonitas" de una escala que cubre el intervalo [low, high]
+///
+/// La separación entre marcas es 1, 2, 2.5 o 5 por una potencia de 10, la menor que no da más de
+/// max_ticks marcas. La primera marca es menor o igual que low y la última mayor o igual que high,
+/// de modo que los datos siempre quedan dentro de la escala. Un intervalo nulo se amplía a ambos
+/// lados para obtener una escala válida.
+pub fn nice_ticks(low: f64, high: f64, max_ticks: usize) -> Vec<f64> {
+    let (low, high) = if low <= high {
+        (low, high)
+    } else {
+        (high, low)
+    };
+    let (low, high) = if high - low < 1e-9 {
+        let delta = (low.abs() * 0.1).max(1.0);
+        (low - delta, high + delta)
+    } else {
+        (low, high)
+    };
+    let divisions = max_ticks.max(3) - 1;
+    let raw_step = (high - low) / divisions as f64;
+    let magnitude = 10.0_f64.powf(raw_step.log10().floor());
+    // Con una separación de 2 veces el paso mínimo siempre caben los datos en las divisiones
+    let step = [1.0, 2.0, 2.5, 5.0, 10.0, 20.0, 25.0, 50.0]
+        .iter()
+        .map(|m| m * magnitude)
+        .find(|step| ((high / step).ceil() - (low / step).floor()).round() as usize <= divisions)
+        .unwrap_or(100.0 * magnitude);
+    let first = (low / step).floor();
+    let last = (high / step).ceil();
+    let nticks = (last - first).round() as usize;
+    (0..=nticks)
+        .map(|i| (first + i as f64) * step)
+        // Evita el cero negativo en las etiquetas
+        .map(|v| if v.abs() < step * 1e-9 { 0.0 } else { v })
+        .collect()
+}
+
+/// Número de decimales necesarios para rotular las marcas de una escala
+pub fn tick_decimals(ticks: &[f64]) -> usize {
+    let step = match ticks {
+        [a, b, ..] => (b - a).abs(),
+        _ => return 0,
+    };
+    (0..4)
+        .find(|d| {
+            let scaled = step * 10.0_f64.powi(*d);
+            (scaled - scaled.round()).abs() < 1e-6
+        })
+        .unwrap_or(4) as usize
 }
 
 /// Dibuja marca de agua de la aplicación
 pub fn draw_watermark(cr: &cairo::Context, x: f64, y: f64) {
-    cr.select_font_face("Arial", cairo::FontSlant::Normal, cairo::FontWeight::Normal);
-    cr.set_font_size(SMALL_SIZE);
-    cr.set_source_rgb(0.2, 0.2, 0.2);
+    let style = chart_style();
+    style.select_font(cr, cairo::FontWeight::Normal);
+    cr.set_font_size(style.small_size);
+    set_color(cr, style.label);
     let mark = format!(
         "{} v.{} ({})",
         APP_NAME,
@@ -64,3 +126,238 @@ pub fn draw_watermark(cr: &cairo::Context, x: f64, y: f64) {
     cr.move_to(x - ext.width, y - 0.25 * ext.height);
     cr.show_text(&mark);
 }
+
+/// Dibuja etiqueta eje Y con centro en (x, y)
+pub fn draw_ytitle(cr: &cairo::Context, title: &str, x: f64, y: f64) {
+    let style = chart_style();
+    // YLabel
+    cr.save();
+    style.select_font(cr, cairo::FontWeight::Normal);
+    cr.set_font_size(style.normal_size * 0.8);
+    set_color(cr, style.muted);
+    let extents = cr.text_extents(title);
+    cr.move_to(x - extents.height / 2.0, y + extents.width / 2.0);
+    cr.rotate(-PI / 2.0);
+    cr.show_text(title);
+    cr.restore();
+}
+
+/// Líneas de separación de meses, etiquetas y ticks
+/// (x0, y0), (x1, y1) son las coordenadas de la esquina sup. izq. e inf. derecha.
+pub fn draw_months(cr: &cairo::Context, x0: f64, x1: f64, y0: f64, y1: f64) {
+    let style = chart_style();
+    let xstep = (x1 - x0) / 12.0;
+    let ticksize: f64 = xstep / 10.0;
+    cr.save();
+    style.select_font(cr, cairo::FontWeight::Normal);
+    cr.set_font_size(style.small_size);
+    cr.set_line_width(0.5);
+    set_color(cr, style.muted);
+    // Rótulos y ticks
+    let ext = cr.text_extents("Ene");
+    let th = ext.height;
+    for (i, mes) in MESES.iter().enumerate() {
+        cr.move_to(rounder(x0 + i as f64 * xstep), y1);
+        cr.rel_line_to(0.0, ticksize);
+        cr.stroke_preserve();
+        cr.rel_move_to(0.0, ticksize + th);
+        cr.show_text(mes);
+    }
+    cr.move_to(rounder(x1), y1);
+    cr.rel_line_to(0.0, ticksize);
+    cr.stroke();
+    // Líneas de mes
+    for i in 1..MESES.len() {
+        cr.move_to(rounder(x0 + i as f64 * xstep), y0);
+        cr.rel_line_to(0.0, y1 - y0);
+        cr.stroke();
+    }
+    cr.restore();
+}
+
+/// Subtítulo y marco
+///
+/// subtitle_height es la altura que tiene el encabezado, por encima del recuadro
+/// (x0, y0) es la esquina sup. izq de la gráfica
+/// width, height es el ancho, alto del recuadro
+pub fn draw_subtitle_and_box(
+    cr: &cairo::Context,
+    subtitle: &str,
+    subtitle_height: f64,
+    x0: f64,
+    y0: f64,
+    width: f64,
+    height: f64,
+) {
+    let style = chart_style();
+    cr.save();
+    cr.set_font_size(style.normal_size);
+    cr.set_line_width(0.5);
+    set_color(cr, style.muted);
+    let ext = cr.text_extents(subtitle);
+    cr.move_to(
+        rounder(x0 + (width - ext.width) / 2.0),
+        rounder(y0 - subtitle_height / 2.0),
+    );
+    cr.show_text(subtitle);
+    cr.rectangle(x0, y0, width, height);
+    cr.stroke();
+    cr.restore();
+}
+
+/// Etiquetas eje Y
+/// values: vector de (coord_y, etiqueta)
+/// ticksize: tamaño del tick
+/// x0: coordenada x del eje Y
+/// left_axis indica si es un eje a la izquierda o a la derecha de la gráfica
+pub fn ylabels(cr: &cairo::Context, values: &[(f64, String)], ticksize: f64, x0: f64, left_axis: bool) {
+    let style = chart_style();
+    cr.save();
+    style.select_font(cr, cairo::FontWeight::Normal);
+    cr.set_font_size(style.small_size);
+    cr.set_line_width(0.5);
+    set_color(cr, style.muted);
+    for (yval, label) in values {
+        cr.move_to(x0, *yval);
+        if left_axis {
+            cr.rel_line_to(-ticksize, 0.0);
+        } else {
+            cr.rel_line_to(ticksize, 0.0);
+        }
+        cr.stroke_preserve();
+        if left_axis {
+            let ext = cr.text_extents(label);
+            cr.rel_move_to(-(ticksize + ext.width), 0.0);
+        } else {
+            cr.rel_move_to(ticksize, 0.0);
+        }
+        cr.show_text(label);
+    }
+    cr.restore();
+}
+
+/// Forma de una región de una gráfica, en coordenadas del widget
+#[derive(Debug, Clone, Copy)]
+pub enum Forma {
+    /// Rectángulo (x, y, ancho, alto). El ancho y el alto pueden ser negativos
+    Rectangulo(f64, f64, f64, f64),
+    /// Sector circular (centro x, centro y, radio, ángulo inicial, ángulo final)
+    /// Los ángulos se miden en radianes, de 0 a 2π, en sentido horario desde el eje X
+    Sector(f64, f64, f64, f64, f64),
+}
+
+/// Región activa de una gráfica (barra, cuña, ...)
+///
+/// Las funciones de dibujo devuelven las regiones dibujadas para poder localizar
+/// el elemento bajo el puntero y mostrar su información
+#[derive(Debug, Clone)]
+pub struct Region {
+    /// Forma de la región
+    pub forma: Forma,
+    /// Texto descriptivo (valor, porcentaje y unidades)
+    pub texto: String,
+    /// Índice del concepto de demanda (0 a 8) y nombre de la serie, en barras de conceptos
+    pub concepto: Option<(usize, &'static str)>,
+}
+
+impl Region {
+    /// ¿Contiene la región el punto (x, y)?
+    pub fn contiene(&self, x: f64, y: f64) -> bool {
+        match self.forma {
+            Forma::Rectangulo(rx, ry, w, h) => {
+                let (x0, x1) = (rx.min(rx + w), rx.max(rx + w));
+                let (y0, y1) = (ry.min(ry + h), ry.max(ry + h));
+                x >= x0 && x <= x1 && y >= y0 && y <= y1
+            }
+            Forma::Sector(cx, cy, r, start, end) => {
+                let (dx, dy) = (x - cx, y - cy);
+                if dx * dx + dy * dy > r * r {
+                    return false;
+                }
+                let mut angle = dy.atan2(dx);
+                if angle < 0.0 {
+                    angle += 2.0 * PI;
+                }
+                angle >= start && angle <= end
+            }
+        }
+    }
+}
+
+/// Localiza la región que contiene el punto (x, y)
+pub fn find_region(regiones: &[Region], x: f64, y: f64) -> Option<&Region> {
+    regiones.iter().find(|r| r.contiene(x, y))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Comprueba que las marcas son equidistantes, cubren el intervalo y no superan el máximo
+    fn check_ticks(low: f64, high: f64, max_ticks: usize) -> Vec<f64> {
+        let ticks = nice_ticks(low, high, max_ticks);
+        assert!(
+            ticks.len() >= 2 && ticks.len() <= max_ticks.max(3),
+            "{:?}",
+            ticks
+        );
+        let step = ticks[1] - ticks[0];
+        // Se admite el error de redondeo en el cálculo de las marcas
+        let tol = step * 1e-9;
+        assert!(ticks[0] <= low.min(high) + tol && ticks[ticks.len() - 1] >= low.max(high) - tol);
+        assert!(ticks
+            .windows(2)
+            .all(|w| ((w[1] - w[0]) - step).abs() < step * 1e-9));
+        ticks
+    }
+
+    #[test]
+    fn nice_ticks_intervalos() {
+        assert_eq!(
+            check_ticks(0.0, 100.0, 6),
+            &[0.0, 20.0, 40.0, 60.0, 80.0, 100.0]
+        );
+        // Intervalo nulo
+        assert_eq!(check_ticks(5.0, 5.0, 5), &[4.0, 4.5, 5.0, 5.5, 6.0]);
+        assert_eq!(check_ticks(0.0, 0.0, 3), &[-1.0, 0.0, 1.0]);
+        // Intervalos negativos o invertidos
+        assert_eq!(check_ticks(-150.0, -3.0, 6), &[-150.0, -100.0, -50.0, 0.0]);
+        assert_eq!(check_ticks(10.0, -10.0, 5), &[-10.0, -5.0, 0.0, 5.0, 10.0]);
+        // Intervalos muy pequeños y muy grandes
+        let ticks = check_ticks(0.0001, 0.0009, 5);
+        assert!((ticks[1] - ticks[0] - 0.00025).abs() < 1e-12);
+        let ticks = check_ticks(0.0, 3.2e6, 8);
+        assert_eq!(ticks[1] - ticks[0], 500_000.0);
+        // Límite del número de marcas
+        for max_ticks in 0..12 {
+            for i in -200..200 {
+                let low = i as f64 * 7.31;
+                for ancho in &[0.37, 9.9, 123.4, 999.0, 3.2e6] {
+                    check_ticks(low, low + ancho, max_ticks);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn tick_decimals_escalas() {
+        assert_eq!(tick_decimals(&[0.0, 10.0, 20.0]), 0);
+        assert_eq!(tick_decimals(&[0.0, 0.5, 1.0]), 1);
+        assert_eq!(tick_decimals(&[0.0, 0.25, 0.5]), 2);
+        assert_eq!(tick_decimals(&[-0.002, 0.0]), 3);
+        assert_eq!(tick_decimals(&[0.0, 0.00001]), 4);
+        assert_eq!(tick_decimals(&[1.0]), 0);
+        assert_eq!(tick_decimals(&nice_ticks(0.0001, 0.0009, 5)), 4);
+    }
+
+    #[test]
+    fn nan_safe_cmp_orden() {
+        // Los valores NaN no producen un pánico al ordenar
+        let mut valores = [3.0, f64::NAN, 1.0, 2.0];
+        valores.sort_by(nan_safe_cmp);
+        assert!(valores.iter().any(|v| v.is_nan()));
+        let mut valores = [3.0, 1.0, 2.0];
+        valores.sort_by(|a, b| nan_safe_cmp(b, a));
+        assert_eq!(valores, [3.0, 2.0, 1.0]);
+    }
+}
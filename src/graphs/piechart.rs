@@ -4,47 +4,17 @@
 
 use std::f64::consts::PI;
 
-use gtk::WidgetExt;
-use itertools::izip;
-
+use crate::analysis::unidades::Unidades;
 use crate::parsers::types::FlujosVec;
 
-use super::draw_watermark;
-
-const COOLING_COLORS: [(f64, f64, f64); 8] = [
-    (0.0, 1.0, 1.0),
-    (0.0, 0.875, 1.0),
-    (0.0, 0.75, 1.0),
-    (0.0, 0.625, 1.0),
-    (0.0, 0.5, 1.0),
-    (0.0, 0.375, 1.0),
-    (0.0, 0.25, 1.0),
-    (0.0, 0.125, 1.0),
-];
-
-const HEATING_COLORS: [(f64, f64, f64); 8] = [
-    (1.0, 1.0, 0.0),
-    (1.0, 0.875, 0.0),
-    (1.0, 0.75, 0.0),
-    (1.0, 0.625, 0.0),
-    (1.0, 0.5, 0.0),
-    (1.0, 0.375, 0.0),
-    (1.0, 0.25, 0.0),
-    (1.0, 0.125, 0.0),
-];
-
-const PIE_LABELS: [&str; 8] = [
-    "Muros",
-    "Cubiertas",
-    "Suelos",
-    "PTs",
-    "Solar huecos",
-    "Transmisión huecos",
-    "Fuentes internas",
-    "Ventilación e infiltraciones",
-];
+use super::{
+    draw_watermark, nan_safe_cmp,
+    style::{chart_style, set_color},
+    Forma, Region, CONCEPT_LABELS,
+};
 
 /// Modo de visualización del gráfico de tarta
+#[derive(Debug, Clone, Copy)]
 pub enum PieMode {
     /// Ganancias de la temporada de calefacción
     CalPos,
@@ -56,6 +26,12 @@ pub enum PieMode {
     RefNeg,
 }
 
+/// Valor total mínimo para representar la gráfica, en las unidades de los datos
+const MIN_TOTAL: f64 = 0.01;
+
+/// Etiqueta de la cuña que agrupa los valores pequeños
+const OTROS_LABEL: &str = "Otros";
+
 /// Datos de cada valor
 struct Point {
     /// Etiqueta
@@ -64,6 +40,8 @@ struct Point {
     value: f64,
     /// Porcentaje, como cadena ("13.1%")
     value_pct: String,
+    /// Etiquetas de los valores agrupados en la cuña "Otros"
+    grouped: Vec<&'static str>,
     /// Ángulo inicial (radianes) 0 en eje X, pi/2 en eje Y.
     start_angle: f64,
     /// Ángluo final (radianes)
@@ -76,30 +54,51 @@ struct Point {
 
 /// Genera datos para la representación de la gráfica
 ///
+/// Se omiten los valores nulos y los valores con un porcentaje inferior a min_pct
+/// se agrupan en una cuña "Otros", si hay más de uno.
+/// Si la suma de los valores es (casi) nula devuelve una lista vacía.
+///
 /// Devuelve lista ordenada de elementos Point {label, value, value_pct, start_angle, end_angle}
-fn build_data(demandas: &[f64]) -> Vec<Point> {
+fn build_data(demandas: &[f64], min_pct: f64) -> Vec<Point> {
     let demanda_total: f64 = demandas.iter().map(|v: &f64| v.abs()).sum();
-    let demandas_pct = demandas.iter().map(|demanda| {
-        if demanda_total != 0.0 {
-            format!("{:.1}%", 100.0 * demanda.abs() / demanda_total)
-        } else {
-            "-".to_string()
-        }
-    });
+    if demanda_total < MIN_TOTAL {
+        return Vec::new();
+    }
+    let pct = |value: f64| 100.0 * value / demanda_total;
+    let point = |label: &str, value: f64, grouped: Vec<&'static str>| Point {
+        label: label.to_string(),
+        value,
+        value_pct: format!("{:.1}%", pct(value)),
+        grouped,
+        start_angle: 0.0,
+        end_angle: 0.0,
+        mid_angle: 0.0,
+        is_right: true,
+    };
 
-    // Datos para representar
-    let mut data: Vec<Point> = izip!(&PIE_LABELS, demandas.iter().cloned(), demandas_pct)
-        .map(|(label, value, ref value_pct)| Point {
-            label: label.to_string(),
-            value,
-            value_pct: value_pct.to_string(),
-            start_angle: 0.0,
-            end_angle: 0.0,
-            mid_angle: 0.0,
-            is_right: true,
-        })
+    // Datos para representar, agrupando los valores pequeños
+    let (small, large): (Vec<_>, Vec<_>) = CONCEPT_LABELS
+        .iter()
+        .zip(demandas.iter().map(|v| v.abs()))
+        .filter(|(_, value)| *value > 0.0)
+        .partition(|(_, value)| pct(*value) < min_pct);
+    let mut data: Vec<Point> = large
+        .iter()
+        .map(|(label, value)| point(label, *value, Vec::new()))
         .collect();
-    data.sort_by(|a, b| a.value.abs().partial_cmp(&b.value.abs()).unwrap());
+    if small.len() > 1 {
+        let value = small.iter().map(|(_, value)| value).sum();
+        let grouped = small.iter().map(|(label, _)| **label).collect();
+        data.push(point(OTROS_LABEL, value, grouped));
+    } else {
+        data.extend(
+            small
+                .iter()
+                .map(|(label, value)| point(label, *value, Vec::new())),
+        );
+    }
+    data.sort_by(|a, b| nan_safe_cmp(&a.value, &b.value));
+
     let angles: Vec<(f64, f64)> = data
         .iter()
         .map(|d| 2.0 * PI * d.value / demanda_total)
@@ -122,52 +121,89 @@ fn build_data(demandas: &[f64]) -> Vec<Point> {
     data
 }
 
+/// Posiciones verticales de las etiquetas de un lado de la gráfica, evitando solapes
+///
+/// Parte de las posiciones deseadas de las etiquetas, ordenadas de arriba abajo, y las desplaza
+/// lo mínimo para que estén separadas al menos step y queden dentro del intervalo [ymin, ymax].
+/// Si no caben, se reduce la separación para repartir el espacio disponible.
+fn place_labels(desired: &[f64], step: f64, ymin: f64, ymax: f64) -> Vec<f64> {
+    if desired.is_empty() {
+        return Vec::new();
+    }
+    let step = step.min((ymax - ymin) / desired.len() as f64);
+    let mut positions = desired.to_vec();
+    // De arriba abajo, evitando solapes con la etiqueta anterior
+    let mut previous = ymin - step;
+    for y in positions.iter_mut() {
+        *y = y.max(previous + step);
+        previous = *y;
+    }
+    // De abajo arriba, sin salirse por debajo
+    let mut next = ymax;
+    for y in positions.iter_mut().rev() {
+        *y = y.min(next - step);
+        next = *y;
+    }
+    positions
+}
+
 /// Dibuja gráfica de tarta para CalPos, CalNeg, RefPos y RefNeg
+///
+/// Los valores con un porcentaje inferior a min_pct se agrupan en una cuña "Otros".
+/// Los valores se muestran en las unidades indicadas.
+///
+/// Devuelve las regiones de las cuñas dibujadas, con su valor y porcentaje
 pub fn draw_piechart(
-    widget: &gtk::DrawingArea,
+    size: (f64, f64),
     cr: &cairo::Context,
     flujos: &FlujosVec,
     mode: PieMode,
-) {
-    let (title, colores, demandas) = match mode {
+    min_pct: f64,
+    unidades: Unidades,
+) -> Vec<Region> {
+    let style = chart_style();
+    let (title, colores, demandas, sin_flujos) = match mode {
         PieMode::CalPos => (
             "Ganancias térmicas, periodo de calefacción",
-            HEATING_COLORS,
+            style.heating_ramp,
             &flujos.calpos,
+            "Sin ganancias térmicas en el periodo de calefacción",
         ),
         PieMode::CalNeg => (
             "Pérdidas térmicas, periodo de calefacción",
-            HEATING_COLORS,
+            style.heating_ramp,
             &flujos.calneg,
+            "Sin pérdidas térmicas en el periodo de calefacción",
         ),
         PieMode::RefPos => (
             "Ganancias térmicas, periodo de refrigeración",
-            COOLING_COLORS,
+            style.cooling_ramp,
             &flujos.refpos,
+            "Sin ganancias térmicas en el periodo de refrigeración",
         ),
         PieMode::RefNeg => (
             "Pérdidas térmicas, periodo de refrigeración",
-            COOLING_COLORS,
+            style.cooling_ramp,
             &flujos.refneg,
+            "Sin pérdidas térmicas en el periodo de refrigeración",
         ),
     };
 
+    // Solo hay conceptos en edificio, plantas y zonas.
     // Si los datos tienen 9 valores es que incluyen al final el total... y lo eliminamos
-    let len = demandas.len();
-    let demandas = if len == 8 {
-        &demandas
-    } else {
-        &demandas[..len - 1]
+    let (demandas, sin_flujos) = match demandas.len() {
+        8 | 9 => (&demandas[..8], sin_flujos),
+        _ => (
+            &demandas[..0],
+            "Seleccione el edificio, una planta o una zona",
+        ),
     };
 
     let demandas = demandas.iter().map(|v| v.abs() as f64).collect::<Vec<_>>();
-    let mut data = build_data(&demandas);
-    let demanda_total: f64 = demandas.iter().map(|v| v.abs()).sum();
+    let mut data = build_data(&demandas, min_pct);
 
     // Posiciones
-    let rect = widget.get_allocation();
-    let width = rect.width as f64;
-    let height = rect.height as f64;
+    let (width, height) = size;
     let htitulo = 0.1 * height;
     let hgrafica = 0.9 * height;
     let wgrafica = 1.0 * width;
@@ -178,11 +214,16 @@ pub fn draw_piechart(
     cr.save();
 
     // Calculamos tamaño de fuente para no salirnos del borde de la imagen
-    cr.select_font_face("Arial", cairo::FontSlant::Normal, cairo::FontWeight::Normal);
+    style.select_font(cr, cairo::FontWeight::Normal);
     cr.set_font_size(fontsize);
     let textmargin = 5.0; // separación de flecha y texto
     let textlen = cr
-        .text_extents(PIE_LABELS.iter().max_by_key(|x| x.len()).unwrap_or(&"-"))
+        .text_extents(
+            CONCEPT_LABELS
+                .iter()
+                .max_by_key(|x| x.len())
+                .unwrap_or(&"-"),
+        )
         .width
         + 2.0 * textmargin;
     let textmaxwidth = 0.5 * wgrafica - 1.1 * radius;
@@ -192,39 +233,62 @@ pub fn draw_piechart(
     let (ox, oy) = (0.5 * wgrafica, htitulo + 0.5 * hgrafica - 2.0 * fontsize);
 
     // Fondo
-    cr.set_source_rgb(1.0, 1.0, 1.0);
+    set_color(cr, style.background);
     cr.new_path();
     cr.rectangle(0.0, 0.0, width, height);
     cr.fill();
 
     // Título
-    cr.select_font_face("Arial", cairo::FontSlant::Normal, cairo::FontWeight::Bold);
+    style.select_font(cr, cairo::FontWeight::Bold);
     cr.set_font_size(20.0);
-    cr.set_source_rgb(0.5, 0.5, 0.5);
+    set_color(cr, style.muted);
     let extents = cr.text_extents(title);
     cr.move_to(ox - extents.width / 2.0, 0.5 * (htitulo + extents.height));
     cr.show_text(title);
 
-    // Caso con demanda total nula
-    if demanda_total < 0.01 {
-        cr.set_source_rgb(0.5, 0.5, 0.5);
+    // Caso sin flujos o con flujos casi nulos
+    if data.is_empty() {
+        set_color(cr, style.muted);
         cr.set_line_width(0.5);
         cr.set_font_size(14.0);
         cr.move_to(ox + radius, oy);
         cr.arc(ox, oy, radius, 0.0, 2.0 * PI);
         cr.stroke();
-        let txt = "Sin datos de demanda o demanda casi nula";
+        let txt = sin_flujos;
         let extents = cr.text_extents(txt);
         cr.move_to(ox - extents.width / 2.0, oy - extents.height / 2.0);
         cr.show_text(txt);
         cr.restore();
-        return;
+        return Vec::new();
     }
 
     // Cuñas del círculo y radios
-    for (point, (r, g, b)) in data.iter().zip(colores.iter()) {
+    let mut regiones = Vec::new();
+    for (point, color) in data.iter().zip(colores.iter()) {
+        regiones.push(Region {
+            forma: Forma::Sector(ox, oy, radius, point.start_angle, point.end_angle),
+            texto: if point.grouped.is_empty() {
+                format!(
+                    "{}\n{:.2} {} ({})",
+                    point.label,
+                    point.value,
+                    unidades.anual(),
+                    point.value_pct
+                )
+            } else {
+                format!(
+                    "{}: {}\n{:.2} {} ({})",
+                    point.label,
+                    point.grouped.join(", "),
+                    point.value,
+                    unidades.anual(),
+                    point.value_pct
+                )
+            },
+            concepto: None,
+        });
         // Cuñas
-        cr.set_source_rgb(*r, *g, *b);
+        set_color(cr, *color);
         cr.move_to(ox, oy);
         cr.line_to(
             ox + radius * point.start_angle.cos(),
@@ -235,7 +299,7 @@ pub fn draw_piechart(
         cr.stroke_preserve();
         cr.fill();
         // Radios
-        cr.set_source_rgb(1.0, 1.0, 1.0);
+        set_color(cr, style.background);
         cr.set_line_width(0.5);
         cr.move_to(ox, oy);
         cr.line_to(
@@ -247,42 +311,50 @@ pub fn draw_piechart(
 
     // Leyendas
     // Reordenamos las cuñas de arriba abajo (eje Y positivo hacia abajo) para colocar etiquetas
-    data.sort_by(|a, b| {
-        (a.mid_angle.sin())
-            .partial_cmp(&(b.mid_angle.sin()))
-            .unwrap()
-    });
+    data.sort_by(|a, b| nan_safe_cmp(&a.mid_angle.sin(), &b.mid_angle.sin()));
 
     // Omite puntos con menos del 0.01%
     let skip_point = |p: &Point| (p.end_angle - p.start_angle) < 0.01 * 2.0 * PI / 100.0;
+    let data: Vec<&Point> = data.iter().filter(|p| !skip_point(p)).collect();
 
-    // Posiciones del texto a cada lado, descontando los % < 0.01%
     let txt_width = textmaxwidth - 40.0; // ancho disponible y margen de 20px por cada lado
-    let numlabels_right: i32 = data.iter().filter(|p| p.is_right && !skip_point(p)).count() as i32;
-    let numlabels_left: i32 = data
-        .iter()
-        .filter(|p| !p.is_right && !skip_point(p))
-        .count() as i32;
-
-    let layout = widget.create_pango_layout(Some("Prueba"));
-    let fontdesc = pango::FontDescription::from_string("Arial Normal 10.5");
+    let layout = match pangocairo::create_layout(cr) {
+        Some(layout) => layout,
+        // Sin contexto de texto se dibujan las cuñas sin leyendas
+        None => {
+            draw_watermark(cr, width - height * 0.05, htitulo);
+            cr.restore();
+            return regiones;
+        }
+    };
+    layout.set_text("Prueba");
+    let fontdesc = style.font_description(style.normal_size);
     layout.set_font_description(Some(&fontdesc));
     layout.set_width(pango::units_from_double(txt_width.round()));
-
     let (_, line_height) = layout.get_pixel_size();
-    let expected_height_left = ((numlabels_left * 3 - 1) * line_height) as f64;
-    let expected_height_right = ((numlabels_right * 3 - 1) * line_height) as f64;
-
+    let line_height = line_height as f64;
+
+    // Posiciones del texto a cada lado, sin solapes: cada etiqueta tiene dos líneas y
+    // se intenta situar a la altura de su cuña
+    let lead_y = |p: &Point| oy + 1.02 * radius * p.mid_angle.sin();
+    let side_positions = |right: bool| {
+        let desired: Vec<f64> = data
+            .iter()
+            .filter(|p| p.is_right == right)
+            .map(|p| lead_y(p) - line_height)
+            .collect();
+        place_labels(&desired, 2.5 * line_height, htitulo, height - line_height)
+    };
+    let mut txt_ypos_right = side_positions(true).into_iter();
+    let mut txt_ypos_left = side_positions(false).into_iter();
     let txt_xpos_right = wgrafica - txt_width;
     let txt_xpos_left = 20.0;
-    let mut txt_ypos_left = oy - expected_height_left / 2.0;
-    let mut txt_ypos_right = oy - expected_height_right / 2.0;
 
-    cr.set_source_rgb(0.0, 0.0, 0.0);
+    set_color(cr, style.text);
     cr.set_line_width(0.5);
-    cr.select_font_face("Arial", cairo::FontSlant::Normal, cairo::FontWeight::Normal);
+    style.select_font(cr, cairo::FontWeight::Normal);
     cr.set_font_size(fontsize);
-    for point in &data {
+    for point in data {
         let Point {
             label,
             value,
@@ -291,13 +363,9 @@ pub fn draw_piechart(
             end_angle,
             mid_angle,
             is_right,
+            ..
         } = point;
 
-        // Omite valores < 0.01 %
-        if skip_point(point) {
-            continue;
-        };
-
         // Porcentajes, solo si hay hueco
         let extents = cr.text_extents(value_pct);
         let available_height = (2.0 / 3.0 * radius * (end_angle - start_angle).sin()).abs();
@@ -309,30 +377,37 @@ pub fn draw_piechart(
             cr.show_text(value_pct);
         }
 
+        // Posición del texto
+        let (txt_xpos, txt_ypos) = if *is_right {
+            (txt_xpos_right, txt_ypos_right.next().unwrap_or(oy))
+        } else {
+            (txt_xpos_left, txt_ypos_left.next().unwrap_or(oy))
+        };
+
         // Líneas
         let x_start_lead = ox + 1.02 * radius * mid_angle.cos();
-        let y_start_lead = oy + 1.02 * radius * mid_angle.sin();
-        cr.set_source_rgb(0.5, 0.5, 0.5);
+        let y_start_lead = lead_y(point);
+        set_color(cr, style.muted);
         cr.move_to(x_start_lead, y_start_lead);
         if *is_right {
             cr.line_to(ox + radius + 10.0, y_start_lead);
-            cr.line_to(txt_xpos_right - 10.0, txt_ypos_right + line_height as f64);
+            cr.line_to(txt_xpos - 10.0, txt_ypos + line_height);
         } else {
             cr.line_to(ox - radius - 10.0, y_start_lead);
-            cr.line_to(textmaxwidth - 10.0, txt_ypos_left + line_height as f64);
+            cr.line_to(textmaxwidth - 10.0, txt_ypos + line_height);
         };
         cr.stroke();
 
         // Textos
-        cr.set_source_rgb(0.0, 0.0, 0.0);
-        layout.set_text(&format!("{}\n{:.1} kWh/m2·a ({})", label, value, value_pct));
-        if *is_right {
-            cr.move_to(txt_xpos_right, txt_ypos_right);
-            txt_ypos_right += line_height as f64 * 3.0;
-        } else {
-            cr.move_to(txt_xpos_left, txt_ypos_left);
-            txt_ypos_left += line_height as f64 * 3.0;
-        };
+        set_color(cr, style.text);
+        layout.set_text(&format!(
+            "{}\n{:.1} {} ({})",
+            label,
+            value,
+            unidades.anual(),
+            value_pct
+        ));
+        cr.move_to(txt_xpos, txt_ypos);
         pangocairo::show_layout(cr, &layout);
     }
 
@@ -340,4 +415,6 @@ pub fn draw_piechart(
 
     // Restauramos contexto
     cr.restore();
+
+    regiones
 }
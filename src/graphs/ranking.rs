@@ -0,0 +1,176 @@
+//! Clasificación de los elementos con peor comportamiento del edificio
+//!
+//! Diagrama de barras horizontales con los elementos ordenados según su contribución a las
+//! pérdidas en calefacción o a las ganancias en refrigeración, referida a la superficie del edificio.
+
+use super::{
+    draw_subtitle_and_box, draw_watermark, linear_scale, nice_ticks, rounder,
+    style::{chart_style, set_color, set_color_alpha},
+    tick_decimals,
+};
+use crate::analysis::ranking::{CriterioRanking, PosicionRanking};
+use crate::analysis::unidades::Unidades;
+
+/// Número máximo de elementos representados
+const MAX_ELEMENTOS: usize = 25;
+
+/// Dibuja la clasificación de elementos
+///
+/// Se resaltan los elementos de las zonas seleccionadas (todos si la lista está vacía).
+/// Los valores están referidos a la superficie del edificio, salvo en unidades absolutas.
+pub fn draw_ranking(
+    size: (f64, f64),
+    cr: &cairo::Context,
+    posiciones: &[PosicionRanking],
+    criterio: CriterioRanking,
+    seleccion: &[String],
+    unidades: Unidades,
+) {
+    let style = chart_style();
+    let title = "Elementos con peor comportamiento";
+
+    // Posiciones y cálculos previos
+    let (widget_width, widget_height) = size;
+    let htitle = 0.1 * widget_height;
+    let subtitle_block_height = 0.05 * widget_height;
+    let margin = 0.07 * widget_height;
+
+    let x0 = 0.35 * widget_width;
+    let x1 = widget_width - 2.0 * margin;
+    let y0 = htitle + subtitle_block_height;
+    let y1 = widget_height - 1.5 * margin;
+    let width = x1 - x0;
+    let height = y1 - y0;
+
+    cr.save();
+
+    // Fondo
+    set_color(cr, style.background);
+    cr.rectangle(1.0, 1.0, widget_width, widget_height);
+    cr.fill();
+
+    // Título
+    style.select_font(cr, cairo::FontWeight::Bold);
+    cr.set_font_size(style.title_size);
+    set_color(cr, style.muted);
+    let extents = cr.text_extents(title);
+    cr.move_to(
+        (widget_width - extents.width) / 2.0,
+        0.5 * (htitle + extents.height),
+    );
+    cr.show_text(title);
+
+    if posiciones.is_empty() {
+        let txt = "Sin elementos para los filtros seleccionados";
+        let te = cr.text_extents(txt);
+        cr.move_to((widget_width - te.width) / 2.0, widget_height * 0.5);
+        cr.show_text(txt);
+        cr.restore();
+        return;
+    }
+
+    let posiciones = &posiciones[..posiciones.len().min(MAX_ELEMENTOS)];
+    let color = match criterio {
+        CriterioRanking::PerdidasCalefaccion => style.heating[0],
+        CriterioRanking::GananciasRefrigeracion => style.cooling[0],
+    };
+
+    // Subtítulo y marco
+    let subtitle = match unidades {
+        Unidades::Absoluta => format!("{} [{}]", criterio.label(), unidades.anual()),
+        _ => format!(
+            "{} referidas a la superficie del edificio [kWh/m²·año]",
+            criterio.label()
+        ),
+    };
+    draw_subtitle_and_box(cr, &subtitle, subtitle_block_height, x0, y0, width, height);
+
+    // Escala X y rejilla
+    let vmax = posiciones
+        .iter()
+        .map(|p| p.valor as f64)
+        .fold(0.0, f64::max)
+        .max(0.01);
+    let range = nice_ticks(0.0, vmax, 5);
+    let xmax = range[range.len() - 1];
+    let decimals = tick_decimals(&range);
+    let xscale = linear_scale(0.0, xmax, x0, x1);
+    cr.save();
+    cr.set_font_size(style.small_size);
+    cr.set_line_width(0.5);
+    for v in &range {
+        let x = rounder(xscale(*v));
+        set_color(cr, style.grid);
+        cr.move_to(x, y0);
+        cr.line_to(x, y1);
+        cr.stroke();
+        let txt = format!("{:.*}", decimals, v);
+        let te = cr.text_extents(&txt);
+        set_color(cr, style.muted);
+        cr.move_to(x - te.width / 2.0, y1 + 1.5 * te.height);
+        cr.show_text(&txt);
+    }
+    cr.restore();
+
+    // Barras y rótulos
+    let step = height / MAX_ELEMENTOS.max(posiciones.len()) as f64;
+    let barheight = 0.7 * step;
+    cr.set_font_size(style.small_size);
+    for (i, p) in posiciones.iter().enumerate() {
+        let y = y0 + i as f64 * step + (step - barheight) / 2.0;
+        let alpha = if seleccion.is_empty() || seleccion.contains(&p.zona) {
+            1.0
+        } else {
+            0.35
+        };
+        set_color_alpha(cr, color, 0.6 * alpha);
+        cr.rectangle(x0, y, xscale(p.valor.max(0.0) as f64) - x0, barheight);
+        cr.fill();
+
+        // Elemento, tipo, planta y multiplicador a la izquierda
+        let mut label = format!("{}. {} ({}, {}", i + 1, p.elemento, p.tipo, p.planta);
+        if p.multiplicador > 1 {
+            label.push_str(&format!(", x{}", p.multiplicador));
+        }
+        label.push(')');
+        style.select_font(cr, cairo::FontWeight::Normal);
+        set_color_alpha(cr, style.label, alpha);
+        let te = cr.text_extents(&label);
+        cr.move_to(x0 - te.width - 6.0, y + (barheight + te.height) / 2.0);
+        cr.show_text(&label);
+
+        // Valor y fracción del total al final de la barra
+        let txt = format!("{:.2} ({:.1}%)", p.valor, 100.0 * p.fraccion);
+        style.select_font(cr, cairo::FontWeight::Bold);
+        let te = cr.text_extents(&txt);
+        let xbar = xscale(p.valor.max(0.0) as f64);
+        let xtxt = if xbar + te.width + 6.0 > x1 {
+            xbar - te.width - 4.0
+        } else {
+            xbar + 4.0
+        };
+        cr.move_to(xtxt, y + (barheight + te.height) / 2.0);
+        cr.show_text(&txt);
+    }
+
+    // Nota de elementos no representados
+    cr.set_font_size(style.mid_size);
+    style.select_font(cr, cairo::FontWeight::Normal);
+    set_color(cr, style.label);
+    let total: f32 = posiciones.iter().map(|p| p.fraccion).sum();
+    let note = format!(
+        "Se muestran {} elementos, con el {:.1}% del total del edificio",
+        posiciones.len(),
+        100.0 * total
+    );
+    let te = cr.text_extents(&note);
+    cr.move_to(
+        (widget_width - te.width) / 2.0,
+        widget_height - 0.3 * margin,
+    );
+    cr.show_text(&note);
+
+    draw_watermark(cr, widget_width - widget_height * 0.05, htitle);
+
+    cr.restore();
+}
@@ -0,0 +1,224 @@
+//! Red de adyacencia entre zonas
+//!
+//! Las zonas se disponen en círculo, por orden de nombre (y, por tanto, agrupadas por plantas).
+//! El tamaño de cada nodo es proporcional a la superficie de la zona y el grosor de su anillo exterior
+//! al UA con el exterior. El grosor de las líneas que unen las zonas es proporcional al UA entre ellas.
+
+use std::f64::consts::PI;
+
+use super::{
+    draw_watermark,
+    style::{chart_style, set_color, set_color_alpha},
+};
+use crate::analysis::red::RedZonas;
+
+/// Grosor máximo de las líneas de acoplamiento entre zonas y del anillo de UA exterior
+const MAX_LINE_WIDTH: f64 = 10.0;
+
+/// Dibuja la red de adyacencia de las zonas
+///
+/// Se resaltan las zonas seleccionadas y sus acoplamientos (todas si la lista está vacía)
+pub fn draw_redzonas(
+    size: (f64, f64),
+    cr: &cairo::Context,
+    red: Option<&RedZonas>,
+    seleccion: &[String],
+) {
+    let style = chart_style();
+    let title = "Red de adyacencia entre zonas";
+
+    // Posiciones y cálculos previos
+    let (widget_width, widget_height) = size;
+    let htitle = 0.1 * widget_height;
+    let margin = 0.07 * widget_height;
+
+    cr.save();
+
+    // Fondo
+    set_color(cr, style.background);
+    cr.rectangle(1.0, 1.0, widget_width, widget_height);
+    cr.fill();
+
+    // Título
+    style.select_font(cr, cairo::FontWeight::Bold);
+    cr.set_font_size(style.title_size);
+    set_color(cr, style.muted);
+    let extents = cr.text_extents(title);
+    cr.move_to(
+        (widget_width - extents.width) / 2.0,
+        0.5 * (htitle + extents.height),
+    );
+    cr.show_text(title);
+
+    // Sin datos horarios (.bin) dibujamos una nota
+    let red = match red {
+        Some(red) if !red.nodos.is_empty() => red,
+        _ => {
+            let txt = "Sin datos de zonas (.bin) para el modelo actual";
+            let te = cr.text_extents(txt);
+            cr.move_to((widget_width - te.width) / 2.0, widget_height * 0.5);
+            cr.show_text(txt);
+            cr.restore();
+            return;
+        }
+    };
+
+    // Disposición circular de las zonas
+    let cx = widget_width / 2.0;
+    let cy = (htitle + widget_height - margin) / 2.0;
+    let radius = 0.5 * (widget_height - htitle - 3.0 * margin).min(widget_width - 6.0 * margin);
+    let nnodos = red.nodos.len();
+    let angle = |i: usize| -PI / 2.0 + 2.0 * PI * i as f64 / nnodos as f64;
+    let pos: Vec<(f64, f64)> = (0..nnodos)
+        .map(|i| (cx + radius * angle(i).cos(), cy + radius * angle(i).sin()))
+        .collect();
+
+    // Tamaño de los nodos según superficie (área del círculo proporcional a la superficie)
+    let max_node = (PI * radius / nnodos as f64).min(0.1 * radius).max(4.0);
+    let area_max = red
+        .nodos
+        .iter()
+        .map(|n| n.area)
+        .fold(0.0, f32::max)
+        .max(1.0) as f64;
+    let node_radius = |area: f32| max_node * (area as f64 / area_max).sqrt().max(0.2);
+    let ua_max = red.ua_max().max(0.1) as f64;
+    let ua_ext_max = red.nodos.iter().map(|n| n.ua_ext).fold(0.1, f32::max) as f64;
+
+    let ua_int_total = red.ua_int_total();
+
+    let resaltado = |i: usize| seleccion.is_empty() || seleccion.contains(&red.nodos[i].nombre);
+
+    // Acoplamientos entre zonas (primero los no resaltados, para que queden debajo)
+    cr.set_line_cap(cairo::LineCap::Round);
+    for destacar in &[false, true] {
+        for enlace in &red.enlaces {
+            let activo = resaltado(enlace.a) || resaltado(enlace.b);
+            if activo != *destacar {
+                continue;
+            }
+            let (xa, ya) = pos[enlace.a];
+            let (xb, yb) = pos[enlace.b];
+            if activo {
+                set_color_alpha(cr, style.highlight, 0.7);
+            } else {
+                set_color_alpha(cr, style.muted, 0.3);
+            }
+            cr.set_line_width((MAX_LINE_WIDTH * enlace.ua as f64 / ua_max).max(0.5));
+            cr.move_to(xa, ya);
+            // Curva hacia el centro para distinguir enlaces entre zonas próximas
+            cr.curve_to(
+                (xa + cx) / 2.0,
+                (ya + cy) / 2.0,
+                (xb + cx) / 2.0,
+                (yb + cy) / 2.0,
+                xb,
+                yb,
+            );
+            cr.stroke();
+        }
+    }
+
+    // Valores de UA de los acoplamientos de las zonas seleccionadas
+    style.select_font(cr, cairo::FontWeight::Normal);
+    cr.set_font_size(style.small_size);
+    if !seleccion.is_empty() {
+        for enlace in red
+            .enlaces
+            .iter()
+            .filter(|e| resaltado(e.a) || resaltado(e.b))
+        {
+            let (xa, ya) = pos[enlace.a];
+            let (xb, yb) = pos[enlace.b];
+            // Punto medio de la curva de Bézier
+            let xm = 0.125 * (xa + xb) + 0.375 * (xa + xb + 2.0 * cx) / 2.0;
+            let ym = 0.125 * (ya + yb) + 0.375 * (ya + yb + 2.0 * cy) / 2.0;
+            let txt = format!("{:.1}", enlace.ua);
+            let te = cr.text_extents(&txt);
+            set_color_alpha(cr, style.background, 0.8);
+            cr.rectangle(
+                xm - te.width / 2.0 - 1.0,
+                ym - te.height / 2.0 - 1.0,
+                te.width + 2.0,
+                te.height + 2.0,
+            );
+            cr.fill();
+            set_color(cr, style.highlight);
+            cr.move_to(xm - te.width / 2.0, ym + te.height / 2.0);
+            cr.show_text(&txt);
+        }
+    }
+
+    // Zonas: anillo de UA exterior, círculo según superficie y etiquetas
+    for (i, nodo) in red.nodos.iter().enumerate() {
+        let (x, y) = pos[i];
+        let r = node_radius(nodo.area);
+        let ring = (MAX_LINE_WIDTH * nodo.ua_ext as f64 / ua_ext_max).max(1.0);
+        let alpha = if resaltado(i) { 1.0 } else { 0.3 };
+
+        set_color_alpha(cr, style.accent, alpha);
+        cr.arc(x, y, r + ring / 2.0, 0.0, 2.0 * PI);
+        cr.set_line_width(ring);
+        cr.stroke();
+
+        set_color_alpha(cr, style.grid, alpha);
+        cr.arc(x, y, r, 0.0, 2.0 * PI);
+        cr.fill();
+
+        // Etiquetas hacia el exterior del círculo
+        let (dx, dy) = (angle(i).cos(), angle(i).sin());
+        let label = if nodo.multiplicador > 1 {
+            format!("{} (x{})", nodo.nombre, nodo.multiplicador)
+        } else {
+            nodo.nombre.clone()
+        };
+        let detail = format!(
+            "{:.1} m², UAext {:.1} W/K, UAint {:.1} W/K",
+            nodo.area, nodo.ua_ext, ua_int_total[i]
+        );
+        set_color_alpha(cr, style.label, alpha);
+        let lx = x + dx * (r + ring + 6.0);
+        let ly = y + dy * (r + ring + 6.0);
+        let mut yoff = if dy < -0.3 { -1.5 } else { 0.0 };
+        for (txt, weight) in &[
+            (label.as_str(), cairo::FontWeight::Bold),
+            (detail.as_str(), cairo::FontWeight::Normal),
+        ] {
+            style.select_font(cr, *weight);
+            let te = cr.text_extents(txt);
+            // Texto a la derecha o a la izquierda del nodo según su posición
+            let tx = if dx.abs() < 0.3 {
+                lx - te.width / 2.0
+            } else if dx > 0.0 {
+                lx
+            } else {
+                lx - te.width
+            };
+            yoff += 1.0;
+            cr.move_to(
+                tx,
+                ly + (yoff - 0.3) * 1.3 * te.height.max(style.small_size * 0.8),
+            );
+            cr.show_text(txt);
+        }
+    }
+
+    // Leyenda
+    cr.set_font_size(style.mid_size);
+    style.select_font(cr, cairo::FontWeight::Normal);
+    set_color(cr, style.label);
+    let legend = format!(
+        "Tamaño: superficie de la zona - Anillo azul: UA con el exterior (máx. {:.1} W/K) - Líneas: UA entre zonas (máx. {:.1} W/K)",
+        ua_ext_max, ua_max
+    );
+    let te = cr.text_extents(&legend);
+    cr.move_to(
+        (widget_width - te.width) / 2.0,
+        widget_height - 0.5 * margin,
+    );
+    cr.show_text(&legend);
+
+    draw_watermark(cr, widget_width - widget_height * 0.05, htitle);
+
+    cr.restore();
+}
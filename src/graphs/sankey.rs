@@ -0,0 +1,311 @@
+//! Diagrama de Sankey de los flujos de calor en temporadas de calefacción y refrigeración
+//!
+//! Para cada temporada, las ganancias de cada concepto (fuentes, a la izquierda) entran en el
+//! objeto (edificio, planta o zona) y salen por las pérdidas de cada concepto (sumideros, a la
+//! derecha). El balance se cierra con la demanda del sistema: como aporte de calor cuando las
+//! pérdidas superan a las ganancias o como extracción cuando las ganancias superan a las pérdidas.
+//!
+//! El grosor de los flujos es proporcional a su valor, con la misma escala en ambas temporadas.
+
+use super::{
+    draw_watermark,
+    style::{chart_style, set_color, set_color_alpha, ChartStyle, Color},
+    CONCEPT_LABELS,
+};
+use crate::analysis::unidades::Unidades;
+use crate::parsers::types::FlujosVec;
+
+/// Flujo mínimo representado, en las unidades de los datos
+const MIN_FLUJO: f32 = 0.01;
+
+/// Separación vertical entre nodos [px]
+const NODE_GAP: f64 = 6.0;
+
+/// Flujo de un nodo del diagrama
+struct Nodo {
+    label: String,
+    valor: f64,
+    color: Color,
+}
+
+/// Balance de una temporada
+struct Balance {
+    titulo: &'static str,
+    fuentes: Vec<Nodo>,
+    sumideros: Vec<Nodo>,
+}
+
+impl Balance {
+    /// Balance de la temporada a partir de las ganancias y pérdidas por conceptos
+    ///
+    /// La demanda del sistema se obtiene como diferencia entre ganancias y pérdidas,
+    /// con el color y nombre indicados. Los conceptos usan los colores del estilo.
+    fn new(
+        titulo: &'static str,
+        pos: &[f32],
+        neg: &[f32],
+        sistema: &str,
+        color: Color,
+        style: &ChartStyle,
+    ) -> Self {
+        let nodos = |valores: &[f32]| -> Vec<Nodo> {
+            valores
+                .iter()
+                .zip(CONCEPT_LABELS.iter().zip(style.concepts.iter()))
+                .filter(|(v, _)| v.abs() >= MIN_FLUJO)
+                .map(|(v, (label, color))| Nodo {
+                    label: label.to_string(),
+                    valor: v.abs() as f64,
+                    color: *color,
+                })
+                .collect()
+        };
+        let mut fuentes = nodos(pos);
+        let mut sumideros = nodos(neg);
+        let entradas: f64 = fuentes.iter().map(|n| n.valor).sum();
+        let salidas: f64 = sumideros.iter().map(|n| n.valor).sum();
+        let demanda = salidas - entradas;
+        if demanda >= MIN_FLUJO as f64 {
+            fuentes.push(Nodo {
+                label: format!("{} (aporte)", sistema),
+                valor: demanda,
+                color,
+            });
+        } else if -demanda >= MIN_FLUJO as f64 {
+            sumideros.push(Nodo {
+                label: format!("{} (extracción)", sistema),
+                valor: -demanda,
+                color,
+            });
+        }
+        Self {
+            titulo,
+            fuentes,
+            sumideros,
+        }
+    }
+
+    /// Flujo total que atraviesa el objeto
+    fn total(&self) -> f64 {
+        self.fuentes.iter().map(|n| n.valor).sum()
+    }
+
+    /// Número máximo de nodos a un lado del diagrama
+    fn max_nodos(&self) -> usize {
+        self.fuentes.len().max(self.sumideros.len())
+    }
+}
+
+/// Dibuja los diagramas de Sankey de calefacción y refrigeración del objeto activo
+///
+/// Solo está definido para el edificio, las plantas y las zonas (flujos por conceptos)
+pub fn draw_sankey(
+    size: (f64, f64),
+    cr: &cairo::Context,
+    cur_name: &str,
+    flujos: &FlujosVec,
+    unidades: Unidades,
+) {
+    let style = chart_style();
+    let title = "Balance de flujos de calor";
+
+    // Posiciones y cálculos previos
+    let (widget_width, widget_height) = size;
+    let htitle = 0.1 * widget_height;
+    let margin = 0.07 * widget_height;
+
+    cr.save();
+
+    // Fondo
+    set_color(cr, style.background);
+    cr.rectangle(1.0, 1.0, widget_width, widget_height);
+    cr.fill();
+
+    // Título
+    style.select_font(cr, cairo::FontWeight::Bold);
+    cr.set_font_size(style.title_size);
+    set_color(cr, style.muted);
+    let extents = cr.text_extents(title);
+    cr.move_to(
+        (widget_width - extents.width) / 2.0,
+        0.5 * (htitle + extents.height),
+    );
+    cr.show_text(title);
+
+    // Solo hay conceptos en edificio, plantas y zonas
+    if flujos.calpos.len() != 9 {
+        let txt = "Seleccione el edificio, una planta o una zona";
+        let te = cr.text_extents(txt);
+        cr.move_to((widget_width - te.width) / 2.0, widget_height * 0.5);
+        cr.show_text(txt);
+        cr.restore();
+        return;
+    }
+
+    let balances = [
+        Balance::new(
+            "Temporada de calefacción",
+            &flujos.calpos[..8],
+            &flujos.calneg[..8],
+            "Calefacción",
+            style.heating[0],
+            &style,
+        ),
+        Balance::new(
+            "Temporada de refrigeración",
+            &flujos.refpos[..8],
+            &flujos.refneg[..8],
+            "Refrigeración",
+            style.cooling[0],
+            &style,
+        ),
+    ];
+
+    // Escala común a ambos diagramas
+    let y0 = htitle + 0.5 * margin;
+    let y1 = widget_height - margin;
+    let max_nodos = balances.iter().map(Balance::max_nodos).max().unwrap_or(1);
+    let total = balances
+        .iter()
+        .map(Balance::total)
+        .fold(0.0, f64::max)
+        .max(MIN_FLUJO as f64);
+    let scale = ((y1 - y0) - NODE_GAP * max_nodos.saturating_sub(1) as f64).max(10.0) / total;
+
+    let wsankey = widget_width / 2.0;
+    for (i, balance) in balances.iter().enumerate() {
+        draw_balance(
+            cr,
+            balance,
+            cur_name,
+            i as f64 * wsankey,
+            y0,
+            wsankey,
+            y1 - y0,
+            scale,
+        );
+    }
+
+    // Leyenda
+    cr.set_font_size(style.mid_size);
+    style.select_font(cr, cairo::FontWeight::Normal);
+    set_color(cr, style.label);
+    let legend = format!(
+        "Ganancias por conceptos (izq.) y pérdidas por conceptos (dcha.) [{}]",
+        unidades.anual()
+    );
+    let te = cr.text_extents(&legend);
+    cr.move_to(
+        (widget_width - te.width) / 2.0,
+        widget_height - 0.3 * margin,
+    );
+    cr.show_text(&legend);
+
+    draw_watermark(cr, widget_width - widget_height * 0.05, htitle);
+
+    cr.restore();
+}
+
+/// Dibuja el diagrama de Sankey de una temporada en el recuadro indicado
+#[allow(clippy::too_many_arguments)]
+fn draw_balance(
+    cr: &cairo::Context,
+    balance: &Balance,
+    cur_name: &str,
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+    scale: f64,
+) {
+    let style = chart_style();
+    let node_width = 0.03 * width;
+    let xl = x + 0.3 * width;
+    let xr = x + 0.7 * width - node_width;
+    let xm = (xl + xr) / 2.0;
+    let ym = y + height / 2.0;
+
+    cr.save();
+
+    // Subtítulo
+    style.select_font(cr, cairo::FontWeight::Bold);
+    cr.set_font_size(style.mid_size);
+    set_color(cr, style.label);
+    let te = cr.text_extents(balance.titulo);
+    cr.move_to(x + (width - te.width) / 2.0, y);
+    cr.show_text(balance.titulo);
+
+    let total = balance.total();
+    if total < MIN_FLUJO as f64 {
+        style.select_font(cr, cairo::FontWeight::Normal);
+        let txt = "Sin flujos en la temporada";
+        let te = cr.text_extents(txt);
+        cr.move_to(x + (width - te.width) / 2.0, ym);
+        cr.show_text(txt);
+        cr.restore();
+        return;
+    }
+
+    // Nodo central (objeto)
+    let hm = total * scale;
+    let ym0 = ym - hm / 2.0;
+    set_color(cr, style.label);
+    cr.rectangle(xm - node_width / 2.0, ym0, node_width, hm);
+    cr.fill();
+    style.select_font(cr, cairo::FontWeight::Bold);
+    cr.set_font_size(style.small_size);
+    let label = format!("{} ({:.1})", cur_name, total);
+    let te = cr.text_extents(&label);
+    cr.move_to(xm - te.width / 2.0, ym0 - 4.0);
+    cr.show_text(&label);
+
+    // Fuentes (izquierda) y sumideros (derecha)
+    for (nodos, izquierda) in &[(&balance.fuentes, true), (&balance.sumideros, false)] {
+        let hnodos = nodos.iter().map(|n| n.valor * scale).sum::<f64>()
+            + NODE_GAP * nodos.len().saturating_sub(1) as f64;
+        let mut yn = ym - hnodos / 2.0;
+        let mut yc = ym0;
+        let xn = if *izquierda { xl } else { xr };
+        for nodo in nodos.iter() {
+            let h = nodo.valor * scale;
+
+            // Banda entre el nodo y el objeto
+            let (xa, xb) = if *izquierda {
+                (xn + node_width, xm - node_width / 2.0)
+            } else {
+                (xn, xm + node_width / 2.0)
+            };
+            let xc = (xa + xb) / 2.0;
+            set_color_alpha(cr, nodo.color, 0.45);
+            cr.move_to(xa, yn);
+            cr.curve_to(xc, yn, xc, yc, xb, yc);
+            cr.line_to(xb, yc + h);
+            cr.curve_to(xc, yc + h, xc, yn + h, xa, yn + h);
+            cr.close_path();
+            cr.fill();
+
+            // Nodo
+            set_color(cr, nodo.color);
+            cr.rectangle(xn, yn, node_width, h.max(1.0));
+            cr.fill();
+
+            // Rótulo
+            style.select_font(cr, cairo::FontWeight::Normal);
+            set_color(cr, style.label);
+            let txt = format!("{} {:.1}", nodo.label, nodo.valor);
+            let te = cr.text_extents(&txt);
+            let xt = if *izquierda {
+                xn - te.width - 4.0
+            } else {
+                xn + node_width + 4.0
+            };
+            cr.move_to(xt, yn + (h + te.height) / 2.0);
+            cr.show_text(&txt);
+
+            yn += h + NODE_GAP;
+            yc += h;
+        }
+    }
+
+    cr.restore();
+}
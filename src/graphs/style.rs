@@ -0,0 +1,307 @@
+//! Estilo de las gráficas: paleta de colores, tipografía, tamaños de texto y fondo
+//!
+//! El estilo activo se construye a partir de la configuración y lo usan todas las funciones de
+//! dibujo. El tema del sistema sigue al tema de GTK (claro u oscuro).
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use gtk::SettingsExt;
+
+use crate::config::Config;
+
+/// Color RGB con componentes entre 0 y 1
+pub type Color = (f64, f64, f64);
+
+/// Paleta de colores de las series de datos
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Palette {
+    /// Rojos para calefacción y azules para refrigeración
+    #[default]
+    Classic,
+    /// Paleta apta para daltonismo (Okabe-Ito), con bermellón y azul para calefacción y refrigeración
+    ColorBlind,
+}
+
+impl std::str::FromStr for Palette {
+    type Err = crate::utils::Error;
+
+    /// Obtiene la paleta a partir del identificador usado en la interfaz (p.e. "daltonismo")
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "clasica" => Ok(Self::Classic),
+            "daltonismo" => Ok(Self::ColorBlind),
+            _ => Err(format!("Paleta desconocida: {}", s).into()),
+        }
+    }
+}
+
+impl std::fmt::Display for Palette {
+    /// Identificador de la paleta usado en la interfaz y en la configuración
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let id = match self {
+            Self::Classic => "clasica",
+            Self::ColorBlind => "daltonismo",
+        };
+        write!(f, "{}", id)
+    }
+}
+
+/// Tema de color del fondo y los textos de las gráficas
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Theme {
+    /// Claro u oscuro según el tema de GTK
+    #[default]
+    System,
+    /// Fondo blanco y textos oscuros
+    Light,
+    /// Fondo oscuro y textos claros
+    Dark,
+}
+
+impl std::str::FromStr for Theme {
+    type Err = crate::utils::Error;
+
+    /// Obtiene el tema a partir del identificador usado en la interfaz (p.e. "oscuro")
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sistema" => Ok(Self::System),
+            "claro" => Ok(Self::Light),
+            "oscuro" => Ok(Self::Dark),
+            _ => Err(format!("Tema desconocido: {}", s).into()),
+        }
+    }
+}
+
+impl std::fmt::Display for Theme {
+    /// Identificador del tema usado en la interfaz y en la configuración
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let id = match self {
+            Self::System => "sistema",
+            Self::Light => "claro",
+            Self::Dark => "oscuro",
+        };
+        write!(f, "{}", id)
+    }
+}
+
+/// Estilo de las gráficas
+#[derive(Debug, Clone)]
+pub struct ChartStyle {
+    /// Familia tipográfica
+    pub font: String,
+    /// Tamaño de los títulos
+    pub title_size: f64,
+    /// Tamaño de los subtítulos y rótulos de ejes
+    pub normal_size: f64,
+    /// Tamaño de las leyendas
+    pub mid_size: f64,
+    /// Tamaño de las etiquetas
+    pub small_size: f64,
+    /// Color de fondo
+    pub background: Color,
+    /// Color de los textos y ejes principales
+    pub text: Color,
+    /// Color de los títulos, marcos y escalas
+    pub muted: Color,
+    /// Color de las etiquetas y leyendas
+    pub label: Color,
+    /// Color de las líneas de rejilla y rellenos neutros
+    pub grid: Color,
+    /// Colores de calefacción: neta, ganancias y pérdidas
+    pub heating: [Color; 3],
+    /// Colores de refrigeración: neta, ganancias y pérdidas
+    pub cooling: [Color; 3],
+    /// Gama de colores de los conceptos en calefacción
+    pub heating_ramp: [Color; 8],
+    /// Gama de colores de los conceptos en refrigeración
+    pub cooling_ramp: [Color; 8],
+    /// Colores de los 8 conceptos de demanda
+    pub concepts: [Color; 8],
+    /// Paleta secuencial para variables continuas (de menor a mayor valor)
+    pub continuous: [Color; 5],
+    /// Color de resalte de los elementos seleccionados
+    pub highlight: Color,
+    /// Color de acento de los elementos no asociados a una temporada
+    pub accent: Color,
+}
+
+impl Default for ChartStyle {
+    fn default() -> Self {
+        Self::new(Palette::default(), false, "Arial", 1.0)
+    }
+}
+
+impl ChartStyle {
+    /// Estilo con la paleta, tema (claro u oscuro), fuente y escala de texto indicados
+    pub fn new(palette: Palette, dark: bool, font: &str, font_scale: f64) -> Self {
+        let (background, text, muted, label, grid) = if dark {
+            (
+                (0.16, 0.16, 0.17),
+                (0.95, 0.95, 0.95),
+                (0.65, 0.65, 0.65),
+                (0.85, 0.85, 0.85),
+                (0.35, 0.35, 0.35),
+            )
+        } else {
+            (
+                (1.0, 1.0, 1.0),
+                (0.0, 0.0, 0.0),
+                (0.5, 0.5, 0.5),
+                (0.2, 0.2, 0.2),
+                (0.85, 0.85, 0.85),
+            )
+        };
+        let white = (1.0, 1.0, 1.0);
+        let seasons = |base: Color| [base, mix(base, white, 0.4), mix(base, white, 0.6)];
+
+        let (heating, cooling, heating_ramp, cooling_ramp, concepts, continuous, highlight, accent) =
+            match palette {
+                Palette::Classic => (
+                    seasons((1.0, 0.0, 0.0)),
+                    seasons((0.0, 0.0, 1.0)),
+                    ramp((1.0, 1.0, 0.0), (1.0, 0.0, 0.0)),
+                    ramp((0.0, 1.0, 1.0), (0.0, 0.0, 1.0)),
+                    [
+                        (0.60, 0.40, 0.20),
+                        (0.50, 0.50, 0.50),
+                        (0.40, 0.30, 0.50),
+                        (0.90, 0.60, 0.00),
+                        (1.00, 0.85, 0.20),
+                        (0.35, 0.70, 0.90),
+                        (0.80, 0.40, 0.70),
+                        (0.00, 0.60, 0.50),
+                    ],
+                    [
+                        (0.0, 0.0, 0.5),
+                        (0.0, 0.5, 1.0),
+                        (0.5, 1.0, 0.5),
+                        (1.0, 0.8, 0.0),
+                        (0.8, 0.0, 0.0),
+                    ],
+                    (0.9, 0.5, 0.0),
+                    (0.0, 0.4, 0.8),
+                ),
+                Palette::ColorBlind => (
+                    seasons((0.84, 0.37, 0.0)),
+                    seasons((0.0, 0.45, 0.70)),
+                    ramp((0.94, 0.89, 0.26), (0.84, 0.37, 0.0)),
+                    ramp((0.80, 0.92, 0.97), (0.0, 0.30, 0.55)),
+                    [
+                        (0.90, 0.62, 0.00),
+                        (0.60, 0.60, 0.60),
+                        (0.00, 0.45, 0.70),
+                        (0.80, 0.47, 0.65),
+                        (0.94, 0.89, 0.26),
+                        (0.34, 0.71, 0.91),
+                        (0.84, 0.37, 0.00),
+                        (0.00, 0.62, 0.45),
+                    ],
+                    // viridis
+                    [
+                        (0.27, 0.00, 0.33),
+                        (0.23, 0.32, 0.55),
+                        (0.13, 0.57, 0.55),
+                        (0.37, 0.79, 0.38),
+                        (0.99, 0.91, 0.14),
+                    ],
+                    (0.90, 0.62, 0.0),
+                    (0.34, 0.71, 0.91),
+                ),
+            };
+
+        Self {
+            font: font.to_string(),
+            title_size: 20.0 * font_scale,
+            normal_size: 14.0 * font_scale,
+            mid_size: 12.0 * font_scale,
+            small_size: 11.0 * font_scale,
+            background,
+            text,
+            muted,
+            label,
+            grid,
+            heating,
+            cooling,
+            heating_ramp,
+            cooling_ramp,
+            concepts,
+            continuous,
+            highlight,
+            accent,
+        }
+    }
+
+    /// Estilo definido en la configuración
+    ///
+    /// Con el tema del sistema se usa el tema oscuro si GTK prefiere un tema oscuro o el nombre
+    /// del tema de GTK lo indica (p.e. "Adwaita-dark")
+    pub fn from_config(config: &Config) -> Self {
+        let dark = match config.theme {
+            Theme::Light => false,
+            Theme::Dark => true,
+            Theme::System => match gtk::Settings::get_default() {
+                Some(s) => {
+                    s.get_property_gtk_application_prefer_dark_theme()
+                        || s.get_property_gtk_theme_name()
+                            .map(|name| name.to_lowercase().contains("dark"))
+                            .unwrap_or(false)
+                }
+                None => false,
+            },
+        };
+        Self::new(config.palette, dark, &config.font, config.font_scale)
+    }
+
+    /// Selecciona la fuente del estilo con el peso indicado
+    pub fn select_font(&self, cr: &cairo::Context, weight: cairo::FontWeight) {
+        cr.select_font_face(&self.font, cairo::FontSlant::Normal, weight);
+    }
+
+    /// Descripción de la fuente del estilo para pango, con el tamaño indicado en píxeles
+    pub fn font_description(&self, size: f64) -> pango::FontDescription {
+        pango::FontDescription::from_string(&format!("{} Normal {}", self.font, size * 72.0 / 96.0))
+    }
+}
+
+/// Establece el color de trazo y relleno
+pub fn set_color(cr: &cairo::Context, color: Color) {
+    cr.set_source_rgb(color.0, color.1, color.2);
+}
+
+/// Establece el color de trazo y relleno con la opacidad indicada
+pub fn set_color_alpha(cr: &cairo::Context, color: Color, alpha: f64) {
+    cr.set_source_rgba(color.0, color.1, color.2, alpha);
+}
+
+/// Mezcla de los colores a y b, con proporción t de b
+pub fn mix(a: Color, b: Color, t: f64) -> Color {
+    (
+        a.0 + (b.0 - a.0) * t,
+        a.1 + (b.1 - a.1) * t,
+        a.2 + (b.2 - a.2) * t,
+    )
+}
+
+/// Gama de 8 colores desde el color inicial hacia el final (sin alcanzarlo)
+fn ramp(from: Color, to: Color) -> [Color; 8] {
+    let mut colors = [from; 8];
+    for (i, color) in colors.iter_mut().enumerate() {
+        *color = mix(from, to, i as f64 / 8.0);
+    }
+    colors
+}
+
+thread_local! {
+    static CURRENT: RefCell<Rc<ChartStyle>> = RefCell::new(Rc::new(ChartStyle::default()));
+}
+
+/// Establece el estilo de las gráficas
+pub fn set_chart_style(style: ChartStyle) {
+    CURRENT.with(|s| *s.borrow_mut() = Rc::new(style));
+}
+
+/// Estilo activo de las gráficas
+pub fn chart_style() -> Rc<ChartStyle> {
+    CURRENT.with(|s| s.borrow().clone())
+}
@@ -0,0 +1,287 @@
+//! Histograma por zonas de las demandas de calefacción y refrigeración de una planta
+//!
+//! Cada zona se representa con dos barras (calefacción y refrigeración) de flujos netos, por m² de
+//! la zona o en las unidades seleccionadas, apilados por conceptos: los positivos (ganancias) hacia arriba y los negativos
+//! (pérdidas) hacia abajo. El ancho de cada zona es proporcional a su superficie (con multiplicador)
+//! y una marca indica la demanda neta total de la zona.
+
+use super::{
+    draw_subtitle_and_box, draw_watermark, draw_ytitle, linear_scale, nice_ticks, rounder,
+    style::{chart_style, set_color, set_color_alpha},
+    tick_decimals, ylabels, CONCEPT_LABELS,
+};
+use crate::analysis::unidades::Unidades;
+use crate::config::{Config, Escala};
+use crate::parsers::types::{EdificioLIDER, Flujos};
+
+/// Datos de una zona de la planta
+struct BarraZona {
+    nombre: String,
+    superficie: f64,
+    multiplicador: i32,
+    /// Flujos netos de los conceptos en calefacción y refrigeración, en las unidades de la gráfica
+    cal: Vec<f64>,
+    refr: Vec<f64>,
+    /// Demandas netas totales de calefacción y refrigeración, en las unidades de la gráfica
+    cal_total: f64,
+    ref_total: f64,
+}
+
+/// Extremos de una barra apilada (suma de negativos, suma de positivos)
+fn extremos(valores: &[f64]) -> (f64, f64) {
+    valores.iter().fold((0.0, 0.0), |(neg, pos), v| {
+        if *v < 0.0 {
+            (neg + v, pos)
+        } else {
+            (neg, pos + v)
+        }
+    })
+}
+
+/// Dibuja el histograma por zonas de la planta indicada
+///
+/// Se resaltan las zonas seleccionadas (todas si la lista está vacía)
+pub fn draw_zonasplanta(
+    size: (f64, f64),
+    cr: &cairo::Context,
+    edificio: Option<&EdificioLIDER>,
+    planta: Option<&str>,
+    seleccion: &[String],
+    unidades: Unidades,
+    config: &Config,
+) {
+    let style = chart_style();
+    let title = "Demandas por zonas de la planta";
+
+    // Posiciones y cálculos previos
+    let (widget_width, widget_height) = size;
+    let htitle = 0.1 * widget_height;
+    let subtitle_block_height = 0.05 * widget_height;
+    let margin = 0.07 * widget_height;
+
+    let x0 = 2.0 * margin;
+    let x1 = widget_width - x0;
+    let y0 = htitle + subtitle_block_height;
+    let y1 = widget_height - 3.0 * margin;
+    let width = x1 - x0;
+    let height = y1 - y0;
+    let ticksize = width / 10.0 / 12.0;
+
+    cr.save();
+
+    // Fondo
+    set_color(cr, style.background);
+    cr.rectangle(1.0, 1.0, widget_width, widget_height);
+    cr.fill();
+
+    // Título
+    style.select_font(cr, cairo::FontWeight::Bold);
+    cr.set_font_size(style.title_size);
+    set_color(cr, style.muted);
+    let extents = cr.text_extents(title);
+    cr.move_to(
+        (widget_width - extents.width) / 2.0,
+        0.5 * (htitle + extents.height),
+    );
+    cr.show_text(title);
+
+    // Zonas de la planta
+    let (edificio, planta) = match (edificio, planta) {
+        (Some(edificio), Some(planta)) => (edificio, planta),
+        _ => {
+            let txt = "Seleccione una planta o una zona";
+            let te = cr.text_extents(txt);
+            cr.move_to((widget_width - te.width) / 2.0, widget_height * 0.5);
+            cr.show_text(txt);
+            cr.restore();
+            return;
+        }
+    };
+    let netos = |f: &[Flujos], cal: bool, factor: f32| -> Vec<f64> {
+        f.iter()
+            .map(|f| (if cal { f.calnet } else { f.refnet } * factor) as f64)
+            .collect()
+    };
+    let barras: Vec<BarraZona> = edificio
+        .plantas
+        .iter()
+        .filter(|p| p.nombre == planta)
+        .flat_map(|p| p.zonas.iter())
+        .filter_map(|z| edificio.zonas.get(z))
+        .map(|z| {
+            let factor =
+                unidades.factor(z.superficie * z.multiplicador as f32, edificio.superficie);
+            let c = &z.conceptos;
+            let conceptos = [
+                c.pext,
+                c.cub,
+                c.suelos,
+                c.pts,
+                c.huecos_solar,
+                c.huecos_trans,
+                c.fint,
+                c.vent,
+            ];
+            BarraZona {
+                nombre: z.nombre.clone(),
+                superficie: z.superficie as f64,
+                multiplicador: z.multiplicador,
+                cal: netos(&conceptos, true, factor),
+                refr: netos(&conceptos, false, factor),
+                cal_total: (c.total.calnet * factor) as f64,
+                ref_total: (c.total.refnet * factor) as f64,
+            }
+        })
+        .collect();
+    if barras.is_empty() {
+        let txt = format!("La planta {} no tiene zonas", planta);
+        let te = cr.text_extents(&txt);
+        cr.move_to((widget_width - te.width) / 2.0, widget_height * 0.5);
+        cr.show_text(&txt);
+        cr.restore();
+        return;
+    }
+
+    // Escalas
+    let (ymin, ymax) = barras
+        .iter()
+        .flat_map(|b| vec![extremos(&b.cal), extremos(&b.refr)])
+        .fold((0.0, 0.0), |(min, max): (f64, f64), (neg, pos)| {
+            (min.min(neg), max.max(pos))
+        });
+    let (ymin, ymax) = config.limits(Escala::Anual, ymin.min(-1.0) as f32, ymax.max(1.0) as f32);
+    let range = nice_ticks(ymin as f64, ymax as f64, 6);
+    let (ylow, yhigh) = (range[0], range[range.len() - 1]);
+    let decimals = tick_decimals(&range);
+    let yscale = linear_scale(ylow, yhigh, y1, y0);
+    let sup_total: f64 = barras
+        .iter()
+        .map(|b| b.superficie * b.multiplicador as f64)
+        .sum::<f64>()
+        .max(1.0);
+    let gap = 0.02 * width;
+    let wzonas = width - gap * (barras.len() + 1) as f64;
+
+    // Subtítulo, marco y eje Y
+    let subtitle = format!(
+        "{} - Flujos netos por conceptos en calefacción (izq.) y refrigeración (dcha.) [{}]",
+        planta,
+        unidades.anual()
+    );
+    draw_subtitle_and_box(cr, &subtitle, subtitle_block_height, x0, y0, width, height);
+    let ytitle = format!("Demanda [{}]", unidades.anual());
+    draw_ytitle(cr, &ytitle, margin * 0.75, (y0 + y1) / 2.0);
+    let labels: Vec<(f64, String)> = range
+        .iter()
+        .map(|v| (yscale(*v), format!("{:.*}", decimals, v)))
+        .collect();
+    ylabels(cr, labels.as_slice(), ticksize, x0, true);
+    cr.set_line_width(0.5);
+    set_color(cr, style.muted);
+    cr.move_to(x0, rounder(yscale(0.0)));
+    cr.line_to(x1, rounder(yscale(0.0)));
+    cr.stroke();
+
+    // Barras de las zonas, recortadas al marco si superan los límites de la escala
+    let mut x = x0 + gap;
+    for barra in &barras {
+        let wzona = wzonas * barra.superficie * barra.multiplicador as f64 / sup_total;
+        let alpha = if seleccion.is_empty() || seleccion.contains(&barra.nombre) {
+            1.0
+        } else {
+            0.35
+        };
+        let wbar = 0.45 * wzona;
+        cr.save();
+        cr.rectangle(x0, y0, width, height);
+        cr.clip();
+        for (i, (valores, total)) in [
+            (&barra.cal, barra.cal_total),
+            (&barra.refr, barra.ref_total),
+        ]
+        .iter()
+        .enumerate()
+        {
+            let xbar = x + i as f64 * (wzona - wbar);
+            let (mut neg, mut pos) = (0.0, 0.0);
+            for (v, color) in valores.iter().zip(style.concepts.iter()) {
+                let (base, tope) = if *v < 0.0 {
+                    neg += v;
+                    (neg - v, neg)
+                } else {
+                    pos += v;
+                    (pos - v, pos)
+                };
+                set_color_alpha(cr, *color, alpha);
+                cr.rectangle(xbar, yscale(base), wbar, yscale(tope) - yscale(base));
+                cr.fill();
+            }
+            // Demanda neta total de la zona
+            let color = if i == 0 {
+                style.heating[0]
+            } else {
+                style.cooling[0]
+            };
+            let ytotal = rounder(yscale(*total));
+            set_color_alpha(cr, color, alpha);
+            cr.set_line_width(3.0);
+            cr.move_to(xbar - 2.0, ytotal);
+            cr.rel_line_to(wbar + 4.0, 0.0);
+            cr.stroke();
+            cr.set_font_size(style.small_size);
+            style.select_font(cr, cairo::FontWeight::Bold);
+            let txt = format!("{:.1}", total);
+            let te = cr.text_extents(&txt);
+            if te.width < wbar + 4.0 {
+                cr.move_to(xbar + (wbar - te.width) / 2.0, ytotal - 4.0);
+                cr.show_text(&txt);
+            }
+        }
+        cr.restore();
+
+        // Rótulos de la zona: nombre y superficie
+        style.select_font(cr, cairo::FontWeight::Normal);
+        cr.set_font_size(style.small_size);
+        set_color_alpha(cr, style.label, alpha);
+        let detail = if barra.multiplicador > 1 {
+            format!("{:.1} m² x{}", barra.superficie, barra.multiplicador)
+        } else {
+            format!("{:.1} m²", barra.superficie)
+        };
+        for (j, txt) in [barra.nombre.as_str(), detail.as_str()].iter().enumerate() {
+            let te = cr.text_extents(txt);
+            if te.width <= wzona + gap {
+                cr.move_to(
+                    x + (wzona - te.width) / 2.0,
+                    y1 + (1.5 + 1.5 * j as f64) * te.height.max(style.small_size * 0.8),
+                );
+                cr.show_text(txt);
+            }
+        }
+        x += wzona + gap;
+    }
+
+    // Leyenda de conceptos
+    cr.set_font_size(style.mid_size);
+    style.select_font(cr, cairo::FontWeight::Normal);
+    let square = style.mid_size * 0.8;
+    let wlegend: f64 = CONCEPT_LABELS
+        .iter()
+        .map(|l| cr.text_extents(l).x_advance + square + 16.0)
+        .sum();
+    let mut xl = (widget_width - wlegend).max(0.0) / 2.0;
+    let yl = widget_height - 0.6 * margin;
+    for (label, color) in CONCEPT_LABELS.iter().zip(style.concepts.iter()) {
+        set_color(cr, *color);
+        cr.rectangle(xl, yl - square, square, square);
+        cr.fill();
+        set_color(cr, style.label);
+        cr.move_to(xl + square + 4.0, yl);
+        cr.show_text(label);
+        xl += cr.text_extents(label).x_advance + square + 16.0;
+    }
+
+    draw_watermark(cr, widget_width - widget_height * 0.05, htitle);
+
+    cr.restore();
+}
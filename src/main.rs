@@ -7,11 +7,15 @@ use gio::prelude::*; // Incluye macros como get_widget!(ui, gtktype, name)
 use glib::clone;
 use gtk::prelude::*;
 
+mod analysis;
 mod appstate;
+mod cli;
 mod config;
+mod export;
 mod graphs;
 // mod static_resource;
 mod parsers;
+mod report;
 mod utils;
 mod window;
 
@@ -27,6 +31,12 @@ pub(crate) const APP_NAME: &str = env!("CARGO_PKG_NAME");
 pub(crate) const APP_VERSION: &str = env!("CARGO_PKG_VERSION");
 
 fn main() {
+    // Órdenes de línea de comandos, sin interfaz gráfica
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(code) = cli::run(&args) {
+        std::process::exit(code);
+    }
+
     // Comprobación del directorio de ejecución
     utils::check_current_dir();
 
@@ -65,5 +75,5 @@ fn main() {
         window::build_ui(app, &state, &config);
     }));
 
-    app.run(&std::env::args().collect::<Vec<_>>());
+    app.run(&args);
 }
@@ -96,7 +96,7 @@ pub struct ZonaLider {
     pub t_max: Vec<f32>,
     /// Tmin: Temperatura de consigna baja [ºC]
     pub t_min: Vec<f32>,
-    /// Vventinf: Caudal másico de ventilación e infiltración [kg/s?]
+    /// Vventinf: Caudal másico de ventilación e infiltración [kg/s]
     pub v_ventinf: Vec<f32>,
 }
 
@@ -296,7 +296,7 @@ pub struct ZonaLiderFFI {
     /// Tmin: Temperatura de consigna baja [ºC]
     /// float Tmin[nHoras];
     pub t_min: [f32; NHORAS],
-    /// Vventinf: Caudal másico de ventilación e infiltración [kg/s?]
+    /// Vventinf: Caudal másico de ventilación e infiltración [kg/s]
     /// float Vventinf[nHoras];
     pub v_ventinf: [f32; NHORAS],
 }
@@ -0,0 +1,65 @@
+//! Lectura de archivos climáticos de HULC / CTE (.met)
+//!
+//! Tras dos líneas de cabecera (nombre del archivo y localización), contienen una línea por hora con:
+//! mes, día, hora, temperatura seca [ºC], temperatura efectiva del cielo [ºC], radiación directa y difusa
+//! sobre superficie horizontal [W/m²], humedad específica [kg/kg], humedad relativa [%], etc.
+
+use std::path::Path;
+
+use crate::utils::{read_latin1_file, Error};
+
+/// Número de horas en un año
+const NHORAS: usize = 8760;
+
+/// Temperaturas secas horarias del archivo climático [ºC]
+pub fn temperaturas_exteriores<S: AsRef<Path>>(path: S) -> Result<Vec<f32>, Error> {
+    parse_temperaturas(&read_latin1_file(path)?)
+}
+
+/// Temperaturas secas horarias a partir del contenido de un archivo climático [ºC]
+///
+/// Se consideran líneas de datos las que comienzan por tres enteros (mes, día, hora)
+pub fn parse_temperaturas(data: &str) -> Result<Vec<f32>, Error> {
+    let temperaturas: Vec<f32> = data
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() < 4 || fields[..3].iter().any(|f| f.parse::<u32>().is_err()) {
+                return None;
+            }
+            fields[3].parse::<f32>().ok()
+        })
+        .collect();
+    if temperaturas.len() != NHORAS {
+        return Err(format!(
+            "Número incorrecto de datos horarios en el archivo climático: {} (se esperaban {})",
+            temperaturas.len(),
+            NHORAS
+        )
+        .into());
+    }
+    Ok(temperaturas)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_met() {
+        let mut data = "zonaD3.met\n40.68 -3.7 589 0.0\n".to_string();
+        for i in 0..NHORAS {
+            data.push_str(&format!(
+                "{} {} {} {:.1} -9.9 0.0 0.0 0.0041 84 2.1 180\n",
+                1 + i / 730,
+                1 + (i / 24) % 28,
+                1 + i % 24,
+                (i % 24) as f32
+            ));
+        }
+        let temps = parse_temperaturas(&data).unwrap();
+        assert_eq!(temps.len(), NHORAS);
+        assert_eq!(&temps[..3], &[0.0, 1.0, 2.0]);
+        assert!(parse_temperaturas("zonaD3.met\n1 1 1 3.6\n").is_err());
+    }
+}
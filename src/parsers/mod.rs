@@ -1,3 +1,4 @@
 pub mod bin;
+pub mod met;
 pub mod res;
 pub mod types;
\ No newline at end of file
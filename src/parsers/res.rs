@@ -1,7 +1,7 @@
 //! Analizador de archivos de resultados de LIDER
 
 use super::types::Conceptos;
-pub use crate::parsers::types::{EdificioLIDER, PlantaLIDER, ZonaLIDER};
+pub use crate::parsers::types::{EdificioLIDER, PlantaLIDER, VersionRes, ZonaLIDER};
 use crate::utils::read_latin1_file;
 use crate::utils::Error;
 use std::str::Lines;
@@ -16,6 +16,7 @@ impl EdificioLIDER {
             ..Default::default()
         };
         let resdata = read_latin1_file(path)?;
+        edificio.version = detectar_version(&resdata);
 
         let mut lines = &mut resdata.lines();
         while let Some(line) = lines.next() {
@@ -49,6 +50,33 @@ impl EdificioLIDER {
     }
 }
 
+/// Detecta la versión del formato del archivo .res
+///
+/// Se usa la versión indicada en los comentarios del archivo (p.e. "# HULC 2020"), si la hay. En
+/// otro caso se distingue el formato de HULC 2018 y posteriores (que se identifica como HULC 2019)
+/// por las unidades en los encabezados o el concepto "Ventilación más Infiltración", y el resto
+/// se identifica como LIDER 2009, con el mismo formato que HULC 2013.
+pub fn detectar_version(resdata: &str) -> VersionRes {
+    let compacto = |texto: &str| texto.replace(' ', "").to_uppercase();
+    for comentario in resdata.lines().filter(|l| l.trim().starts_with('#')) {
+        let comentario = compacto(comentario);
+        if let Some(version) = VersionRes::TODAS
+            .iter()
+            .find(|v| comentario.contains(&compacto(&v.to_string())))
+        {
+            return *version;
+        }
+    }
+    let con_unidades = resdata.lines().any(|l| {
+        l.starts_with(VersionRes::Hulc2019.concepto_ventilacion()) || l.contains("(kWh/m2 del")
+    });
+    if con_unidades {
+        VersionRes::Hulc2019
+    } else {
+        VersionRes::Lider2009
+    }
+}
+
 /// Localiza conjunto de plantas y zonas
 fn find_plantas_y_zonas(lines: &mut Lines, edificio: &mut EdificioLIDER) -> Result<(), Error> {
     let numplantas: i32 = lines
@@ -106,7 +134,7 @@ fn find_plantas_y_zonas(lines: &mut Lines, edificio: &mut EdificioLIDER) -> Resu
             // Parsing de conceptos de demanda de la zona ----------------
             lines.find(|l| l.starts_with("Concepto, Cal_positivo"));
             // 9 grupos de demanda (Paredes Exteriores, Cubiertas, Suelos, ...)
-            zona.conceptos = Conceptos::from_vec(lines.take(9).collect())?;
+            zona.conceptos = Conceptos::from_vec(lines.take(9).collect(), edificio.version)?;
 
             // Parsing de componentes de demanda de la zona ------------
             lines.find(|l| l.starts_with("Numero de Componentes"));
@@ -147,9 +175,13 @@ fn find_demandas_generales_edificio(
     edificio: &mut EdificioLIDER,
 ) -> Result<(), Error> {
     // Cal, ref, mensual
-    lines.find(|l| {
-        l.starts_with("Calefacción, Refrigeración anual") || l.starts_with("Calefacción anual")
-    });
+    let encabezado = edificio.version.encabezado_demanda_anual();
+    lines.find(|l| l.starts_with(encabezado)).ok_or_else(|| {
+        format!(
+            "Formato incorrecto: no se encuentra \"{}\" ({})",
+            encabezado, edificio.version
+        )
+    })?;
     match lines
         .next()
         .ok_or("Formato incorrecto: datos generales")?
@@ -309,6 +341,7 @@ fn find_ref_mensual_zonas(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::utils::testfile;
 
     #[test]
     fn read_res_testfile() {
@@ -329,4 +362,131 @@ mod tests {
         assert_eq!(res.plantas[0].zonas.len(), 1);
         assert_eq!(res.plantas[1].zonas.len(), 3);
     }
+
+    #[test]
+    fn versiones_res() {
+        // Versión, demandas del edificio y ventilación de la primera zona de cada archivo
+        let fixtures = [
+            (
+                "test.res",
+                VersionRes::Lider2009,
+                [-11.1155, 19.5351, -20.7545],
+            ),
+            (
+                "test2013.res",
+                VersionRes::Hulc2013,
+                [-14.1740, 3.3568, -19.5266],
+            ),
+            (
+                "test2018.res",
+                VersionRes::Hulc2018,
+                [-14.1740, 3.3568, -19.5266],
+            ),
+            (
+                "test2019.res",
+                VersionRes::Hulc2019,
+                [-21.6038, 8.6882, -19.5266],
+            ),
+            (
+                "test2020.res",
+                VersionRes::Hulc2020,
+                [-14.1740, 3.3568, -19.5266],
+            ),
+        ];
+        for (archivo, version, [cal, refr, vent]) in fixtures.iter() {
+            let res = EdificioLIDER::from_file(testfile(archivo)).unwrap();
+            assert_eq!(res.version, *version, "{}", archivo);
+            assert!((res.calefaccion - cal).abs() < 1e-3, "{}", archivo);
+            assert!((res.refrigeracion - refr).abs() < 1e-3, "{}", archivo);
+            let zona = &res.zonas[&res.plantas[0].zonas[0]];
+            let vent_calneg = zona.conceptos.vent.calneg;
+            assert!((vent_calneg - vent).abs() < 1e-3, "{}", archivo);
+        }
+        assert_eq!(
+            detectar_version("# Generado con HULC2020\r\n"),
+            VersionRes::Hulc2020
+        );
+
+        // Los conceptos y encabezados de otras versiones no se aceptan
+        let linea = "Infiltración, 0.1, -20.7, -20.6, 3.3, -14.3, -11.0";
+        let conceptos = |linea| {
+            let mut lineas = vec!["Paredes Exteriores, 0, 0, 0, 0, 0, 0"; 8];
+            lineas.push(linea);
+            lineas
+        };
+        assert!(Conceptos::from_vec(conceptos(linea), VersionRes::Lider2009).is_ok());
+        assert!(Conceptos::from_vec(conceptos(linea), VersionRes::Hulc2019).is_err());
+        let mut lineas = "Calefacción, Refrigeración anual\n-1.0, 1.0\n".lines();
+        let mut edificio = EdificioLIDER {
+            version: VersionRes::Hulc2018,
+            ..Default::default()
+        };
+        assert!(find_demandas_generales_edificio(&mut lineas, &mut edificio).is_err());
+    }
+
+    #[test]
+    fn tipos_elementos() {
+        use crate::parsers::types::{Flujos, TipoElemento};
+
+        assert_eq!(
+            TipoElemento::from_nombre("P02_E01_PE001_V3"),
+            (TipoElemento::Hueco, Some("P02_E01_PE001".to_string()))
+        );
+        assert_eq!(
+            TipoElemento::from_nombre("P03_E02C001"),
+            (TipoElemento::Cubierta, None)
+        );
+        assert_eq!(
+            TipoElemento::from_nombre("P02_E01_MED002"),
+            (TipoElemento::Medianera, None)
+        );
+        assert_eq!(
+            TipoElemento::from_nombre("P02_E01_FI001").0,
+            TipoElemento::ForjadoInterior
+        );
+
+        let res = EdificioLIDER::from_file(testfile("test.res")).unwrap();
+        let zona = &res.zonas["P02_E06"];
+        assert_eq!(
+            zona.elementos
+                .iter()
+                .filter(|e| e.tipo == TipoElemento::ParedExterior)
+                .count(),
+            3
+        );
+        assert_eq!(zona.huecos("P02_E06_PE002").count(), 1);
+        // Los huecos se agrupan con su muro
+        let muros = zona.flujos_grupo(TipoElemento::ParedExterior);
+        let conjunto = zona.flujos_conjunto("P02_E06_PE002");
+        assert!(
+            (muros.calnet
+                - zona.conceptos.pext.calnet
+                - zona.conceptos.huecos_solar.calnet
+                - zona.conceptos.huecos_trans.calnet)
+                .abs()
+                < 0.01
+        );
+        // El conjunto suma los flujos del muro y de sus huecos
+        let muro = zona
+            .elementos
+            .iter()
+            .find(|e| e.nombre == "P02_E06_PE002")
+            .unwrap();
+        let huecos: Vec<_> = zona
+            .elementos
+            .iter()
+            .filter(|e| TipoElemento::from_nombre(&e.nombre).1.as_deref() == Some("P02_E06_PE002"))
+            .collect();
+        assert!(!huecos.is_empty());
+        let esperado = huecos.iter().fold(muro.flujos, |acc, e| acc + e.flujos);
+        let valores = |f: Flujos| [f.calpos, f.calneg, f.calnet, f.refpos, f.refneg, f.refnet];
+        for (calculado, esperado) in valores(conjunto).iter().zip(valores(esperado).iter()) {
+            assert!((calculado - esperado).abs() < 1e-4);
+        }
+        assert!(valores(conjunto) != valores(muro.flujos));
+        // Todos los huecos de la zona tienen su muro en la zona
+        assert!(valores(zona.flujos_grupo(TipoElemento::Hueco))
+            .iter()
+            .all(|v| v.abs() < 1e-6));
+    }
 }
@@ -4,6 +4,7 @@
 //! - ZonaLIDER
 //! - Elemento
 
+use crate::graphs::CONCEPT_LABELS;
 use crate::utils::Error;
 use std::{
     collections::HashMap,
@@ -16,6 +17,8 @@ pub const TYPE_EDIFICIO: u8 = 0;
 pub const TYPE_PLANTA: u8 = 1;
 pub const TYPE_ZONA: u8 = 2;
 pub const TYPE_COMPONENTE: u8 = 3;
+pub const TYPE_GRUPO: u8 = 4;
+pub const TYPE_CONJUNTO: u8 = 5;
 
 /// Tipo de objeto activo
 #[allow(unused)]
@@ -25,6 +28,10 @@ pub enum TipoObjeto {
     Planta,
     Zona,
     Elemento,
+    /// Elementos de un mismo tipo de una zona
+    Grupo,
+    /// Pared con sus huecos
+    Conjunto,
     None,
 }
 
@@ -41,6 +48,8 @@ impl From<u8> for TipoObjeto {
             TYPE_PLANTA => TipoObjeto::Planta,
             TYPE_ZONA => TipoObjeto::Zona,
             TYPE_COMPONENTE => TipoObjeto::Elemento,
+            TYPE_GRUPO => TipoObjeto::Grupo,
+            TYPE_CONJUNTO => TipoObjeto::Conjunto,
             _ => TipoObjeto::None,
         }
     }
@@ -53,6 +62,8 @@ impl From<TipoObjeto> for u8 {
             TipoObjeto::Planta => 1,
             TipoObjeto::Zona => 2,
             TipoObjeto::Elemento => 3,
+            TipoObjeto::Grupo => 4,
+            TipoObjeto::Conjunto => 5,
             _ => 255,
         }
     }
@@ -65,17 +76,100 @@ impl Display for TipoObjeto {
             TipoObjeto::Planta => "PLANTA",
             TipoObjeto::Zona => "ZONA",
             TipoObjeto::Elemento => "COMPONENTE",
+            TipoObjeto::Grupo => "GRUPO DE COMPONENTES",
+            TipoObjeto::Conjunto => "CONJUNTO",
             _ => "",
         };
         write!(f, "{}", ss)
     }
 }
 
+/// Versión del formato de los archivos de resultados .res
+///
+/// LIDER 2009 y HULC 2013 usan el mismo formato, con el concepto "Infiltración" y encabezados sin
+/// unidades. Desde HULC 2018 el concepto es "Ventilación más Infiltración" y los encabezados
+/// indican las unidades (p.e. "Calefacción anual (kWh/m2 del edificio)").
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VersionRes {
+    /// LIDER 2009 (CTE 2006)
+    #[default]
+    Lider2009,
+    /// HULC 2013 (DB-HE 2013)
+    Hulc2013,
+    /// HULC 2018 (DB-HE 2019)
+    Hulc2018,
+    /// HULC 2019 (DB-HE 2019)
+    Hulc2019,
+    /// HULC 2020 (DB-HE 2019)
+    Hulc2020,
+}
+
+impl std::fmt::Display for VersionRes {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let nombre = match self {
+            Self::Lider2009 => "LIDER 2009",
+            Self::Hulc2013 => "HULC 2013",
+            Self::Hulc2018 => "HULC 2018",
+            Self::Hulc2019 => "HULC 2019",
+            Self::Hulc2020 => "HULC 2020",
+        };
+        write!(f, "{}", nombre)
+    }
+}
+
+impl VersionRes {
+    /// Todas las versiones, de la más antigua a la más reciente
+    pub const TODAS: [Self; 5] = [
+        Self::Lider2009,
+        Self::Hulc2013,
+        Self::Hulc2018,
+        Self::Hulc2019,
+        Self::Hulc2020,
+    ];
+
+    /// Formato con unidades en los encabezados (HULC 2018 y posteriores)
+    pub fn con_unidades(self) -> bool {
+        !matches!(self, Self::Lider2009 | Self::Hulc2013)
+    }
+
+    /// Nombre del concepto de ventilación e infiltraciones en los resultados de las zonas
+    pub fn concepto_ventilacion(self) -> &'static str {
+        if self.con_unidades() {
+            "Ventilación más Infiltración"
+        } else {
+            "Infiltración"
+        }
+    }
+
+    /// Comienzo del encabezado de las demandas anuales del edificio
+    pub fn encabezado_demanda_anual(self) -> &'static str {
+        if self.con_unidades() {
+            "Calefacción anual"
+        } else {
+            "Calefacción, Refrigeración anual"
+        }
+    }
+
+    /// Versión del DB-HE con la que se usa el programa
+    ///
+    /// No está definida para LIDER 2009, ya que los archivos de HULC 2013 sin indicación de la
+    /// versión tienen el mismo formato y se detectan como de LIDER 2009.
+    pub fn dbhe(self) -> Option<&'static str> {
+        match self {
+            Self::Lider2009 => None,
+            Self::Hulc2013 => Some("DB-HE 2013"),
+            _ => Some("DB-HE 2019"),
+        }
+    }
+}
+
 #[derive(Clone, Debug, Default)]
 /// Edificio en LIDER
 pub struct EdificioLIDER {
     /// Nombre del edificio
     pub nombre: String,
+    /// Versión del formato del archivo .res
+    pub version: VersionRes,
     /// Superficie del edificio [m²]
     pub superficie: f32,
     /// Demanda anual de calefacción del edificio [kWh/m²/año]
@@ -324,6 +418,75 @@ impl ZonaLIDER {
             elementos: Vec::new(),
         }
     }
+
+    /// Huecos de la zona alojados en el elemento indicado
+    pub fn huecos(&self, padre: &str) -> impl Iterator<Item = &Elemento> + '_ {
+        let padre = padre.to_string();
+        self.elementos
+            .iter()
+            .filter(move |e| e.padre.as_deref() == Some(padre.as_str()))
+    }
+
+    /// Tipos de elementos para la agrupación de los elementos de la zona
+    ///
+    /// Los huecos se agrupan con el elemento en el que se alojan cuando este existe en la zona,
+    /// y solamente se agrupan como huecos los que no tienen elemento padre en la zona
+    pub fn grupo(&self, elemento: &Elemento) -> TipoElemento {
+        match &elemento.padre {
+            Some(padre) => self
+                .elementos
+                .iter()
+                .find(|e| &e.nombre == padre)
+                .map(|e| e.tipo)
+                .unwrap_or(elemento.tipo),
+            None => elemento.tipo,
+        }
+    }
+
+    /// Flujos de calor de los elementos de un grupo de la zona [kWh/m²·año]
+    pub fn flujos_grupo(&self, tipo: TipoElemento) -> Flujos {
+        self.elementos
+            .iter()
+            .filter(|e| self.grupo(e) == tipo)
+            .fold(Flujos::default(), |acc, e| acc + e.flujos)
+    }
+
+    /// Flujos de calor de un elemento junto con los de sus huecos [kWh/m²·año]
+    pub fn flujos_conjunto(&self, nombre: &str) -> Flujos {
+        self.elementos
+            .iter()
+            .filter(|e| e.nombre == nombre || e.padre.as_deref() == Some(nombre))
+            .fold(Flujos::default(), |acc, e| acc + e.flujos)
+    }
+
+    /// Flujos de calor de un hueco de la zona, separados en solar y transmisión [kWh/m²·año]
+    ///
+    /// HULC no separa los flujos de cada hueco, por lo que sus ganancias y pérdidas se reparten
+    /// en la proporción de los conceptos "Solar ventanas" y "Transmisión ventanas" de la zona.
+    pub fn flujos_hueco(&self, hueco: &Elemento) -> (Flujos, Flujos) {
+        let (solar, trans) = (&self.conceptos.huecos_solar, &self.conceptos.huecos_trans);
+        let parte = |valor: f32, solar: f32, trans: f32| {
+            if (solar + trans).abs() > f32::EPSILON {
+                valor * solar / (solar + trans)
+            } else {
+                0.0
+            }
+        };
+        let f = &hueco.flujos;
+        let calpos = parte(f.calpos, solar.calpos, trans.calpos);
+        let calneg = parte(f.calneg, solar.calneg, trans.calneg);
+        let refpos = parte(f.refpos, solar.refpos, trans.refpos);
+        let refneg = parte(f.refneg, solar.refneg, trans.refneg);
+        let solar = Flujos {
+            calpos,
+            calneg,
+            calnet: calpos + calneg,
+            refpos,
+            refneg,
+            refnet: refpos + refneg,
+        };
+        (solar, hueco.flujos + solar * -1.0)
+    }
 }
 
 // ----------------------------------------------------------------------------------------
@@ -357,6 +520,22 @@ impl Default for FlujosVec {
     }
 }
 
+impl Mul<f32> for FlujosVec {
+    type Output = FlujosVec;
+
+    fn mul(self, other: f32) -> Self::Output {
+        let scale = |v: Vec<f32>| v.into_iter().map(|x| x * other).collect();
+        Self::Output {
+            calpos: scale(self.calpos),
+            calneg: scale(self.calneg),
+            calnet: scale(self.calnet),
+            refpos: scale(self.refpos),
+            refneg: scale(self.refneg),
+            refnet: scale(self.refnet),
+        }
+    }
+}
+
 /// Flujos a través de un elemento
 #[derive(Debug, Copy, Clone, PartialEq, Default)]
 pub struct Flujos {
@@ -488,7 +667,8 @@ pub struct Conceptos {
 impl Conceptos {
     /// Parsea concepto desde lista de 6 líneas de componentes:
     ///     concepto, calpos, calneg, calnet, refpos, refneg, refnet
-    pub fn from_vec(vec: Vec<&str>) -> Result<Self, Error> {
+    /// El nombre del concepto de ventilación depende de la versión del archivo .res
+    pub fn from_vec(vec: Vec<&str>, version: VersionRes) -> Result<Self, Error> {
         if vec.len() != 9 {
             return Err(format!("Lista de conceptos de tamaño inesperado {:?}", vec).into());
         }
@@ -517,14 +697,35 @@ impl Conceptos {
                 "Solar Ventanas" => res.huecos_solar = flujos,
                 "Transmisión Ventanas" => res.huecos_trans = flujos,
                 "Fuentes Internas" => res.fint = flujos,
-                "Infiltración" | "Ventilación más Infiltración" => res.vent = flujos,
+                _ if name == version.concepto_ventilacion() => res.vent = flujos,
                 "TOTAL" => res.total = flujos,
-                _ => return Err(format!("Error al procesar concepto {}", name).into()),
+                _ => {
+                    return Err(format!(
+                        "Error al procesar concepto {} (formato {})",
+                        name, version
+                    )
+                    .into())
+                }
             }
         }
         Ok(res)
     }
 
+    /// Lista de pares (nombre del concepto, flujos), en el orden de HULC y con el total al final
+    pub fn to_named_list(self) -> [(&'static str, Flujos); 9] {
+        [
+            (CONCEPT_LABELS[0], self.pext),
+            (CONCEPT_LABELS[1], self.cub),
+            (CONCEPT_LABELS[2], self.suelos),
+            (CONCEPT_LABELS[3], self.pts),
+            (CONCEPT_LABELS[4], self.huecos_solar),
+            (CONCEPT_LABELS[5], self.huecos_trans),
+            (CONCEPT_LABELS[6], self.fint),
+            (CONCEPT_LABELS[7], self.vent),
+            ("TOTAL", self.total),
+        ]
+    }
+
     /// Valor mínimo y máximo de entre todos los flujos
     /// Si se indica only_net_fluxes, solo considera los flujos netos (calnet, refnet)
     /// De lo contrario usa todos los flujos (calpos, calneg, calnet, refpos, refneg, refnet)
@@ -738,7 +939,108 @@ impl Mul<Conceptos> for f32 {
     }
 }
 
-#[derive(Debug, Clone, Default)]
+/// Tipo de elemento constructivo
+///
+/// Se deduce del código que sigue al nombre de la zona en el nombre del elemento de HULC
+/// (p.e. PE en P02_E01_PE001). Los huecos añaden el sufijo _V al nombre del elemento en el
+/// que se alojan (p.e. P02_E01_PE001_V3).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TipoElemento {
+    /// Pared exterior (PE)
+    ParedExterior,
+    /// Hueco (_V)
+    Hueco,
+    /// Cubierta (C)
+    Cubierta,
+    /// Forjado en contacto con el aire exterior (FE)
+    ForjadoExterior,
+    /// Forjado interior (FI)
+    ForjadoInterior,
+    /// Medianera (MED)
+    Medianera,
+    /// Pared en contacto con el terreno (PCT)
+    ParedTerreno,
+    /// Suelo en contacto con el terreno (FTER, TER)
+    SueloTerreno,
+    /// Otros elementos
+    Otro,
+}
+
+impl TipoElemento {
+    /// Tipos de elementos, en el orden de presentación
+    pub const TODOS: [TipoElemento; 9] = [
+        Self::ParedExterior,
+        Self::Hueco,
+        Self::Cubierta,
+        Self::ForjadoExterior,
+        Self::ForjadoInterior,
+        Self::Medianera,
+        Self::ParedTerreno,
+        Self::SueloTerreno,
+        Self::Otro,
+    ];
+
+    /// Nombre del grupo de elementos del tipo
+    pub fn nombre(self) -> &'static str {
+        match self {
+            Self::ParedExterior => "Paredes exteriores",
+            Self::Hueco => "Huecos",
+            Self::Cubierta => "Cubiertas",
+            Self::ForjadoExterior => "Forjados exteriores",
+            Self::ForjadoInterior => "Forjados interiores",
+            Self::Medianera => "Medianeras",
+            Self::ParedTerreno => "Paredes en contacto con el terreno",
+            Self::SueloTerreno => "Suelos en contacto con el terreno",
+            Self::Otro => "Otros elementos",
+        }
+    }
+
+    /// Tipo de elemento a partir del nombre de su grupo
+    pub fn from_grupo(nombre: &str) -> Option<Self> {
+        Self::TODOS.iter().copied().find(|t| t.nombre() == nombre)
+    }
+
+    /// Tipo de elemento a partir de su código (p.e. PE)
+    pub fn from_codigo(codigo: &str) -> Self {
+        match codigo {
+            "PE" => Self::ParedExterior,
+            "V" => Self::Hueco,
+            "C" => Self::Cubierta,
+            "FE" => Self::ForjadoExterior,
+            "FI" => Self::ForjadoInterior,
+            "MED" => Self::Medianera,
+            "PCT" => Self::ParedTerreno,
+            "FTER" | "TER" => Self::SueloTerreno,
+            _ => Self::Otro,
+        }
+    }
+
+    /// Tipo de elemento y nombre del elemento padre (en huecos) a partir del nombre del elemento
+    pub fn from_nombre(nombre: &str) -> (Self, Option<String>) {
+        let is_digit = |c: char| c.is_ascii_digit();
+        // Huecos: nombre del elemento padre + _V + número opcional
+        if let Some(pos) = nombre.rfind("_V") {
+            if pos > 0 && nombre[pos + 2..].chars().all(is_digit) {
+                return (Self::Hueco, Some(nombre[..pos].to_string()));
+            }
+        }
+        let ultimo = nombre.rsplit('_').next().unwrap_or(nombre);
+        // Algunos elementos no separan su código del de la zona (p.e. P03_E02C001)
+        let ultimo = match ultimo.strip_prefix('E') {
+            Some(resto) if resto.starts_with(is_digit) => resto.trim_start_matches(is_digit),
+            _ => ultimo,
+        };
+        (Self::from_codigo(ultimo.trim_end_matches(is_digit)), None)
+    }
+}
+
+impl Display for TipoElemento {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.nombre())
+    }
+}
+
+#[derive(Debug, Clone)]
 /// Flujos de calor de elementos de LIDER
 /// Puede usarse para definir el comportamiento de elementos constructivos o grupos de demanda
 pub struct Elemento {
@@ -752,6 +1054,10 @@ pub struct Elemento {
     /// - negativo (pérdidas) de energía en temporada de refrigeración [kWh/año]
     /// - neto de energía en temporada de refrigeración [kWh/año] (ganancias - pérdidas)
     pub flujos: Flujos,
+    /// Tipo de elemento constructivo, según su nombre
+    pub tipo: TipoElemento,
+    /// Nombre del elemento en el que se aloja (p.e. el muro de un hueco)
+    pub padre: Option<String>,
 }
 
 impl std::str::FromStr for Elemento {
@@ -762,7 +1068,15 @@ impl std::str::FromStr for Elemento {
         let nombre = data.next().map(|s| s.trim_matches('\"').to_string());
         let flujos = data.next().map(|v| v.parse::<Flujos>());
         match (nombre, flujos) {
-            (Some(nombre), Some(Ok(flujos))) => Ok(Self { nombre, flujos }),
+            (Some(nombre), Some(Ok(flujos))) => {
+                let (tipo, padre) = TipoElemento::from_nombre(&nombre);
+                Ok(Self {
+                    nombre,
+                    flujos,
+                    tipo,
+                    padre,
+                })
+            }
             _ => return Err(format!("Formato de elemento constructivo erróneo: {}", s).into()),
         }
     }
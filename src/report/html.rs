@@ -0,0 +1,252 @@
+//! Informe de resultados en formato HTML
+//!
+//! Genera un único archivo HTML autocontenido, sin recursos externos, que se puede enviar y abrir
+//! en cualquier navegador: las gráficas se incrustan como SVG, las tablas se ordenan pulsando en
+//! sus encabezados y las secciones de plantas y zonas se pueden plegar.
+
+use std::fmt::Write;
+use std::path::Path;
+
+use super::{con_estilo_informe, tabla_elementos, Documento, Tabla};
+use crate::appstate::AppState;
+use crate::config::Config;
+use crate::utils::Error;
+use crate::APP_VERSION;
+
+/// Tamaño (ancho, alto) de las gráficas [px]
+const SIZE_GRAFICA: (f64, f64) = (900.0, 500.0);
+
+/// Hoja de estilos del informe
+const ESTILOS: &str = r#"
+body { font-family: Arial, Helvetica, sans-serif; color: #222; margin: 2em auto; max-width: 1000px; }
+h1 { margin-bottom: 0.2em; }
+.ruta { color: #666; font-size: 0.9em; }
+details { margin: 1em 0; border-left: 3px solid #ddd; padding-left: 1em; }
+summary { cursor: pointer; }
+summary h2, summary h3, summary h4 { display: inline; }
+table { border-collapse: collapse; margin: 1em 0; font-size: 0.9em; }
+caption { text-align: left; font-weight: bold; padding: 0.3em 0; }
+th, td { border-bottom: 1px solid #ddd; padding: 0.2em 0.6em; }
+th { background: #eee; cursor: pointer; text-align: left; }
+th[data-orden="asc"]::after { content: " \25B2"; }
+th[data-orden="desc"]::after { content: " \25BC"; }
+td.num { text-align: right; }
+tfoot td { font-weight: bold; }
+svg { display: block; max-width: 100%; height: auto; margin: 1em 0; }
+"#;
+
+/// Ordenación de las tablas al pulsar en sus encabezados (numérica o alfabética)
+const SCRIPT: &str = r#"
+document.querySelectorAll("table.ordenable th").forEach(function (th) {
+  th.addEventListener("click", function () {
+    var tbody = th.closest("table").tBodies[0];
+    var col = Array.prototype.indexOf.call(th.parentNode.children, th);
+    var asc = th.getAttribute("data-orden") !== "asc";
+    th.parentNode.querySelectorAll("th").forEach(function (h) { h.removeAttribute("data-orden"); });
+    th.setAttribute("data-orden", asc ? "asc" : "desc");
+    var filas = Array.prototype.slice.call(tbody.rows);
+    filas.sort(function (a, b) {
+      var x = a.cells[col].textContent, y = b.cells[col].textContent;
+      var nx = parseFloat(x), ny = parseFloat(y);
+      var c = (!isNaN(nx) && !isNaN(ny)) ? nx - ny : x.localeCompare(y, "es");
+      return asc ? c : -c;
+    });
+    filas.forEach(function (fila) { tbody.appendChild(fila); });
+  });
+});
+"#;
+
+/// Genera el informe HTML del modelo activo en la ruta indicada
+pub fn export_html<P: AsRef<Path>>(
+    st: &AppState,
+    config: &Config,
+    incluir_zonas: bool,
+    path: P,
+) -> Result<(), Error> {
+    let documento = Documento::new(st, incluir_zonas)?;
+    let html = con_estilo_informe(config, || to_html(&documento, st, config))?;
+    std::fs::write(path, html)?;
+    Ok(())
+}
+
+/// Documento HTML del informe
+///
+/// Cada sección se muestra en un bloque plegable, con las zonas dentro de su planta. Las zonas
+/// aparecen plegadas inicialmente.
+fn to_html(documento: &Documento, st: &AppState, config: &Config) -> Result<String, Error> {
+    let mut out = String::new();
+    writeln!(
+        out,
+        "<!DOCTYPE html>\n<html lang=\"es\">\n<head>\n<meta charset=\"utf-8\">\n<title>Informe de resultados - {}</title>\n<style>{}</style>\n</head>\n<body>",
+        escape(&documento.proyecto),
+        ESTILOS
+    )?;
+    writeln!(
+        out,
+        "<h1>Informe de resultados: {}</h1>\n<p class=\"ruta\">{}<br>Generado el {} con ViSOL {}</p>",
+        escape(&documento.proyecto),
+        escape(&documento.ruta.display().to_string()),
+        documento.fecha,
+        APP_VERSION
+    )?;
+
+    let mut st = st.clone();
+    let mut abiertas: Vec<usize> = Vec::new();
+    let mut num_grafica = 0;
+    for seccion in &documento.secciones {
+        while abiertas.last().map(|n| *n >= seccion.nivel) == Some(true) {
+            abiertas.pop();
+            writeln!(out, "</details>")?;
+        }
+        abiertas.push(seccion.nivel);
+        let cabecera = format!("h{}", seccion.nivel + 2);
+        writeln!(
+            out,
+            "<details{}>\n<summary><{h}>{}</{h}></summary>",
+            if seccion.nivel < 2 { " open" } else { "" },
+            escape(&seccion.titulo),
+            h = cabecera
+        )?;
+
+        seccion.select(&mut st);
+        for tabla in &seccion.tablas {
+            write_tabla(&mut out, tabla)?;
+        }
+        if seccion.nivel == 0 {
+            let elementos = tabla_elementos(&st);
+            writeln!(
+                out,
+                "<details>\n<summary>Elementos constructivos ({})</summary>",
+                elementos.filas.len()
+            )?;
+            write_tabla(&mut out, &elementos)?;
+            writeln!(out, "</details>")?;
+        }
+        for grafica in &seccion.graficas {
+            num_grafica += 1;
+            let svg = grafica.to_svg(SIZE_GRAFICA, &st, config)?;
+            writeln!(out, "{}", svg_incrustado(&svg, num_grafica))?;
+        }
+    }
+    for _ in abiertas {
+        writeln!(out, "</details>")?;
+    }
+
+    writeln!(out, "<script>{}</script>\n</body>\n</html>", SCRIPT)?;
+    Ok(out)
+}
+
+/// Tabla ordenable, con los totales en el pie para que no se ordenen con los datos
+fn write_tabla(out: &mut String, tabla: &Tabla) -> Result<(), Error> {
+    let celdas = |fila: &[String], etiqueta: &str| -> String {
+        fila.iter()
+            .map(|celda| {
+                let clase = if etiqueta == "td" && celda.parse::<f64>().is_ok() {
+                    " class=\"num\""
+                } else {
+                    ""
+                };
+                format!("<{e}{}>{}</{e}>", clase, escape(celda), e = etiqueta)
+            })
+            .collect()
+    };
+    writeln!(
+        out,
+        "<table class=\"ordenable\">\n<caption>{}</caption>\n<thead><tr>{}</tr></thead>\n<tbody>",
+        escape(&tabla.titulo),
+        celdas(&tabla.cabecera, "th")
+    )?;
+    for fila in &tabla.filas {
+        writeln!(out, "<tr>{}</tr>", celdas(fila, "td"))?;
+    }
+    writeln!(out, "</tbody>")?;
+    if !tabla.totales.is_empty() {
+        writeln!(out, "<tfoot>")?;
+        for fila in &tabla.totales {
+            writeln!(out, "<tr>{}</tr>", celdas(fila, "td"))?;
+        }
+        writeln!(out, "</tfoot>")?;
+    }
+    writeln!(out, "</table>")?;
+    Ok(())
+}
+
+/// Documento SVG preparado para incluirlo en línea en el HTML
+///
+/// Se elimina la declaración XML y se añade un prefijo propio a los identificadores (glifos,
+/// recortes...) para que no colisionen entre las distintas gráficas del documento.
+fn svg_incrustado(svg: &str, num: usize) -> String {
+    let prefijo = format!("g{}-", num);
+    let inicio = svg.find("<svg").unwrap_or(0);
+    svg[inicio..]
+        .replace("id=\"", &format!("id=\"{}", prefijo))
+        .replace("href=\"#", &format!("href=\"#{}", prefijo))
+        .replace("url(#", &format!("url(#{}", prefijo))
+}
+
+/// Escapa los caracteres especiales de HTML
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_html() {
+        assert_eq!(
+            escape(r#"<a href="x">P&B</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;P&amp;B&lt;/a&gt;"
+        );
+        assert_eq!(escape("Zona 1"), "Zona 1");
+    }
+
+    #[test]
+    fn svg_incrustado_prefijos() {
+        let svg = r##"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg"><defs><g id="glyph0-1"/><clipPath id="clip1"/></defs>
+<use xlink:href="#glyph0-1"/><g clip-path="url(#clip1)"/></svg>"##;
+        let out = svg_incrustado(svg, 3);
+        assert!(out.starts_with("<svg"));
+        assert!(out.contains(r#"id="g3-glyph0-1""#));
+        assert!(out.contains(r#"id="g3-clip1""#));
+        assert!(out.contains(r##"xlink:href="#g3-glyph0-1""##));
+        assert!(out.contains("url(#g3-clip1)"));
+        assert!(!out.contains(r#"id="glyph"#));
+    }
+
+    #[test]
+    fn tabla_html() {
+        let tabla = Tabla {
+            titulo: "Zonas <planta>".to_string(),
+            cabecera: vec!["Zona".to_string(), "Superficie [m²]".to_string()],
+            filas: vec![vec!["P01_E01".to_string(), "25.50".to_string()]],
+            totales: vec![vec!["Total".to_string(), "25.50".to_string()]],
+        };
+        let mut out = String::new();
+        write_tabla(&mut out, &tabla).unwrap();
+        assert!(
+            out.starts_with("<table class=\"ordenable\">\n<caption>Zonas &lt;planta&gt;</caption>")
+        );
+        assert!(out.contains("<thead><tr><th>Zona</th><th>Superficie [m²]</th></tr></thead>"));
+        assert!(out
+            .contains("<tbody>\n<tr><td>P01_E01</td><td class=\"num\">25.50</td></tr>\n</tbody>"));
+        assert!(
+            out.contains("<tfoot>\n<tr><td>Total</td><td class=\"num\">25.50</td></tr>\n</tfoot>")
+        );
+        assert!(out.ends_with("</table>\n"));
+
+        // Sin totales no hay pie de tabla
+        let tabla = Tabla {
+            totales: Vec::new(),
+            ..tabla
+        };
+        let mut out = String::new();
+        write_tabla(&mut out, &tabla).unwrap();
+        assert!(!out.contains("<tfoot>"));
+    }
+}
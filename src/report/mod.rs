@@ -0,0 +1,423 @@
+//! Informes de resultados del edificio
+//!
+//! El contenido del informe (secciones, tablas y gráficas) es independiente del formato de salida
+//! (PDF o HTML). Cada sección corresponde a un objeto (edificio, planta o zona) y sus gráficas se
+//! dibujan con el estado de la aplicación seleccionando ese objeto.
+
+use std::path::{Path, PathBuf};
+
+use chrono::prelude::*;
+
+use crate::analysis::confort::{HorasFueraConsigna, TOLERANCIA_CONSIGNA};
+use crate::appstate::{AppState, TipoObjeto};
+use crate::config::Config;
+use crate::export::tablas::{columnas_flujos, valores};
+use crate::graphs::grafica::Grafica;
+use crate::graphs::piechart::PieMode;
+use crate::graphs::style::{chart_style, set_chart_style, ChartStyle};
+use crate::utils::Error;
+
+pub mod html;
+pub mod pdf;
+
+/// Meses del año
+const MESES: [&str; 12] = [
+    "Enero",
+    "Febrero",
+    "Marzo",
+    "Abril",
+    "Mayo",
+    "Junio",
+    "Julio",
+    "Agosto",
+    "Septiembre",
+    "Octubre",
+    "Noviembre",
+    "Diciembre",
+];
+
+/// Tabla de datos del informe
+#[derive(Debug, Clone, Default)]
+pub struct Tabla {
+    /// Título de la tabla
+    pub titulo: String,
+    /// Encabezados de las columnas
+    pub cabecera: Vec<String>,
+    /// Filas de datos, ya formateados
+    pub filas: Vec<Vec<String>>,
+    /// Filas de totales, tras las filas de datos
+    pub totales: Vec<Vec<String>>,
+}
+
+/// Sección del informe, correspondiente a un objeto del edificio
+#[derive(Debug, Clone)]
+pub struct Seccion {
+    /// Título de la sección
+    pub titulo: String,
+    /// Nivel de la sección (0: edificio, 1: planta, 2: zona)
+    pub nivel: usize,
+    /// Tipo del objeto de la sección
+    pub tipo: TipoObjeto,
+    /// Nombre del objeto de la sección
+    pub nombre: String,
+    /// Tablas de la sección
+    pub tablas: Vec<Tabla>,
+    /// Gráficas de la sección
+    pub graficas: Vec<Grafica>,
+}
+
+impl Seccion {
+    /// Selecciona el objeto de la sección en el estado de la aplicación
+    pub fn select(&self, st: &mut AppState) {
+        st.curr_obj_type = self.tipo;
+        st.curr_name = self.nombre.clone();
+        st.curr_zone = if self.tipo == TipoObjeto::Zona {
+            self.nombre.clone()
+        } else {
+            String::new()
+        };
+    }
+}
+
+/// Contenido del informe de resultados
+#[derive(Debug, Clone)]
+pub struct Documento {
+    /// Nombre del proyecto
+    pub proyecto: String,
+    /// Ruta del archivo de resultados
+    pub ruta: PathBuf,
+    /// Fecha de generación del informe
+    pub fecha: String,
+    /// Secciones del informe: edificio, plantas y, opcionalmente, zonas
+    pub secciones: Vec<Seccion>,
+}
+
+impl Documento {
+    /// Genera el contenido del informe a partir del estado de la aplicación
+    ///
+    /// Incluye una sección para el edificio, una por planta y, si se indica, una por zona.
+    pub fn new(st: &AppState, incluir_zonas: bool) -> Result<Self, Error> {
+        let edificio = st
+            .edificio
+            .as_ref()
+            .ok_or("Sin datos de resultados (.res) para el modelo actual")?;
+        let ruta = st.respath.clone().unwrap_or_default();
+        let proyecto = st
+            .filename()
+            .map(|f| f.display().to_string())
+            .unwrap_or_else(|| edificio.nombre.clone());
+        let hay_bin = st.bindata.is_some();
+        let mut st = st.clone();
+        st.ranking_tipo = None;
+        st.ranking_planta = None;
+
+        // Edificio
+        let mut resumen = tabla_demandas(&st, "Resumen por plantas", "Planta");
+        for planta in &edificio.plantas {
+            resumen
+                .filas
+                .push(fila_demandas(&mut st, TipoObjeto::Planta, &planta.nombre));
+        }
+        resumen
+            .totales
+            .push(fila_total(&mut st, TipoObjeto::Edificio, &edificio.nombre));
+        let mut graficas = vec![
+            Grafica::HistoConceptos,
+            Grafica::HistoMeses,
+            Grafica::Tarta(PieMode::CalPos),
+            Grafica::Tarta(PieMode::CalNeg),
+            Grafica::Tarta(PieMode::RefPos),
+            Grafica::Tarta(PieMode::RefNeg),
+            Grafica::Sankey,
+            Grafica::Ranking,
+        ];
+        if hay_bin {
+            graficas.extend_from_slice(&[Grafica::Duracion, Grafica::RedZonas]);
+        }
+        let mut secciones = vec![Seccion {
+            titulo: format!("Edificio: {}", edificio.nombre),
+            nivel: 0,
+            tipo: TipoObjeto::Edificio,
+            nombre: edificio.nombre.clone(),
+            tablas: vec![resumen],
+            graficas,
+        }];
+
+        for planta in &edificio.plantas {
+            // Planta
+            let mut zonas = tabla_demandas(&st, "Zonas de la planta", "Zona");
+            for zona in &planta.zonas {
+                zonas
+                    .filas
+                    .push(fila_demandas(&mut st, TipoObjeto::Zona, zona));
+            }
+            zonas
+                .totales
+                .push(fila_total(&mut st, TipoObjeto::Planta, &planta.nombre));
+            secciones.push(Seccion {
+                titulo: format!("Planta: {}", planta.nombre),
+                nivel: 1,
+                tipo: TipoObjeto::Planta,
+                nombre: planta.nombre.clone(),
+                tablas: vec![zonas],
+                graficas: vec![
+                    Grafica::ZonasPlanta,
+                    Grafica::HistoConceptos,
+                    Grafica::HistoMeses,
+                    Grafica::Sankey,
+                ],
+            });
+            if !incluir_zonas {
+                continue;
+            }
+
+            // Zonas de la planta
+            for zona in &planta.zonas {
+                let mut datos = tabla_demandas(&st, "Datos de la zona", "Zona");
+                datos
+                    .filas
+                    .push(fila_demandas(&mut st, TipoObjeto::Zona, zona));
+                let mut tablas = vec![datos];
+                let mut graficas = vec![Grafica::HistoConceptos, Grafica::HistoMeses];
+                if let Some(zonalider) = st.bindata.as_ref().and_then(|b| b.zonas.get(zona)) {
+                    tablas.push(tabla_fuera_consigna(&HorasFueraConsigna::from_zona(
+                        zonalider,
+                        TOLERANCIA_CONSIGNA,
+                    )));
+                    graficas.extend_from_slice(&[
+                        Grafica::HorariosZona,
+                        Grafica::MapaHorario,
+                        Grafica::Duracion,
+                    ]);
+                }
+                secciones.push(Seccion {
+                    titulo: format!("Zona: {}", zona),
+                    nivel: 2,
+                    tipo: TipoObjeto::Zona,
+                    nombre: zona.clone(),
+                    tablas,
+                    graficas,
+                });
+            }
+        }
+
+        Ok(Self {
+            proyecto,
+            ruta,
+            fecha: Local::now().format("%d/%m/%Y %H:%M").to_string(),
+            secciones,
+        })
+    }
+}
+
+/// Genera el informe del modelo activo en la ruta indicada
+///
+/// El formato (PDF o HTML) se deduce de la extensión del archivo (.html o .htm y PDF en otro caso).
+pub fn export_informe<P: AsRef<Path>>(
+    st: &AppState,
+    config: &Config,
+    incluir_zonas: bool,
+    path: P,
+) -> Result<(), Error> {
+    let path = path.as_ref();
+    match path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_lowercase)
+        .as_deref()
+    {
+        Some("html") | Some("htm") => html::export_html(st, config, incluir_zonas, path),
+        _ => pdf::export_pdf(st, config, incluir_zonas, path),
+    }
+}
+
+/// Ejecuta f con el estilo de gráficas de los informes, restaurando después el estilo activo
+///
+/// Los informes usan la paleta y tipografía de la configuración, siempre con tema claro.
+fn con_estilo_informe<T>(config: &Config, f: impl FnOnce() -> T) -> T {
+    let estilo_previo = chart_style();
+    set_chart_style(ChartStyle::new(
+        config.palette,
+        false,
+        &config.font,
+        config.font_scale,
+    ));
+    let res = f();
+    set_chart_style((*estilo_previo).clone());
+    res
+}
+
+/// Tabla de flujos de todos los elementos constructivos del edificio, en las unidades activas
+///
+/// Los flujos de cada elemento se refieren a la superficie de su zona, como en las tablas de flujos.
+pub fn tabla_elementos(st: &AppState) -> Tabla {
+    let mut cabecera: Vec<String> = ["Planta", "Zona", "Elemento", "Tipo"]
+        .iter()
+        .map(|c| c.to_string())
+        .collect();
+    cabecera.extend(columnas_flujos(st.unidades));
+    let mut tabla = Tabla {
+        titulo: "Elementos constructivos".to_string(),
+        cabecera,
+        ..Default::default()
+    };
+    let edificio = match st.edificio.as_ref() {
+        Some(edificio) => edificio,
+        None => return tabla,
+    };
+    for planta in &edificio.plantas {
+        for zona in planta.zonas.iter().filter_map(|z| edificio.zonas.get(z)) {
+            let factor = st.unidades.factor(
+                zona.superficie * zona.multiplicador as f32,
+                edificio.superficie,
+            );
+            for elemento in &zona.elementos {
+                let mut fila = vec![
+                    planta.nombre.clone(),
+                    zona.nombre.clone(),
+                    elemento.nombre.clone(),
+                    zona.grupo(elemento).nombre().to_string(),
+                ];
+                fila.extend(
+                    valores(&(elemento.flujos * factor))
+                        .iter()
+                        .map(|v| format!("{:.2}", v)),
+                );
+                tabla.filas.push(fila);
+            }
+        }
+    }
+    tabla
+}
+
+/// Tabla vacía de multiplicador, superficie y demandas, con el nombre de objeto indicado
+fn tabla_demandas(st: &AppState, titulo: &str, objeto: &str) -> Tabla {
+    let unidades = st.unidades.anual();
+    Tabla {
+        titulo: titulo.to_string(),
+        cabecera: vec![
+            objeto.to_string(),
+            "Multiplicador".to_string(),
+            "Superficie [m²]".to_string(),
+            format!("Calefacción [{}]", unidades),
+            format!("Refrigeración [{}]", unidades),
+        ],
+        ..Default::default()
+    }
+}
+
+/// Fila de multiplicador, superficie y demandas del objeto, en las unidades activas
+fn fila_demandas(st: &mut AppState, tipo: TipoObjeto, nombre: &str) -> Vec<String> {
+    st.curr_obj_type = tipo;
+    st.curr_name = nombre.to_string();
+    let (mul, sup, cal, refr) = st.basicdata().unwrap_or_default();
+    vec![
+        nombre.to_string(),
+        mul.to_string(),
+        format!("{:.2}", sup),
+        format!("{:.1}", cal),
+        format!("{:.1}", refr),
+    ]
+}
+
+/// Fila de totales del objeto (planta o edificio), en las unidades activas
+fn fila_total(st: &mut AppState, tipo: TipoObjeto, nombre: &str) -> Vec<String> {
+    let mut fila = fila_demandas(st, tipo, nombre);
+    fila[0] = "Total".to_string();
+    fila
+}
+
+/// Tabla mensual de horas fuera de consigna
+fn tabla_fuera_consigna(horas: &HorasFueraConsigna) -> Tabla {
+    let filas: Vec<Vec<String>> = MESES
+        .iter()
+        .enumerate()
+        .map(|(i, mes)| {
+            vec![
+                mes.to_string(),
+                horas.calefaccion[i].to_string(),
+                horas.refrigeracion[i].to_string(),
+            ]
+        })
+        .collect();
+    let (cal, refr) = horas.totales();
+    let totales = vec![vec!["Total".to_string(), cal.to_string(), refr.to_string()]];
+    Tabla {
+        titulo: format!(
+            "Horas fuera de consigna (tolerancia {:.1} ºC)",
+            TOLERANCIA_CONSIGNA
+        ),
+        cabecera: vec![
+            "Mes".to_string(),
+            "Bajo consigna de calefacción [h]".to_string(),
+            "Sobre consigna de refrigeración [h]".to_string(),
+        ],
+        filas,
+        totales,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parsers::bin::BinData;
+    use crate::parsers::types::EdificioLIDER;
+    use crate::utils::testfile;
+
+    fn teststate() -> AppState {
+        AppState {
+            respath: Some(testfile("test.res")),
+            edificio: Some(EdificioLIDER::from_file(testfile("test.res")).unwrap()),
+            binpath: Some(testfile("test.bin")),
+            bindata: Some(BinData::from_file(testfile("test.bin")).unwrap()),
+            ..AppState::new()
+        }
+    }
+
+    #[test]
+    fn documento_secciones() {
+        assert!(Documento::new(&AppState::new(), false).is_err());
+
+        let st = teststate();
+        let edificio = st.edificio.as_ref().unwrap();
+        let nplantas = edificio.plantas.len();
+        let nzonas: usize = edificio.plantas.iter().map(|p| p.zonas.len()).sum();
+
+        let documento = Documento::new(&st, false).unwrap();
+        assert_eq!(documento.proyecto, "test");
+        assert_eq!(documento.secciones.len(), 1 + nplantas);
+        let seccion = &documento.secciones[0];
+        assert_eq!(seccion.tipo, TipoObjeto::Edificio);
+        // Con datos horarios se incluyen la curva de duración y la red de zonas
+        assert_eq!(seccion.graficas.len(), 10);
+        let resumen = &seccion.tablas[0];
+        assert_eq!(resumen.filas.len(), nplantas);
+        assert_eq!(resumen.totales[0][0], "Total");
+        assert!(resumen
+            .filas
+            .iter()
+            .all(|fila| fila.len() == resumen.cabecera.len()));
+
+        // Una sección por zona tras su planta, con las horas fuera de consigna
+        let documento = Documento::new(&st, true).unwrap();
+        assert_eq!(documento.secciones.len(), 1 + nplantas + nzonas);
+        assert_eq!(documento.secciones[1].nivel, 1);
+        let zona = &documento.secciones[2];
+        assert_eq!(zona.nivel, 2);
+        assert_eq!(zona.nombre, edificio.plantas[0].zonas[0]);
+        assert_eq!(zona.tablas.len(), 2);
+        assert_eq!(zona.tablas[1].filas.len(), 12);
+    }
+
+    #[test]
+    fn tabla_horas_fuera_consigna() {
+        let mut horas = HorasFueraConsigna::default();
+        horas.calefaccion[0] = 10;
+        horas.refrigeracion[6] = 5;
+        horas.refrigeracion[7] = 3;
+        let tabla = tabla_fuera_consigna(&horas);
+        assert_eq!(tabla.filas.len(), 12);
+        assert_eq!(tabla.filas[0], &["Enero", "10", "0"]);
+        assert_eq!(tabla.totales, vec![vec!["Total", "10", "8"]]);
+    }
+}
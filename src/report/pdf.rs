@@ -0,0 +1,297 @@
+//! Informe de resultados en formato PDF
+//!
+//! Genera un documento A4 apaisado con una portada, las tablas de cada sección y una página por
+//! gráfica, dibujada con las mismas funciones que la interfaz. Las páginas se graban en superficies
+//! de grabación de cairo, que se reproducen en el PDF o en la impresión del informe.
+
+use std::path::Path;
+
+use super::{con_estilo_informe, Documento, Tabla};
+use crate::appstate::AppState;
+use crate::config::Config;
+use crate::graphs::grafica::Grafica;
+use crate::graphs::style::{chart_style, set_color};
+use crate::utils::Error;
+use crate::APP_VERSION;
+
+/// Ancho de página (A4 apaisado) [pt]
+pub const ANCHO: f64 = 842.0;
+/// Alto de página (A4 apaisado) [pt]
+pub const ALTO: f64 = 595.0;
+/// Margen de página [pt]
+const MARGEN: f64 = 36.0;
+/// Alto de las filas de las tablas [pt]
+const ALTO_FILA: f64 = 16.0;
+
+/// Genera el informe PDF del modelo activo en la ruta indicada
+///
+/// Las gráficas usan la paleta y tipografía de la configuración, siempre con tema claro.
+pub fn export_pdf<P: AsRef<Path>>(
+    st: &AppState,
+    config: &Config,
+    incluir_zonas: bool,
+    path: P,
+) -> Result<(), Error> {
+    let paginas = paginas_informe(st, config, incluir_zonas)?;
+    let surface = cairo::PdfSurface::new(ANCHO, ALTO, path)?;
+    let cr = cairo::Context::new(&surface);
+    for pagina in &paginas {
+        cr.set_source_surface(pagina, 0.0, 0.0);
+        cr.paint();
+        cr.show_page();
+    }
+    surface.finish();
+    Ok(())
+}
+
+/// Páginas del informe del modelo activo, de tamaño ANCHO x ALTO [pt]
+///
+/// Las gráficas usan la paleta y tipografía de la configuración, siempre con tema claro.
+pub fn paginas_informe(
+    st: &AppState,
+    config: &Config,
+    incluir_zonas: bool,
+) -> Result<Vec<cairo::RecordingSurface>, Error> {
+    let documento = Documento::new(st, incluir_zonas)?;
+    con_estilo_informe(config, || grabar_paginas(&documento, st, config))
+}
+
+/// Página con una gráfica del objeto activo, del tamaño (ancho, alto) indicado [pt]
+///
+/// Se usa el estilo de los informes, para imprimir una gráfica suelta.
+pub fn pagina_grafica(
+    grafica: Grafica,
+    size: (f64, f64),
+    st: &AppState,
+    config: &Config,
+) -> Result<cairo::RecordingSurface, Error> {
+    let pagina = superficie_pagina(size)?;
+    con_estilo_informe(config, || {
+        grafica.draw(&cairo::Context::new(&pagina), size, st, config)
+    });
+    Ok(pagina)
+}
+
+/// Graba las páginas del documento
+fn grabar_paginas(
+    documento: &Documento,
+    st: &AppState,
+    config: &Config,
+) -> Result<Vec<cairo::RecordingSurface>, Error> {
+    let mut pdf = Paginas::new(&documento.proyecto)?;
+
+    pdf.portada(documento);
+    let mut st = st.clone();
+    for seccion in &documento.secciones {
+        seccion.select(&mut st);
+        pdf.nueva_pagina()?;
+        pdf.titulo(&seccion.titulo, seccion.nivel);
+        for tabla in &seccion.tablas {
+            pdf.tabla(tabla)?;
+        }
+        for grafica in &seccion.graficas {
+            pdf.nueva_pagina()?;
+            pdf.titulo(&seccion.titulo, seccion.nivel);
+            let (x0, y0) = (MARGEN, pdf.y);
+            let size = (ANCHO - 2.0 * MARGEN, ALTO - MARGEN - y0);
+            pdf.cr.save();
+            pdf.cr.translate(x0, y0);
+            pdf.cr.rectangle(0.0, 0.0, size.0, size.1);
+            pdf.cr.clip();
+            grafica.draw(&pdf.cr, size, &st, config);
+            pdf.cr.restore();
+        }
+    }
+    pdf.fin_pagina();
+    Ok(pdf.paginas)
+}
+
+/// Superficie de grabación de una página del tamaño (ancho, alto) indicado [pt]
+fn superficie_pagina(size: (f64, f64)) -> Result<cairo::RecordingSurface, Error> {
+    Ok(cairo::RecordingSurface::create(
+        cairo::Content::ColorAlpha,
+        cairo::Rectangle {
+            x: 0.0,
+            y: 0.0,
+            width: size.0,
+            height: size.1,
+        },
+    )?)
+}
+
+/// Paginación del documento: páginas grabadas y posición vertical en la página actual
+struct Paginas {
+    /// Páginas del documento, la última es la página actual
+    paginas: Vec<cairo::RecordingSurface>,
+    /// Contexto de dibujo de la página actual
+    cr: cairo::Context,
+    /// Texto del pie de página
+    pie: String,
+    /// Posición vertical del siguiente contenido en la página [pt]
+    y: f64,
+}
+
+impl Paginas {
+    /// Documento con la primera página empezada
+    fn new(pie: &str) -> Result<Self, Error> {
+        let pagina = superficie_pagina((ANCHO, ALTO))?;
+        Ok(Self {
+            cr: cairo::Context::new(&pagina),
+            paginas: vec![pagina],
+            pie: pie.to_string(),
+            y: MARGEN,
+        })
+    }
+
+    /// Termina la página actual con su pie
+    fn fin_pagina(&mut self) {
+        let style = chart_style();
+        style.select_font(&self.cr, cairo::FontWeight::Normal);
+        self.cr.set_font_size(8.0);
+        set_color(&self.cr, style.muted);
+        self.cr.move_to(MARGEN, ALTO - MARGEN / 2.0);
+        self.cr
+            .show_text(&format!("{} - ViSOL {}", self.pie, APP_VERSION));
+        let num = format!("Página {}", self.paginas.len());
+        let extents = self.cr.text_extents(&num);
+        self.cr
+            .move_to(ANCHO - MARGEN - extents.width, ALTO - MARGEN / 2.0);
+        self.cr.show_text(&num);
+    }
+
+    /// Termina la página actual y empieza una nueva
+    fn nueva_pagina(&mut self) -> Result<(), Error> {
+        self.fin_pagina();
+        let pagina = superficie_pagina((ANCHO, ALTO))?;
+        self.cr = cairo::Context::new(&pagina);
+        self.paginas.push(pagina);
+        self.y = MARGEN;
+        Ok(())
+    }
+
+    /// Escribe un texto en la posición actual y avanza la posición vertical
+    fn texto(&mut self, texto: &str, size: f64, weight: cairo::FontWeight) {
+        let style = chart_style();
+        style.select_font(&self.cr, weight);
+        self.cr.set_font_size(size);
+        set_color(&self.cr, style.text);
+        self.y += size;
+        self.cr.move_to(MARGEN, self.y);
+        self.cr.show_text(texto);
+        self.y += 0.5 * size;
+    }
+
+    /// Página de portada con el nombre del proyecto, la ruta del archivo y la fecha
+    fn portada(&mut self, documento: &Documento) {
+        self.y = ALTO / 3.0;
+        self.texto("Informe de resultados", 28.0, cairo::FontWeight::Bold);
+        self.y += 12.0;
+        self.texto(&documento.proyecto, 20.0, cairo::FontWeight::Normal);
+        self.y += 24.0;
+        self.texto(
+            &documento.ruta.display().to_string(),
+            11.0,
+            cairo::FontWeight::Normal,
+        );
+        self.texto(
+            &format!("Generado el {} con ViSOL {}", documento.fecha, APP_VERSION),
+            11.0,
+            cairo::FontWeight::Normal,
+        );
+    }
+
+    /// Título de sección, de tamaño decreciente con el nivel
+    fn titulo(&mut self, titulo: &str, nivel: usize) {
+        let size = [20.0, 17.0, 14.0][nivel.min(2)];
+        self.texto(titulo, size, cairo::FontWeight::Bold);
+        self.y += 6.0;
+    }
+
+    /// Tabla con título y encabezado, que continúa en páginas nuevas si no cabe
+    fn tabla(&mut self, tabla: &Tabla) -> Result<(), Error> {
+        let ncols = tabla.cabecera.len().max(1);
+        let ancho = ANCHO - 2.0 * MARGEN;
+        // La primera columna (nombres) es el doble de ancha que las demás
+        let ancho_col = ancho / (ncols + 1) as f64;
+        let xcol = |i: usize| {
+            if i == 0 {
+                MARGEN
+            } else {
+                MARGEN + (i + 1) as f64 * ancho_col
+            }
+        };
+
+        self.y += 6.0;
+        self.texto(&tabla.titulo, 12.0, cairo::FontWeight::Bold);
+        let mut filas = tabla.filas.iter().chain(tabla.totales.iter()).peekable();
+        loop {
+            // Encabezado
+            self.fila(&tabla.cabecera, &xcol, ancho_col, true);
+            while let Some(fila) = filas.peek() {
+                if self.y + ALTO_FILA > ALTO - MARGEN {
+                    break;
+                }
+                self.fila(fila, &xcol, ancho_col, false);
+                filas.next();
+            }
+            if filas.peek().is_none() {
+                break;
+            }
+            self.nueva_pagina()?;
+            self.texto(
+                &format!("{} (continuación)", tabla.titulo),
+                12.0,
+                cairo::FontWeight::Bold,
+            );
+        }
+        Ok(())
+    }
+
+    /// Fila de una tabla, con los números alineados a la derecha
+    fn fila(
+        &mut self,
+        celdas: &[String],
+        xcol: &dyn Fn(usize) -> f64,
+        ancho_col: f64,
+        cabecera: bool,
+    ) {
+        let style = chart_style();
+        let ancho_nombre = 2.0 * ancho_col;
+        if cabecera {
+            set_color(&self.cr, style.grid);
+            self.cr
+                .rectangle(MARGEN, self.y, ANCHO - 2.0 * MARGEN, ALTO_FILA);
+            self.cr.fill();
+        }
+        let weight = if cabecera {
+            cairo::FontWeight::Bold
+        } else {
+            cairo::FontWeight::Normal
+        };
+        style.select_font(&self.cr, weight);
+        self.cr.set_font_size(if cabecera { 8.0 } else { 9.0 });
+        set_color(&self.cr, style.text);
+        let ybase = self.y + 0.7 * ALTO_FILA;
+        for (i, celda) in celdas.iter().enumerate() {
+            let extents = self.cr.text_extents(celda);
+            let x = if i == 0 || cabecera {
+                xcol(i) + 2.0
+            } else {
+                xcol(i) + ancho_col - extents.width - 4.0
+            };
+            let limite = if i == 0 { ancho_nombre } else { ancho_col };
+            self.cr.save();
+            self.cr.rectangle(xcol(i), self.y, limite - 2.0, ALTO_FILA);
+            self.cr.clip();
+            self.cr.move_to(x, ybase);
+            self.cr.show_text(celda);
+            self.cr.restore();
+        }
+        set_color(&self.cr, style.grid);
+        self.cr.set_line_width(0.5);
+        self.cr.move_to(MARGEN, self.y + ALTO_FILA);
+        self.cr.rel_line_to(ANCHO - 2.0 * MARGEN, 0.0);
+        self.cr.stroke();
+        self.y += ALTO_FILA;
+    }
+}
@@ -53,6 +53,14 @@ pub fn check_current_dir() {
     };
 }
 
+/// Ruta de un archivo de datos de prueba del directorio src/data
+#[cfg(test)]
+pub fn testfile(name: &str) -> std::path::PathBuf {
+    std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("src/data")
+        .join(name)
+}
+
 /// Lee a una cadena un archivo en latin1
 pub fn read_latin1_file<T: AsRef<Path>>(path: T) -> Result<String, Error> {
     let buf = {
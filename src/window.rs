@@ -29,13 +29,23 @@ use gdk_pixbuf::Pixbuf;
 use gio::prelude::*;
 use glib::clone;
 use gtk::prelude::*;
+use gtk::SettingsExt;
 
+use crate::analysis::contribuciones::Componente;
+use crate::analysis::cumplimiento::{versiones_dbhe, ZONAS_CLIMATICAS};
+use crate::analysis::informe::Informe;
 use crate::appstate::{AppState, TipoObjeto};
 use crate::config::Config;
-use crate::graphs::histoconceptos::draw_histoconceptos;
-use crate::graphs::histomeses::draw_histomeses;
-use crate::graphs::horarioszona::draw_zonasgraph;
-use crate::graphs::piechart::{draw_piechart, PieMode};
+use crate::export::horarios::{export_horarios, Disposicion};
+use crate::export::tablas::export_tablas;
+use crate::graphs::grafica::Grafica;
+use crate::graphs::piechart::PieMode;
+use crate::graphs::style::{set_chart_style, ChartStyle};
+use crate::graphs::{find_region, Region};
+use crate::parsers::types::{Conceptos, Elemento, TipoElemento};
+use crate::report::export_informe;
+use crate::report::pdf::{pagina_grafica, paginas_informe, ALTO, ANCHO};
+use crate::utils::Error;
 
 // Inspeccionar elementos con CTRL+ SHIFT + D con la app lanzada
 
@@ -53,6 +63,28 @@ pub fn build_ui(
 
     window.set_application(Some(app));
 
+    // Configuración guardada por el usuario
+    if let Some(path) = Config::user_path().filter(|path| path.exists()) {
+        match Config::load(&path) {
+            Ok(saved) => *config.borrow_mut() = saved,
+            Err(e) => {
+                let sb: gtk::Statusbar = ui.get_object("statusbar").unwrap();
+                sb.push(0, &format!("Error al leer la configuración: {}", e));
+            }
+        }
+    }
+
+    // Estilo de las gráficas según la configuración, siguiendo los cambios de tema de GTK
+    set_chart_style(ChartStyle::from_config(&config.borrow()));
+    if let Some(settings) = gtk::Settings::get_default() {
+        let restyle = clone!(@weak config, @weak ui => move |_: &gtk::Settings| {
+            set_chart_style(ChartStyle::from_config(&config.borrow()));
+            update_graphs(ui);
+        });
+        settings.connect_property_gtk_theme_name_notify(restyle.clone());
+        settings.connect_property_gtk_application_prefer_dark_theme_notify(restyle);
+    }
+
     let ui_treeview: gtk::TreeView = ui.get_object("treeview").unwrap();
     // Columna de icono (3 del modelo)
     let col = gtk::TreeViewColumn::new();
@@ -103,149 +135,513 @@ pub fn build_ui(
     // Gráfica de datos horarios de zona
     let da_zonasgraph: gtk::DrawingArea = ui.get_object("zonasgraph").unwrap();
     da_zonasgraph.connect_draw(
-        clone!(@weak state => @default-return Inhibit(false), move |widget, cr| {
-            let st = state.borrow();
-            let curr_name = st.curr_name.as_str();
-            let zonedata = st.bindata.as_ref().and_then(|data| data.zonas.get(curr_name));
-            draw_zonasgraph(widget, cr, zonedata);
+        clone!(@weak state, @weak config => @default-return Inhibit(false), move |widget, cr| {
+            Grafica::HorariosZona.draw(cr, widget_size(widget), &state.borrow(), &config.borrow());
             Inhibit(false)
         }),
     );
 
-    // Histograma de flujos por conceptos de demanda y demandas netas anuales
-    let da_histoconceptos: gtk::DrawingArea = ui.get_object("histoconceptos").unwrap();
-    da_histoconceptos.connect_draw(
-        clone!(@weak state => @default-return Inhibit(false), move |widget, cr| {
-            let st = state.borrow();
-            let show_detail = st.show_detail;
-            let curr_name = st.curr_name.as_str();
-            let flujos = &st.concepts_data();
-            let (min, max) = st.edificio.as_ref().map(|e| e.minmaxconceptos(!show_detail)).unwrap_or((-15.0, 15.0));
-            draw_histoconceptos(widget, cr, curr_name, flujos, min, max, show_detail);
-            Inhibit(true)
+    // Mapa horario de variables de zona
+    let da_carpetplot: gtk::DrawingArea = ui.get_object("carpetplot").unwrap();
+    da_carpetplot.connect_draw(
+        clone!(@weak state, @weak config => @default-return Inhibit(false), move |widget, cr| {
+            Grafica::MapaHorario.draw(cr, widget_size(widget), &state.borrow(), &config.borrow());
+            Inhibit(false)
         }),
     );
 
-    // Histograma de demanda mensual
-    let da_histomeses: gtk::DrawingArea = ui.get_object("histomeses").unwrap();
-    da_histomeses.connect_draw(
-        clone!(@weak state => @default-return Inhibit(false), move |widget, cr| {
-            let st = state.borrow();
-            let (min, max) = st.edificio.as_ref().map(|e| e.minmaxmeses()).unwrap_or((-15.0,15.0));
-            let (cal_meses, ref_meses) = st.calref_monthly_data();
-            draw_histomeses(widget, cr, &cal_meses, &ref_meses, min, max);
-            Inhibit(true)
+    // Selección de variable del mapa horario
+    let cb_carpetvar: gtk::ComboBoxText = ui.get_object("cb_carpetvar").unwrap();
+    cb_carpetvar.connect_changed(clone!(@weak state, @strong ui => move |cb| {
+        if let Some(var) = cb.get_active_id().and_then(|id| id.parse().ok()) {
+            state.borrow_mut().carpet_var = var;
+            update_graphs(ui.clone());
+        }
+    }));
+
+    // Curvas de duración de carga
+    let da_curvasduracion: gtk::DrawingArea = ui.get_object("curvasduracion").unwrap();
+    da_curvasduracion.connect_draw(
+        clone!(@weak state, @weak config => @default-return Inhibit(false), move |widget, cr| {
+            Grafica::Duracion.draw(cr, widget_size(widget), &state.borrow(), &config.borrow());
+            Inhibit(false)
         }),
     );
 
-    // Cal pos
-    let da_calpos: gtk::DrawingArea = ui.get_object("pieglobalcalpos").unwrap();
-    da_calpos.connect_draw(
-        clone!(@weak state => @default-return Inhibit(false), move |widget, cr| {
-            let st = state.borrow();
-            let flujos = &st.concepts_data();
-            draw_piechart(widget, cr, flujos, PieMode::CalPos);
-            Inhibit(true)
+    // Red de adyacencia entre zonas
+    let da_redzonas: gtk::DrawingArea = ui.get_object("redzonas").unwrap();
+    da_redzonas.connect_draw(
+        clone!(@weak state, @weak config => @default-return Inhibit(false), move |widget, cr| {
+            Grafica::RedZonas.draw(cr, widget_size(widget), &state.borrow(), &config.borrow());
+            Inhibit(false)
         }),
     );
 
-    // Cal neg
-    let da_calneg: gtk::DrawingArea = ui.get_object("pieglobalcalneg").unwrap();
-    da_calneg.connect_draw(
-        clone!(@weak state => @default-return Inhibit(false), move |widget, cr| {
-            let st = state.borrow();
-            draw_piechart(widget, cr, &st.concepts_data(), PieMode::CalNeg);
-            Inhibit(true)
+    // Diagrama de Sankey de flujos de calor
+    let da_sankey: gtk::DrawingArea = ui.get_object("sankey").unwrap();
+    da_sankey.connect_draw(
+        clone!(@weak state, @weak config => @default-return Inhibit(false), move |widget, cr| {
+            Grafica::Sankey.draw(cr, widget_size(widget), &state.borrow(), &config.borrow());
+            Inhibit(false)
         }),
     );
 
-    // Ref pos
-    let da_refpos: gtk::DrawingArea = ui.get_object("pieglobalrefpos").unwrap();
-    da_refpos.connect_draw(
-        clone!(@weak state => @default-return Inhibit(false), move |widget, cr| {
+    // Histograma por zonas de la planta
+    let da_zonasplanta: gtk::DrawingArea = ui.get_object("zonasplanta").unwrap();
+    da_zonasplanta.connect_draw(
+        clone!(@weak state, @weak config => @default-return Inhibit(false), move |widget, cr| {
+            Grafica::ZonasPlanta.draw(cr, widget_size(widget), &state.borrow(), &config.borrow());
+            Inhibit(false)
+        }),
+    );
+
+    // Clasificación de elementos con peor comportamiento
+    let da_ranking: gtk::DrawingArea = ui.get_object("rankingelementos").unwrap();
+    da_ranking.connect_draw(
+        clone!(@weak state, @weak config => @default-return Inhibit(false), move |widget, cr| {
+            Grafica::Ranking.draw(cr, widget_size(widget), &state.borrow(), &config.borrow());
+            Inhibit(false)
+        }),
+    );
+
+    // Unidades de flujos y demandas
+    let cb_unidades: gtk::ComboBoxText = ui.get_object("cb_unidades").unwrap();
+    cb_unidades.connect_changed(clone!(@weak state, @strong ui => move |cb| {
+        if let Some(unidades) = cb.get_active_id().and_then(|id| id.parse().ok()) {
+            state.borrow_mut().unidades = unidades;
+            update_labelzona(&ui, &state.borrow());
+            update_graphs(ui.clone());
+        }
+    }));
+
+    // Versión del DB-HE, zona climática y uso del edificio para comprobar los límites de demanda
+    let cb_dbhe: gtk::ComboBoxText = ui.get_object("cb_dbhe").unwrap();
+    match versiones_dbhe() {
+        Ok(versiones) => {
+            for version in versiones {
+                cb_dbhe.append(Some(&version), &version);
+            }
+        }
+        Err(e) => {
+            let sb: gtk::Statusbar = ui.get_object("statusbar").unwrap();
+            sb.push(0, &e.to_string());
+        }
+    }
+    cb_dbhe.set_active_id(Some(&state.borrow().dbhe.version));
+    cb_dbhe.connect_changed(clone!(@weak state, @strong ui => move |cb| {
+        if let Some(version) = cb.get_active_id() {
+            state.borrow_mut().dbhe.version = version.to_string();
+            update_labelzona(&ui, &state.borrow());
+        }
+    }));
+    let cb_zonaclimatica: gtk::ComboBoxText = ui.get_object("cb_zonaclimatica").unwrap();
+    cb_zonaclimatica.append(Some(""), "Zona climática");
+    for zona in ZONAS_CLIMATICAS.iter() {
+        cb_zonaclimatica.append(Some(zona), zona);
+    }
+    cb_zonaclimatica.set_active_id(Some(""));
+    cb_zonaclimatica.connect_changed(clone!(@weak state, @strong ui => move |cb| {
+        state.borrow_mut().dbhe.zona = cb.get_active_id().and_then(|id| id.parse().ok());
+        update_labelzona(&ui, &state.borrow());
+    }));
+    let cb_uso: gtk::ComboBoxText = ui.get_object("cb_uso").unwrap();
+    cb_uso.append(Some(""), "Uso del edificio");
+    cb_uso.append(Some("residencial"), "Residencial");
+    cb_uso.append(Some("terciario"), "Terciario");
+    cb_uso.set_active_id(Some(""));
+    cb_uso.connect_changed(clone!(@weak state, @strong ui => move |cb| {
+        state.borrow_mut().dbhe.uso = cb.get_active_id().and_then(|id| id.parse().ok());
+        update_labelzona(&ui, &state.borrow());
+    }));
+
+    // Paleta y tema de las gráficas
+    let cb_paleta: gtk::ComboBoxText = ui.get_object("cb_paleta").unwrap();
+    cb_paleta.set_active_id(Some(&config.borrow().palette.to_string()));
+    cb_paleta.connect_changed(clone!(@weak config, @strong ui => move |cb| {
+        if let Some(palette) = cb.get_active_id().and_then(|id| id.parse().ok()) {
+            config.borrow_mut().palette = palette;
+            set_chart_style(ChartStyle::from_config(&config.borrow()));
+            save_config(&ui, &config.borrow());
+            update_graphs(ui.clone());
+        }
+    }));
+    let cb_tema: gtk::ComboBoxText = ui.get_object("cb_tema").unwrap();
+    cb_tema.set_active_id(Some(&config.borrow().theme.to_string()));
+    cb_tema.connect_changed(clone!(@weak config, @strong ui => move |cb| {
+        if let Some(theme) = cb.get_active_id().and_then(|id| id.parse().ok()) {
+            config.borrow_mut().theme = theme;
+            set_chart_style(ChartStyle::from_config(&config.borrow()));
+            save_config(&ui, &config.borrow());
+            update_graphs(ui.clone());
+        }
+    }));
+
+    // Filtros de la clasificación de elementos
+    let cb_rankingcriterio: gtk::ComboBoxText = ui.get_object("cb_rankingcriterio").unwrap();
+    cb_rankingcriterio.connect_changed(clone!(@weak state, @strong ui => move |cb| {
+        if let Some(criterio) = cb.get_active_id().and_then(|id| id.parse().ok()) {
+            state.borrow_mut().ranking_criterio = criterio;
+            update_graphs(ui.clone());
+        }
+    }));
+    let cb_rankingtipo: gtk::ComboBoxText = ui.get_object("cb_rankingtipo").unwrap();
+    cb_rankingtipo.append(Some(""), "Todos los tipos");
+    for tipo in TipoElemento::TODOS.iter() {
+        cb_rankingtipo.append(Some(tipo.nombre()), tipo.nombre());
+    }
+    cb_rankingtipo.set_active_id(Some(""));
+    cb_rankingtipo.connect_changed(clone!(@weak state, @strong ui => move |cb| {
+        let tipo = cb.get_active_id().and_then(|id| TipoElemento::from_grupo(&id));
+        state.borrow_mut().ranking_tipo = tipo;
+        update_graphs(ui.clone());
+    }));
+    let cb_rankingplanta: gtk::ComboBoxText = ui.get_object("cb_rankingplanta").unwrap();
+    cb_rankingplanta.connect_changed(clone!(@weak state, @strong ui => move |cb| {
+        let planta = cb.get_active_id().map(|id| id.to_string()).filter(|id| !id.is_empty());
+        state.borrow_mut().ranking_planta = planta;
+        update_graphs(ui.clone());
+    }));
+
+    // Exporta las curvas de duración de carga a CSV
+    let bt_exportduracion: gtk::Button = ui.get_object("bt_exportduracion").unwrap();
+    bt_exportduracion.connect_clicked(clone!(@weak state, @weak window, @strong ui => move |_| {
+        let sb: gtk::Statusbar = ui.get_object("statusbar").unwrap();
+        let (curvas, name, dir) = {
             let st = state.borrow();
-            draw_piechart(widget, cr, &st.concepts_data(), PieMode::RefPos);
+            (
+                st.duration_curves(),
+                format!("{}-curvasduracion.csv", st.curr_name),
+                st.dirname().map(Path::to_path_buf),
+            )
+        };
+        let curvas = match curvas {
+            Some(curvas) => curvas,
+            None => {
+                sb.push(0, "Sin datos horarios para el objeto seleccionado");
+                return;
+            }
+        };
+        if let Some(path) = savefile(&window, "Exportar curvas de duración de carga", &name, dir.as_deref(), &[("CSV (*.csv)", "*.csv")]) {
+            match std::fs::write(&path, curvas.to_csv()) {
+                Ok(_) => sb.push(0, &format!("Exportadas curvas de duración de carga: {}", path.display())),
+                Err(e) => sb.push(0, &format!("Error al exportar las curvas de duración de carga: {}", e)),
+            };
+        }
+    }));
+
+    // Histograma de flujos por conceptos de demanda y demandas netas anuales
+    let da_histoconceptos: gtk::DrawingArea = ui.get_object("histoconceptos").unwrap();
+    let regiones_histoconceptos = connect_tooltips(&da_histoconceptos);
+    da_histoconceptos.connect_draw(
+        clone!(@weak state, @weak config, @strong regiones_histoconceptos => @default-return Inhibit(false), move |widget, cr| {
+            *regiones_histoconceptos.borrow_mut() = Grafica::HistoConceptos.draw(cr, widget_size(widget), &state.borrow(), &config.borrow());
+            Inhibit(true)
+        }),
+    );
+    // Lista de elementos que contribuyen al concepto de la barra pulsada
+    da_histoconceptos.add_events(gdk::EventMask::BUTTON_PRESS_MASK);
+    da_histoconceptos.connect_button_press_event(
+        clone!(@weak state, @weak window, @strong regiones_histoconceptos => @default-return Inhibit(false), move |_, event| {
+            let (x, y) = event.get_position();
+            let seleccion = find_region(&regiones_histoconceptos.borrow(), x, y).and_then(|r| r.concepto);
+            let (concepto, componente) = match seleccion.and_then(|(c, serie)| serie.parse::<Componente>().ok().map(|s| (c, s))) {
+                Some(seleccion) => seleccion,
+                None => return Inhibit(false),
+            };
+            let (nombre, _) = Conceptos::default().to_named_list()[concepto];
+            let titulo = format!("{} - {} ({})", nombre, componente.label(), state.borrow().curr_name);
+            let (contribuciones, unidades) = {
+                let st = state.borrow();
+                (st.concept_contributions(concepto, componente), st.unidades)
+            };
+            let text = if contribuciones.is_empty() {
+                "El concepto no tiene elementos constructivos asociados".to_string()
+            } else {
+                let mut text = format!("{:>14}  Elemento (tipo, zona)\n", unidades.anual());
+                for c in &contribuciones {
+                    text.push_str(&format!("{:>14.2}  {} ({}, {})\n", c.valor, c.elemento, c.tipo, c.zona));
+                }
+                text
+            };
+            show_text(&window, &titulo, &text);
             Inhibit(true)
         }),
     );
 
-    // Ref neg
-    let da_refneg: gtk::DrawingArea = ui.get_object("pieglobalrefneg").unwrap();
-    da_refneg.connect_draw(
-        clone!(@weak state => @default-return Inhibit(false), move |widget, cr| {
-            let st = state.borrow();
-            draw_piechart(widget, cr, &st.concepts_data(), PieMode::RefNeg);
-            gtk::Inhibit(false)
+    // Histograma de demanda mensual
+    let da_histomeses: gtk::DrawingArea = ui.get_object("histomeses").unwrap();
+    let regiones_histomeses = connect_tooltips(&da_histomeses);
+    da_histomeses.connect_draw(
+        clone!(@weak state, @weak config, @strong regiones_histomeses => @default-return Inhibit(false), move |widget, cr| {
+            *regiones_histomeses.borrow_mut() = Grafica::HistoMeses.draw(cr, widget_size(widget), &state.borrow(), &config.borrow());
+            Inhibit(true)
         }),
     );
 
+    // Gráficas de tarta: cal pos, cal neg, ref pos y ref neg
+    for &(id, mode) in &[
+        ("pieglobalcalpos", PieMode::CalPos),
+        ("pieglobalcalneg", PieMode::CalNeg),
+        ("pieglobalrefpos", PieMode::RefPos),
+        ("pieglobalrefneg", PieMode::RefNeg),
+    ] {
+        let da: gtk::DrawingArea = ui.get_object(id).unwrap();
+        let regiones = connect_tooltips(&da);
+        da.connect_draw(
+            clone!(@weak state, @weak config, @strong regiones => @default-return Inhibit(false), move |widget, cr| {
+                *regiones.borrow_mut() = Grafica::Tarta(mode).draw(cr, widget_size(widget), &state.borrow(), &config.borrow());
+                Inhibit(true)
+            }),
+        );
+    }
+
     // Guarda pantallazo de la gráfica actual
     let mnu_screenshot: gtk::ToolButton = ui.get_object("savebutton").unwrap();
-    mnu_screenshot.connect_clicked(clone!(@weak state, @weak config, @strong ui => move |_| {
+    mnu_screenshot.connect_clicked(clone!(@weak state, @weak config, @weak window, @strong ui => move |_| {
+        let sb: gtk::Statusbar = ui.get_object("statusbar").unwrap();
         let nb: gtk::Notebook = ui.get_object("notebook").unwrap();
-        let idx = nb.get_current_page();
-        let container = nb.get_nth_page(idx).unwrap();
-        let config = config.borrow();
-        let out_dpi = config.out_dpi; // 100
-        let out_fmt = &config.out_fmt; // '%Y%m%d_%H%M%S'
-        let out_basename = &config.out_basename; // 'ViSol'
-        let scale = out_dpi as f64 / 72.0;
-        for child in container.downcast::<gtk::Container>().unwrap().get_children() {
-            if child.is::<gtk::DrawingArea>() {
-                // Dibuja widget en superficie
-                let size = child.get_allocation();
-                let surf = cairo::ImageSurface::create(cairo::Format::ARgb32, (size.width as f64 * scale) as i32, (size.height as f64 * scale) as i32).expect("No se ha podido crear la superficie cairo");
-                let ctx = cairo::Context::new(&surf);
-                ctx.scale(scale, scale);
-                child.draw(&ctx);
-                // Genera nombre de archivo
-                let st = state.borrow();
-                let timestamp = Local::now().format(out_fmt).to_string();
-                let name = child.get_buildable_name().unwrap_or_else(|| "grafica".to_string());
-                let filename = format!("{}-{}-{}-{}.png", out_basename, name, timestamp, st.filename().expect("Sin nombre de archivo para el modelo actual").display());
-                let pathname = st.dirname().expect("Sin ruta para el modelo actual").join(filename);
-                let mut outfile = std::fs::File::create(&pathname).expect("No se ha podido crear el archivo");
-                // Guarda superficie en archivo y notifica a la app
-                surf.write_to_png(&mut outfile).expect("No ");
-                let sb: gtk::Statusbar = ui.get_object("statusbar").unwrap();
-                sb.push(0, &format!("Guardando captura de pantalla: {}", pathname.display()));
-                break
-            }
+        let page = match nb.get_nth_page(nb.get_current_page()) {
+            Some(page) => page,
+            None => return,
+        };
+        // Nombre de la gráfica de la página o, si no tiene, de la propia página
+        let name = page
+            .clone()
+            .downcast::<gtk::Container>()
+            .ok()
+            .and_then(|container| {
+                container
+                    .get_children()
+                    .into_iter()
+                    .find(|child| child.is::<gtk::DrawingArea>())
+                    .and_then(|child| child.get_buildable_name())
+            })
+            .or_else(|| page.get_buildable_name())
+            .map(|name| name.to_string())
+            .unwrap_or_else(|| "grafica".to_string());
+        let (filename, dir, dpi) = {
+            let config = config.borrow();
+            let st = state.borrow();
+            let timestamp = Local::now().format(&config.out_fmt).to_string();
+            let modelo = st
+                .filename()
+                .map(|f| format!("-{}", f.to_string_lossy()))
+                .unwrap_or_default();
+            let filename = format!("{}-{}-{}{}.png", config.out_basename, name, timestamp, modelo);
+            let dir = config
+                .out_dir
+                .clone()
+                .or_else(|| st.dirname().map(Path::to_path_buf));
+            (filename, dir, config.out_dpi)
+        };
+        if let Some(path) = savefile_captura(&window, &filename, dir.as_deref()) {
+            match save_screenshot(&page, &path, dpi) {
+                Ok(_) => {
+                    sb.push(0, &format!("Guardada captura de pantalla: {}", path.display()));
+                    config.borrow_mut().out_dir = path.parent().map(Path::to_path_buf);
+                    save_config(&ui, &config.borrow());
+                }
+                Err(e) => {
+                    sb.push(0, &format!("Error al guardar la captura de pantalla: {}", e));
+                }
+            };
         }
     }
     ));
 
+    // Copia la gráfica de la página activa al portapapeles, como imagen y como datos tabulados
+    let mnu_copiar: gtk::ToolButton = ui.get_object("copiarbutton").unwrap();
+    mnu_copiar.connect_clicked(clone!(@weak state, @weak config, @strong ui => move |_| {
+        let sb: gtk::Statusbar = ui.get_object("statusbar").unwrap();
+        let (grafica, size) = match grafica_activa(&ui) {
+            Some(grafica) => grafica,
+            None => {
+                sb.push(0, "La página activa no contiene ninguna gráfica");
+                return;
+            }
+        };
+        let (pixbuf, datos) = {
+            let st = state.borrow();
+            let surf = match cairo::ImageSurface::create(cairo::Format::ARgb32, size.0 as i32, size.1 as i32) {
+                Ok(surf) => surf,
+                Err(e) => {
+                    sb.push(0, &format!("Error al copiar la gráfica: {}", e));
+                    return;
+                }
+            };
+            grafica.draw(&cairo::Context::new(&surf), size, &st, &config.borrow());
+            (
+                gdk::pixbuf_get_from_surface(&surf, 0, 0, size.0 as i32, size.1 as i32),
+                grafica.to_tsv(&st),
+            )
+        };
+        // Formatos ofrecidos: imagen (info 0) y texto separado por tabuladores (info 1)
+        let mut targets = Vec::new();
+        if pixbuf.is_some() {
+            targets.push(gtk::TargetEntry::new("image/png", gtk::TargetFlags::empty(), 0));
+        }
+        for target in &["UTF8_STRING", "text/plain;charset=utf-8", "text/plain", "STRING", "TEXT"] {
+            targets.push(gtk::TargetEntry::new(target, gtk::TargetFlags::empty(), 1));
+        }
+        let clipboard = gtk::Clipboard::get(&gdk::SELECTION_CLIPBOARD);
+        let ok = clipboard.set_with_data(&targets, move |_, sel, info| {
+            match (info, pixbuf.as_ref()) {
+                (0, Some(pixbuf)) => sel.set_pixbuf(pixbuf),
+                _ => sel.set_text(&datos),
+            };
+        });
+        if ok {
+            sb.push(0, "Gráfica y datos copiados al portapapeles");
+        } else {
+            sb.push(0, "No se ha podido copiar la gráfica al portapapeles");
+        }
+    }));
+
+    // Exporta los datos horarios de las zonas del objeto seleccionado
+    let mnu_exporthorarios: gtk::ToolButton = ui.get_object("exporthorariosbutton").unwrap();
+    mnu_exporthorarios.connect_clicked(clone!(@weak state, @weak window, @strong ui => move |_| {
+        let sb: gtk::Statusbar = ui.get_object("statusbar").unwrap();
+        let (zonas, name, dir) = {
+            let st = state.borrow();
+            if st.bindata.is_none() {
+                sb.push(0, "Sin datos horarios (.bin) para el modelo actual");
+                return;
+            }
+            (
+                st.selected_zones(),
+                format!("{}-horarios", st.curr_name),
+                st.dirname().map(Path::to_path_buf),
+            )
+        };
+        if zonas.is_empty() {
+            sb.push(0, "Seleccione el edificio, una planta o una zona para exportar sus datos horarios");
+            return;
+        }
+        if let Some((path, disposicion)) = savefile_horarios(&window, &name, dir.as_deref()) {
+            let st = state.borrow();
+            match export_horarios(st.bindata.as_ref().unwrap(), &zonas, disposicion, &path) {
+                Ok(omitidas) if omitidas.is_empty() => sb.push(0, &format!("Exportados datos horarios: {}", path.display())),
+                Ok(omitidas) => sb.push(0, &format!(
+                    "Exportados datos horarios: {} (zonas sin datos horarios: {})",
+                    path.display(),
+                    omitidas.join(", ")
+                )),
+                Err(e) => sb.push(0, &format!("Error al exportar los datos horarios: {}", e)),
+            };
+        }
+    }));
+
+    // Exporta las tablas de flujos por conceptos y elementos del edificio
+    let mnu_exporttablas: gtk::ToolButton = ui.get_object("exporttablasbutton").unwrap();
+    mnu_exporttablas.connect_clicked(clone!(@weak state, @weak window, @strong ui => move |_| {
+        let sb: gtk::Statusbar = ui.get_object("statusbar").unwrap();
+        let dir = {
+            let st = state.borrow();
+            if st.edificio.is_none() {
+                sb.push(0, "Sin datos de resultados (.res) para el modelo actual");
+                return;
+            }
+            st.dirname().map(Path::to_path_buf)
+        };
+        let filters = [("Excel (*.xlsx)", "*.xlsx"), ("CSV (*.csv)", "*.csv")];
+        if let Some(path) = savefile(&window, "Exportar tablas de flujos", "flujos.xlsx", dir.as_deref(), &filters) {
+            let st = state.borrow();
+            match export_tablas(st.edificio.as_ref().unwrap(), st.unidades, &path) {
+                Ok(_) => sb.push(0, &format!("Exportadas tablas de flujos: {}", path.display())),
+                Err(e) => sb.push(0, &format!("Error al exportar las tablas de flujos: {}", e)),
+            };
+        }
+    }));
+
+    // Genera el informe de resultados en PDF o HTML
+    let mnu_informe: gtk::ToolButton = ui.get_object("informebutton").unwrap();
+    mnu_informe.connect_clicked(clone!(@weak state, @weak config, @weak window, @strong ui => move |_| {
+        let sb: gtk::Statusbar = ui.get_object("statusbar").unwrap();
+        let (name, dir) = {
+            let st = state.borrow();
+            if st.edificio.is_none() {
+                sb.push(0, "Sin datos de resultados (.res) para el modelo actual");
+                return;
+            }
+            (
+                format!("{}-informe.pdf", st.filename().map(|f| f.display().to_string()).unwrap_or_default()),
+                st.dirname().map(Path::to_path_buf),
+            )
+        };
+        if let Some((path, incluir_zonas)) = savefile_informe(&window, &name, dir.as_deref()) {
+            match export_informe(&state.borrow(), &config.borrow(), incluir_zonas, &path) {
+                Ok(_) => sb.push(0, &format!("Generado informe de resultados: {}", path.display())),
+                Err(e) => sb.push(0, &format!("Error al generar el informe de resultados: {}", e)),
+            };
+        }
+    }));
+
+    // Impresión de la gráfica activa o del informe de resultados
+    let impresion = Rc::new(RefCell::new(Impresion::default()));
+    let mnu_imprimir: gtk::MenuToolButton = ui.get_object("imprimirbutton").unwrap();
+    mnu_imprimir.connect_clicked(
+        clone!(@weak state, @weak config, @weak window, @strong ui, @strong impresion => move |_| {
+            print_grafica(&window, &ui, &state, &config, &impresion);
+        }),
+    );
+    let mnu_imprimirgrafica: gtk::MenuItem = ui.get_object("imprimirgrafica").unwrap();
+    mnu_imprimirgrafica.connect_activate(
+        clone!(@weak state, @weak config, @weak window, @strong ui, @strong impresion => move |_| {
+            print_grafica(&window, &ui, &state, &config, &impresion);
+        }),
+    );
+    let mnu_imprimirinforme: gtk::MenuItem = ui.get_object("imprimirinforme").unwrap();
+    mnu_imprimirinforme.connect_activate(
+        clone!(@weak state, @weak config, @weak window, @strong ui, @strong impresion => move |_| {
+            print_informe(&window, &ui, &state, &config, false, &impresion);
+        }),
+    );
+    let mnu_imprimirinformezonas: gtk::MenuItem = ui.get_object("imprimirinformezonas").unwrap();
+    mnu_imprimirinformezonas.connect_activate(
+        clone!(@weak state, @weak config, @weak window, @strong ui, @strong impresion => move |_| {
+            print_informe(&window, &ui, &state, &config, true, &impresion);
+        }),
+    );
+    let mnu_configurarpagina: gtk::MenuItem = ui.get_object("configurarpagina").unwrap();
+    mnu_configurarpagina.connect_activate(clone!(@weak window, @strong impresion => move |_| {
+        let mut impresion = impresion.borrow_mut();
+        let settings = impresion.settings.clone().unwrap_or_else(gtk::PrintSettings::new);
+        impresion.page_setup = gtk::print_run_page_setup_dialog(Some(&window), impresion.page_setup.as_ref(), &settings);
+    }));
+
+    // Botón de validación de los datos de resultados
+    let mnu_validar: gtk::ToolButton = ui.get_object("validarbutton").unwrap();
+    mnu_validar.connect_clicked(clone!(@weak state, @weak window, @strong ui => move |_| {
+        let informes: Vec<Informe> = {
+            let st = state.borrow();
+            st.check_res().into_iter().chain(st.check_res_bin()).collect()
+        };
+        if informes.is_empty() {
+            let sb: gtk::Statusbar = ui.get_object("statusbar").unwrap();
+            sb.push(0, "Sin datos de resultados (.res) para el modelo actual");
+            return;
+        }
+        show_informes(&window, &informes);
+    }));
+
     // Selecciona nueva fila al cambiar el cursor en la vista de árbol
     ui_treeview.connect_cursor_changed(clone!(@weak state, @strong ui => move |tv| {
         let selection = tv.get_selection();
         if let Some((model, iter)) = selection.get_selected() {
             let sb: gtk::Statusbar = ui.get_object("statusbar").unwrap();
-            let labelzona: gtk::Label = ui.get_object("labelzona").unwrap();
 
             let nombre: String = model.get_value(&iter, 0).get().unwrap().unwrap();
-            let tipo = model.get_value(&iter, 1).get_some::<u8>().unwrap().into();
+            let tipo: TipoObjeto = model.get_value(&iter, 1).get_some::<u8>().unwrap().into();
             let zone = model.get_value(&iter, 2).get::<String>().unwrap().unwrap();
-            let (mul, sup, cal, refr) = {
+            {
                 let mut model = state.borrow_mut();
                 model.curr_obj_type = tipo;
                 model.curr_name = nombre.clone();
                 model.curr_zone = zone;
-                model.basicdata().unwrap()
-            };
+            }
 
-            let mut txt1 = format!("<big><b>{}</b></big> ({})\n", nombre, tipo);
-            match tipo {
-                TipoObjeto::Edificio | TipoObjeto::Planta | TipoObjeto::Zona => {
-                    txt1.push_str(&format!("<i>{} x {:.2}m²</i>\n", mul, sup));
-                    txt1.push_str(&format!("calefacción: {:6.1}<i>kWh/m²año</i>, ", cal));
-                    txt1.push_str(&format!("refrigeración: {:6.1}<i>kWh/m²año</i>", refr));
-                },
-                _ => {
-                    txt1.push('\n');
-                }
-            };
             sb.push(0, &format!("Seleccionado {}: {}", tipo, nombre));
-            labelzona.set_property("label", &txt1).expect("Fallo al establecer etiqueta");
+            update_labelzona(&ui, &state.borrow());
             // Actualizar controles
             update_graphs(ui.clone());
         }
@@ -304,9 +700,20 @@ fn update_graphs(ui: gtk::Builder) {
     da_refneg.queue_draw();
     let da_zonasgraph: gtk::DrawingArea = ui.get_object("zonasgraph").unwrap();
     da_zonasgraph.queue_draw();
+    let da_carpetplot: gtk::DrawingArea = ui.get_object("carpetplot").unwrap();
+    da_carpetplot.queue_draw();
+    let da_curvasduracion: gtk::DrawingArea = ui.get_object("curvasduracion").unwrap();
+    da_curvasduracion.queue_draw();
+    let da_redzonas: gtk::DrawingArea = ui.get_object("redzonas").unwrap();
+    da_redzonas.queue_draw();
+    let da_ranking: gtk::DrawingArea = ui.get_object("rankingelementos").unwrap();
+    da_ranking.queue_draw();
+    let da_zonasplanta: gtk::DrawingArea = ui.get_object("zonasplanta").unwrap();
+    da_zonasplanta.queue_draw();
+    let da_sankey: gtk::DrawingArea = ui.get_object("sankey").unwrap();
+    da_sankey.queue_draw();
 }
 
-
 /// Load data from file path into the state and application ui
 fn loadfile<P: AsRef<Path>>(path: P, state: Rc<RefCell<AppState>>, ui: &gtk::Builder) {
     let sb: gtk::Statusbar = ui.get_object("statusbar").unwrap();
@@ -319,6 +726,17 @@ fn loadfile<P: AsRef<Path>>(path: P, state: Rc<RefCell<AppState>>, ui: &gtk::Bui
         let mut state = state.borrow_mut();
         state.load_data(&Some(path.to_path_buf()));
         sb.push(0, &format!("Seleccionado archivo: {}", path.display()));
+        if let Some(informe) = state.check_res() {
+            if !informe.incidencias.is_empty() {
+                sb.push(0, &informe.resumen());
+            }
+        }
+        if let Some(informe) = state.check_res_bin() {
+            if !informe.incidencias.is_empty() {
+                let binpath = state.binpath.as_ref().unwrap();
+                sb.push(0, &format!("{} ({})", informe.resumen(), binpath.display()));
+            }
+        }
         let mut pth = path.display().to_string();
         let pth: String = pth.drain(..std::cmp::max(0, pth.len() - 40)).collect(); // Recortar a máx 40 caracteres
         window.set_title(&format!("ViSOL [... {}]", &pth));
@@ -381,24 +799,90 @@ fn loadfile<P: AsRef<Path>>(path: P, state: Rc<RefCell<AppState>>, ui: &gtk::Bui
                 );
                 // Expande hasta el nivel de zonas
                 tv.expand_to_path(ts.get_path(&zonaiter).as_ref().unwrap());
-                // Carga los componentes de las zonas
-                for elemento in &e.zonas.get(zona).unwrap().elementos {
+                // Carga los componentes de las zonas, agrupados por tipo
+                let z = e.zonas.get(zona).unwrap();
+                let insert_child = |parent: &gtk::TreeIter, nombre: &str, tipo: TipoObjeto| {
                     ts.insert_with_values(
-                        Some(&zonaiter),
+                        Some(parent),
                         None,
                         &[0, 1, 2, 3],
-                        &[
-                            &elemento.nombre,
-                            &u8::from(TipoObjeto::Elemento),
-                            &zona,
-                            &elemento_icon,
-                        ],
-                    );
+                        &[&nombre, &u8::from(tipo), &zona, &elemento_icon],
+                    )
+                };
+                // Los huecos alojados en un elemento de la zona se muestran en el conjunto de ese elemento
+                let alojado = |el: &Elemento| {
+                    z.elementos
+                        .iter()
+                        .any(|o| el.padre.as_ref() == Some(&o.nombre))
+                };
+                for tipo in TipoElemento::TODOS.iter().copied() {
+                    let elementos: Vec<_> = z
+                        .elementos
+                        .iter()
+                        .filter(|el| z.grupo(el) == tipo && !alojado(el))
+                        .collect();
+                    if elementos.is_empty() {
+                        continue;
+                    }
+                    let grupoiter = insert_child(&zonaiter, tipo.nombre(), TipoObjeto::Grupo);
+                    for elemento in elementos {
+                        let huecos: Vec<_> = z.huecos(&elemento.nombre).collect();
+                        if huecos.is_empty() {
+                            insert_child(&grupoiter, &elemento.nombre, TipoObjeto::Elemento);
+                        } else {
+                            let conjuntoiter =
+                                insert_child(&grupoiter, &elemento.nombre, TipoObjeto::Conjunto);
+                            insert_child(&conjuntoiter, &elemento.nombre, TipoObjeto::Elemento);
+                            for hueco in huecos {
+                                insert_child(&conjuntoiter, &hueco.nombre, TipoObjeto::Elemento);
+                            }
+                        }
+                    }
                 }
             }
         }
-        sb.push(0, &format!("Cargado modelo: {}", path.display()));
+        sb.push(
+            0,
+            &format!("Cargado modelo: {} ({})", path.display(), e.version),
+        );
+    }
+
+    // Plantas disponibles en la clasificación de elementos
+    // (sin tomar prestado el estado, que se modifica al cambiar la selección)
+    let plantas: Vec<String> = state
+        .borrow()
+        .edificio
+        .as_ref()
+        .map(|e| e.plantas.iter().map(|p| p.nombre.clone()).collect())
+        .unwrap_or_default();
+    let cb_rankingplanta: gtk::ComboBoxText = ui.get_object("cb_rankingplanta").unwrap();
+    cb_rankingplanta.remove_all();
+    cb_rankingplanta.append(Some(""), "Todas las plantas");
+    for planta in &plantas {
+        cb_rankingplanta.append(Some(planta), planta);
     }
+    cb_rankingplanta.set_active_id(Some(""));
+
+    // Versión del DB-HE del archivo .res y zona climática y uso del edificio, detectados en el
+    // proyecto o elegidos antes
+    let (version, zona, uso) = {
+        let st = state.borrow();
+        (
+            st.dbhe.version.clone(),
+            st.dbhe.zona.as_ref().map(ToString::to_string),
+            st.dbhe.uso.map(|uso| uso.to_string()),
+        )
+    };
+    let cb_dbhe: gtk::ComboBoxText = ui.get_object("cb_dbhe").unwrap();
+    if !cb_dbhe.set_active_id(Some(&version)) {
+        // Versión sin límites de demanda en la tabla
+        cb_dbhe.append(Some(&version), &version);
+        cb_dbhe.set_active_id(Some(&version));
+    }
+    let cb_zonaclimatica: gtk::ComboBoxText = ui.get_object("cb_zonaclimatica").unwrap();
+    cb_zonaclimatica.set_active_id(Some(zona.as_deref().unwrap_or("")));
+    let cb_uso: gtk::ComboBoxText = ui.get_object("cb_uso").unwrap();
+    cb_uso.set_active_id(Some(uso.as_deref().unwrap_or("")));
 }
 
 /// Abre archivo de resultados
@@ -422,6 +906,520 @@ fn openfile() -> Option<PathBuf> {
     res
 }
 
+/// Crea diálogo de selección de archivo de destino para exportar datos
+///
+/// Propone el nombre de archivo name en el directorio dir (si se indica)
+/// y añade los filtros de archivo indicados como pares (nombre, patrón)
+fn save_dialog(
+    window: &gtk::ApplicationWindow,
+    title: &str,
+    name: &str,
+    dir: Option<&Path>,
+    filters: &[(&str, &str)],
+) -> gtk::FileChooserDialog {
+    let chooser = gtk::FileChooserDialog::with_buttons(
+        Some(title),
+        Some(window),
+        gtk::FileChooserAction::Save,
+        &[
+            ("Cancelar", gtk::ResponseType::Cancel),
+            ("Guardar", gtk::ResponseType::Accept),
+        ],
+    );
+    chooser.set_do_overwrite_confirmation(true);
+    chooser.set_current_name(name);
+    if let Some(dir) = dir {
+        chooser.set_current_folder(dir);
+    }
+    for (filtername, pattern) in filters {
+        let filter = gtk::FileFilter::new();
+        filter.set_name(Some(filtername));
+        filter.add_pattern(pattern);
+        chooser.add_filter(&filter);
+    }
+    chooser
+}
+
+/// Selecciona archivo de destino para exportar datos
+fn savefile(
+    window: &gtk::ApplicationWindow,
+    title: &str,
+    name: &str,
+    dir: Option<&Path>,
+    filters: &[(&str, &str)],
+) -> Option<PathBuf> {
+    let chooser = save_dialog(window, title, name, dir, filters);
+    let res = if chooser.run() == gtk::ResponseType::Accept {
+        chooser.get_filename()
+    } else {
+        None
+    };
+    chooser.close();
+    res
+}
+
+/// Selecciona archivo de destino y disposición de los datos horarios exportados
+///
+/// El formato (CSV o Parquet) se deduce de la extensión del archivo o, si no tiene una extensión
+/// conocida, del filtro seleccionado
+fn savefile_horarios(
+    window: &gtk::ApplicationWindow,
+    name: &str,
+    dir: Option<&Path>,
+) -> Option<(PathBuf, Disposicion)> {
+    let chooser = save_dialog(
+        window,
+        "Exportar datos horarios de zonas",
+        name,
+        dir,
+        &[
+            ("CSV (*.csv)", "*.csv"),
+            ("Parquet (*.parquet)", "*.parquet"),
+        ],
+    );
+    let cb_disposicion = gtk::ComboBoxText::new();
+    cb_disposicion.append(
+        Some("ancha"),
+        "Formato ancho (una columna por zona y variable)",
+    );
+    cb_disposicion.append(
+        Some("larga"),
+        "Formato largo (fecha, zona, variable, unidad, valor)",
+    );
+    cb_disposicion.set_active_id(Some("ancha"));
+    chooser.set_extra_widget(&cb_disposicion);
+
+    let res = if chooser.run() == gtk::ResponseType::Accept {
+        let disposicion = match cb_disposicion.get_active_id().as_deref() {
+            Some("larga") => Disposicion::Larga,
+            _ => Disposicion::Ancha,
+        };
+        let extension = match chooser.get_filter().and_then(|filter| filter.get_name()) {
+            Some(name) if name.starts_with("Parquet") => "parquet",
+            _ => "csv",
+        };
+        chooser.get_filename().map(|path| {
+            let path = match path
+                .extension()
+                .and_then(|e| e.to_str())
+                .map(str::to_lowercase)
+                .as_deref()
+            {
+                Some("csv") | Some("parquet") => path,
+                _ => path.with_extension(extension),
+            };
+            (path, disposicion)
+        })
+    } else {
+        None
+    };
+    chooser.close();
+    res
+}
+
+/// Selecciona archivo de destino del informe y si se incluye una sección por zona
+///
+/// El formato (PDF o HTML) se deduce de la extensión del archivo
+fn savefile_informe(
+    window: &gtk::ApplicationWindow,
+    name: &str,
+    dir: Option<&Path>,
+) -> Option<(PathBuf, bool)> {
+    let chooser = save_dialog(
+        window,
+        "Generar informe de resultados",
+        name,
+        dir,
+        &[("PDF (*.pdf)", "*.pdf"), ("HTML (*.html)", "*.html")],
+    );
+    let cb_zonas = gtk::CheckButton::with_label("Incluir una sección por zona");
+    chooser.set_extra_widget(&cb_zonas);
+
+    let res = if chooser.run() == gtk::ResponseType::Accept {
+        chooser
+            .get_filename()
+            .map(|path| (path, cb_zonas.get_active()))
+    } else {
+        None
+    };
+    chooser.close();
+    res
+}
+
+/// Selecciona archivo de destino de la captura de pantalla (PNG, SVG o PDF)
+///
+/// Si el nombre no tiene una extensión conocida se usa la del filtro seleccionado.
+fn savefile_captura(
+    window: &gtk::ApplicationWindow,
+    name: &str,
+    dir: Option<&Path>,
+) -> Option<PathBuf> {
+    let chooser = save_dialog(
+        window,
+        "Guardar captura de pantalla",
+        name,
+        dir,
+        &[
+            ("PNG (*.png)", "*.png"),
+            ("SVG (*.svg)", "*.svg"),
+            ("PDF (*.pdf)", "*.pdf"),
+        ],
+    );
+    let res = if chooser.run() == gtk::ResponseType::Accept {
+        let filtro = chooser
+            .get_filter()
+            .and_then(|filter| filter.get_name())
+            .map(|name| name.to_lowercase())
+            .unwrap_or_default();
+        chooser.get_filename().map(|path| {
+            match path
+                .extension()
+                .and_then(|e| e.to_str())
+                .map(str::to_lowercase)
+                .as_deref()
+            {
+                Some("png") | Some("svg") | Some("pdf") => path,
+                _ => path.with_extension(filtro.get(..3).unwrap_or("png")),
+            }
+        })
+    } else {
+        None
+    };
+    chooser.close();
+    res
+}
+
+/// Guarda la página indicada en un archivo PNG, SVG o PDF, según su extensión
+///
+/// Las imágenes PNG se generan con la resolución indicada [ppp].
+fn save_screenshot(page: &gtk::Widget, path: &Path, dpi: i32) -> Result<(), Error> {
+    let rect = page.get_allocation();
+    let (width, height) = (rect.width as f64, rect.height as f64);
+    match path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_lowercase)
+        .as_deref()
+    {
+        Some("svg") => {
+            let surf = cairo::SvgSurface::new(width, height, Some(path))?;
+            page.draw(&cairo::Context::new(&surf));
+            surf.finish();
+        }
+        Some("pdf") => {
+            let surf = cairo::PdfSurface::new(width, height, path)?;
+            page.draw(&cairo::Context::new(&surf));
+            surf.finish();
+        }
+        _ => {
+            let scale = dpi as f64 / 72.0;
+            let surf = cairo::ImageSurface::create(
+                cairo::Format::ARgb32,
+                (width * scale) as i32,
+                (height * scale) as i32,
+            )?;
+            {
+                let ctx = cairo::Context::new(&surf);
+                ctx.scale(scale, scale);
+                page.draw(&ctx);
+            }
+            let mut outfile = std::fs::File::create(path)?;
+            surf.write_to_png(&mut outfile)?;
+        }
+    }
+    Ok(())
+}
+
+/// Guarda la configuración del usuario, informando de los errores en la barra de estado
+fn save_config(ui: &gtk::Builder, config: &Config) {
+    let res = Config::user_path()
+        .ok_or_else(|| "No se ha encontrado el directorio de configuración del usuario".into())
+        .and_then(|path| config.save(path));
+    if let Err(e) = res {
+        let sb: gtk::Statusbar = ui.get_object("statusbar").unwrap();
+        sb.push(0, &format!("Error al guardar la configuración: {}", e));
+    }
+}
+
+/// Ajustes de impresión y de página de la sesión
+#[derive(Default)]
+struct Impresion {
+    /// Ajustes de la última impresión
+    settings: Option<gtk::PrintSettings>,
+    /// Configuración de página elegida por el usuario
+    page_setup: Option<gtk::PageSetup>,
+}
+
+/// Imprime la gráfica de la página activa
+///
+/// La página se graba antes de abrir el diálogo de impresión, que ejecuta su propio bucle de
+/// eventos, para no mantener prestados el estado ni la configuración mientras está abierto.
+fn print_grafica(
+    window: &gtk::ApplicationWindow,
+    ui: &gtk::Builder,
+    state: &RefCell<AppState>,
+    config: &RefCell<Config>,
+    impresion: &RefCell<Impresion>,
+) {
+    let sb: gtk::Statusbar = ui.get_object("statusbar").unwrap();
+    let (grafica, size) = match grafica_activa(ui) {
+        Some(grafica) => grafica,
+        None => {
+            sb.push(0, "La página activa no contiene ninguna gráfica");
+            return;
+        }
+    };
+    let (nombre, pagina) = {
+        let st = state.borrow();
+        let pagina = pagina_grafica(grafica, size, &st, &config.borrow());
+        (st.curr_name.clone(), pagina)
+    };
+    let res =
+        pagina.and_then(|pagina| print_paginas(window, &nombre, vec![pagina], size, impresion));
+    if let Err(e) = res {
+        sb.push(0, &format!("Error al imprimir la gráfica: {}", e));
+    }
+}
+
+/// Imprime el informe de resultados del edificio, con una sección por zona si se indica
+///
+/// Como en la impresión de gráficas, las páginas se graban antes de abrir el diálogo de impresión.
+fn print_informe(
+    window: &gtk::ApplicationWindow,
+    ui: &gtk::Builder,
+    state: &RefCell<AppState>,
+    config: &RefCell<Config>,
+    incluir_zonas: bool,
+    impresion: &RefCell<Impresion>,
+) {
+    let sb: gtk::Statusbar = ui.get_object("statusbar").unwrap();
+    let (trabajo, paginas) = {
+        let st = state.borrow();
+        let trabajo = format!(
+            "Informe de resultados - {}",
+            st.filename()
+                .map(|f| f.display().to_string())
+                .unwrap_or_default()
+        );
+        (
+            trabajo,
+            paginas_informe(&st, &config.borrow(), incluir_zonas),
+        )
+    };
+    let res = paginas
+        .and_then(|paginas| print_paginas(window, &trabajo, paginas, (ANCHO, ALTO), impresion));
+    if let Err(e) = res {
+        sb.push(
+            0,
+            &format!("Error al imprimir el informe de resultados: {}", e),
+        );
+    }
+}
+
+/// Imprime las páginas grabadas, de tamaño (ancho, alto) [pt], ajustadas al área imprimible
+///
+/// El diálogo de impresión permite cambiar la configuración de página y obtener una vista previa.
+/// Sin configuración de página elegida por el usuario se imprime en apaisado.
+fn print_paginas(
+    window: &gtk::ApplicationWindow,
+    trabajo: &str,
+    paginas: Vec<cairo::RecordingSurface>,
+    size: (f64, f64),
+    impresion: &RefCell<Impresion>,
+) -> Result<(), Error> {
+    let op = gtk::PrintOperation::new();
+    op.set_job_name(trabajo);
+    op.set_unit(gtk::Unit::Points);
+    op.set_embed_page_setup(true);
+    op.set_n_pages(paginas.len() as i32);
+    {
+        let impresion = impresion.borrow();
+        let page_setup = impresion.page_setup.clone().unwrap_or_else(|| {
+            let page_setup = gtk::PageSetup::new();
+            page_setup.set_orientation(gtk::PageOrientation::Landscape);
+            page_setup
+        });
+        op.set_default_page_setup(Some(&page_setup));
+        op.set_print_settings(impresion.settings.as_ref());
+    }
+    op.connect_draw_page(move |_, ctx, num| {
+        let (cr, pagina) = match (ctx.get_cairo_context(), paginas.get(num as usize)) {
+            (Some(cr), Some(pagina)) => (cr, pagina),
+            _ => return,
+        };
+        let (width, height) = (ctx.get_width(), ctx.get_height());
+        let escala = (width / size.0).min(height / size.1);
+        cr.translate(
+            0.5 * (width - escala * size.0),
+            0.5 * (height - escala * size.1),
+        );
+        cr.scale(escala, escala);
+        cr.set_source_surface(pagina, 0.0, 0.0);
+        cr.paint();
+    });
+    if op.run(gtk::PrintOperationAction::PrintDialog, Some(window))?
+        == gtk::PrintOperationResult::Apply
+    {
+        impresion.borrow_mut().settings = op.get_print_settings();
+    }
+    Ok(())
+}
+
+/// Actualiza la etiqueta con los datos básicos del objeto activo
+fn update_labelzona(ui: &gtk::Builder, st: &AppState) {
+    let labelzona: gtk::Label = ui.get_object("labelzona").unwrap();
+    let (mul, sup, cal, refr) = match st.basicdata() {
+        Some(data) => data,
+        None => return,
+    };
+    let tipo = st.curr_obj_type;
+    let unidades = st.unidades.anual();
+    let mut txt1 = format!("<big><b>{}</b></big> ({})\n", st.curr_name, tipo);
+    match tipo {
+        TipoObjeto::Edificio | TipoObjeto::Planta | TipoObjeto::Zona => {
+            txt1.push_str(&format!("<i>{} x {:.2}m²</i>\n", mul, sup));
+            txt1.push_str(&format!("calefacción: {:6.1}<i>{}</i>, ", cal, unidades));
+            txt1.push_str(&format!("refrigeración: {:6.1}<i>{}</i>", refr, unidades));
+        }
+        _ => {
+            txt1.push('\n');
+        }
+    };
+    if tipo == TipoObjeto::Edificio {
+        txt1.push('\n');
+        txt1.push_str(&markup_dbhe(st));
+    }
+    labelzona
+        .set_property("label", &txt1)
+        .expect("Fallo al establecer etiqueta");
+}
+
+/// Distintivos de cumplimiento de los límites de demanda del DB-HE del edificio, con sus márgenes
+fn markup_dbhe(st: &AppState) -> String {
+    let (zona, uso, indicadores) = match (&st.dbhe.zona, st.dbhe.uso, st.check_dbhe()) {
+        (_, _, Err(e)) => {
+            return format!(
+                "<small><span foreground=\"#c62828\">{}</span></small>",
+                glib::markup_escape_text(&e.to_string())
+            )
+        }
+        (Some(zona), Some(uso), Ok(Some(indicadores))) => (zona, uso, indicadores),
+        _ => {
+            return "<small><i>Seleccione la zona climática y el uso del edificio para comprobar los límites de demanda del DB-HE</i></small>".to_string()
+        }
+    };
+    let mut txt = format!(
+        "<small>{}, zona {}, uso {}:</small>",
+        st.dbhe.version, zona, uso
+    );
+    for indicador in &indicadores {
+        let servicio = indicador.servicio.to_string().to_uppercase();
+        match (indicador.cumple(), indicador.limite, indicador.margen()) {
+            (Some(cumple), Some(limite), Some(margen)) => {
+                let (color, resultado) = if cumple {
+                    ("#2e7d32", "CUMPLE")
+                } else {
+                    ("#c62828", "NO CUMPLE")
+                };
+                txt.push_str(&format!(
+                    " <span background=\"{}\" foreground=\"white\"><b> {} {} </b></span> <small>{:.1} / {:.1} <i>kWh/m²·año</i> (margen {:+.1}, {:+.0}%)</small>",
+                    color,
+                    servicio,
+                    resultado,
+                    indicador.demanda,
+                    limite,
+                    margen,
+                    indicador.margen_pct().unwrap_or_default()
+                ));
+            }
+            _ => txt.push_str(&format!(
+                " <span background=\"#757575\" foreground=\"white\"><b> {} SIN LÍMITE </b></span>",
+                servicio
+            )),
+        }
+    }
+    txt
+}
+
+/// Muestra los informes de validación de los datos en un diálogo
+fn show_informes(window: &gtk::ApplicationWindow, informes: &[Informe]) {
+    let text = informes
+        .iter()
+        .map(|informe| {
+            if informe.incidencias.is_empty() {
+                format!("{}\nSin incidencias\n", informe.resumen())
+            } else {
+                informe.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    show_text(window, "Validación de los datos de resultados", &text);
+}
+
+/// Muestra un texto en un diálogo modal
+fn show_text(window: &gtk::ApplicationWindow, title: &str, text: &str) {
+    let dialog = gtk::Dialog::with_buttons(
+        Some(title),
+        Some(window),
+        gtk::DialogFlags::MODAL | gtk::DialogFlags::DESTROY_WITH_PARENT,
+        &[("Cerrar", gtk::ResponseType::Close)],
+    );
+    dialog.set_default_size(800, 500);
+
+    let textview = gtk::TextView::new();
+    textview.set_editable(false);
+    textview.set_monospace(true);
+    textview.set_wrap_mode(gtk::WrapMode::WordChar);
+    textview.get_buffer().unwrap().set_text(text);
+    let scrolled = gtk::ScrolledWindow::new(gtk::NONE_ADJUSTMENT, gtk::NONE_ADJUSTMENT);
+    scrolled.add(&textview);
+    dialog
+        .get_content_area()
+        .pack_start(&scrolled, true, true, 0);
+    dialog.show_all();
+    dialog.run();
+    dialog.close();
+}
+
+/// Activa las descripciones emergentes de las regiones de una gráfica
+///
+/// Devuelve la lista de regiones, que debe actualizarse al dibujar la gráfica
+fn connect_tooltips(da: &gtk::DrawingArea) -> Rc<RefCell<Vec<Region>>> {
+    let regiones: Rc<RefCell<Vec<Region>>> = Rc::new(RefCell::new(Vec::new()));
+    da.set_has_tooltip(true);
+    da.connect_query_tooltip(clone!(@strong regiones => move |_, x, y, _, tooltip| {
+        match find_region(&regiones.borrow(), x as f64, y as f64) {
+            Some(region) => {
+                tooltip.set_text(Some(&region.texto));
+                true
+            }
+            None => false,
+        }
+    }));
+    regiones
+}
+
+/// Tamaño (ancho, alto) de un área de dibujo
+fn widget_size(da: &gtk::DrawingArea) -> (f64, f64) {
+    let rect = da.get_allocation();
+    (rect.width as f64, rect.height as f64)
+}
+
+/// Gráfica de la página activa y su tamaño (ancho, alto) en pantalla
+fn grafica_activa(ui: &gtk::Builder) -> Option<(Grafica, (f64, f64))> {
+    let nb: gtk::Notebook = ui.get_object("notebook").unwrap();
+    let container = nb
+        .get_nth_page(nb.get_current_page())?
+        .downcast::<gtk::Container>()
+        .ok()?;
+    container.get_children().into_iter().find_map(|child| {
+        let da = child.downcast::<gtk::DrawingArea>().ok()?;
+        let grafica = da.get_buildable_name()?.parse::<Grafica>().ok()?;
+        Some((grafica, widget_size(&da)))
+    })
+}
+
 /// Muestra ventana de créditos
 fn show_about(window: &gtk::ApplicationWindow) {
     let builder: gtk::Builder = gtk::Builder::from_file("res/about.ui");